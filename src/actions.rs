@@ -1,5 +1,23 @@
-use std::path::Path;
-use crate::files::{FileEntry, Restriction};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+use crate::{
+    checksum::{self, Algorithm, FileChecksums, VerifyEntry, WrittenManifest},
+    archive::{ArchiveEntry, ArchiveFormat, ArchiveInfo, CreateOptions, ExtractOptions},
+    diff::CompareFiles,
+    duplicates::DuplicateGroup,
+    files::{FileEntry, ListingFilter},
+    job::Job,
+    permissions::PermissionsEditor,
+    properties::Properties,
+    restriction::Restriction,
+    sync::{SyncMode, SyncOperation},
+    tab::TabView,
+    timestamps::TimestampEditor,
+    vfs::Vfs,
+    zip::ZipOptions,
+};
 
 pub trait GetName: Fn(&Vec<&FileEntry>) -> String {}
 impl<F> GetName for F where F: Fn(&Vec<&FileEntry>) -> String {}
@@ -10,26 +28,343 @@ impl<F> CanExecute for F where F: Fn(&Vec<&FileEntry>, bool) -> bool {}
 pub trait Execute: Fn(&FileEntry, &mut ActionState) {}
 impl<F> Execute for F where F: Fn(&FileEntry, &mut ActionState) {}
 
+pub trait ExecuteSelection: Fn(&Vec<&FileEntry>, &mut ActionState) {}
+impl<F> ExecuteSelection for F where F: Fn(&Vec<&FileEntry>, &mut ActionState) {}
+
 #[derive(Default, Debug)]
 pub struct ActionState {
     pub relead: bool,
     pub add_entry: Option<(String, bool)>,
-    pub extract_zip_archive: Option<ExtractZipArchive>,
-    pub zip_dir: Option<ZipDir>,
+    pub extract_archive: Option<ExtractArchive>,
+    pub create_archive: Option<CreateArchive>,
+    pub add_to_archive: Option<AddToArchive>,
+    pub test_archive: Option<TestArchive>,
+    pub checksums: Option<Checksums>,
+    pub create_manifest: Option<CreateManifest>,
+    pub verify_manifest: Option<VerifyManifest>,
     pub renaming: Option<Renaming>,
+    pub duplicates: Option<Duplicates>,
+    pub compare_dirs: Option<CompareRequest>,
+    pub sync: Option<SyncFolders>,
+    pub open_view: Option<TabView>,
+    pub permissions: Option<PermissionsEditor>,
+    pub properties: Option<Properties>,
+    pub timestamps: Option<TimestampEditor>,
+    pub command_output: Option<CommandOutput>,
+    pub navigate: Option<String>,
+    pub create_link: Option<CreateLink>,
+    /// The filter of the tab, so actions that walk folders can honor it.
+    pub filter: ListingFilter,
+    /// Shown above the table until dismissed, e.g. errors of external commands.
+    pub message: Option<String>,
+    pub extract_entries: Option<ExtractEntries>,
+    /// Paths whose changes get thrown away once the user confirms.
+    pub discard_changes: Option<Vec<String>>,
+    /// The provider of the tab, file operations on its entries go through it.
+    pub fs: Vfs,
 }
+
 #[derive(Debug)]
-pub struct ExtractZipArchive {
+pub struct ExtractEntries {
+    pub archive: String,
+    pub entries: Vec<String>,
+    pub target: String,
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct CreateLink {
+    pub source: String,
+    pub link: String,
+    pub symbolic: bool,
+    pub error: Option<String>,
+}
+
+impl CreateLink {
+    pub fn new(source: &FileEntry, symbolic: bool) -> Self {
+        Self {
+            source: source.path.to_string(),
+            link: format!("{} link", source.path),
+            symbolic,
+            error: None,
+        }
+    }
+
+    pub fn create(&self) -> std::io::Result<()> {
+        if self.symbolic {
+            std::os::unix::fs::symlink(&self.source, &self.link)
+        } else {
+            std::fs::hard_link(&self.source, &self.link)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SyncFolders {
     pub source: String,
     pub target: String,
-    pub strip_toplevel: bool,
+    pub mode: SyncMode,
+    pub include: String,
+    pub exclude: String,
+    pub plan: Option<Job<Result<Vec<SyncOperation>, String>>>,
+    pub run: Option<Job<Vec<String>>>,
 }
 
 #[derive(Debug)]
-pub struct ZipDir {
+pub struct CompareRequest {
+    pub left: String,
+    pub right: String,
+    pub content: bool,
+}
+#[derive(Debug)]
+pub struct ExtractArchive {
     pub source: String,
     pub target: String,
-    pub method: zip::CompressionMethod,
+    pub strip_toplevel: bool,
+    pub listing: Result<Vec<ArchiveEntry>, String>,
+    pub encrypted: bool,
+    pub password: String,
+    /// Extract only the checked entries instead of everything.
+    pub choose: bool,
+    pub selected: HashSet<String>,
+    pub job: Option<Job<std::io::Result<Vec<String>>>>,
+}
+
+impl ExtractArchive {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            target: ArchiveFormat::strip_extension(source).unwrap_or(source).to_string(),
+            strip_toplevel: true,
+            listing: crate::archive::list(source).map_err(|e| e.to_string()),
+            encrypted: crate::archive::is_encrypted(source).unwrap_or(false),
+            password: String::new(),
+            choose: false,
+            selected: HashSet::new(),
+            job: None,
+        }
+    }
+
+    pub fn start(&mut self) {
+        let options = ExtractOptions {
+            strip_toplevel: self.strip_toplevel,
+            password: self.encrypted.then(|| self.password.clone()),
+            only: self.choose.then(|| self.selected.clone()),
+        };
+        let (source, target) = (self.source.clone(), self.target.clone());
+        self.job = Some(Job::spawn(move |p| {
+            crate::archive::extract(&source, Path::new(&target), &options, p)
+        }));
+    }
+}
+
+#[derive(Debug)]
+pub struct CreateArchive {
+    /// The folder entry names are relative to.
+    pub base: String,
+    pub sources: Vec<String>,
+    pub target: String,
+    pub options: CreateOptions,
+    pub job: Option<Job<anyhow::Result<()>>>,
+}
+
+impl CreateArchive {
+    pub fn new(entries: &Vec<&FileEntry>, filter: ListingFilter) -> Self {
+        let first = Path::new(&entries[0].path);
+        let base = first.parent().unwrap_or(first).to_str().unwrap_or_default().to_string();
+        let name = if entries.len() == 1 {
+            match entries[0].file_type.is_dir() {
+                true => entries[0].file_name.clone(),
+                false => first.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            }
+        } else {
+            "Archive".to_string()
+        };
+        // like Finder, count up instead of overwriting an existing archive
+        let mut target = format!("{}/{}.zip", base, name);
+        for i in 2.. {
+            if !Path::new(&target).exists() {
+                break;
+            }
+            target = format!("{}/{} {}.zip", base, name, i);
+        }
+        Self {
+            base,
+            sources: entries.iter().map(|e| e.path.clone()).collect(),
+            target,
+            options: CreateOptions {
+                format: ArchiveFormat::Zip,
+                zip: ZipOptions::default(),
+                follow_links: false,
+                filter,
+                exclude: ".DS_Store".to_string(),
+            },
+            job: None,
+        }
+    }
+
+    /// Switches the format, keeping the target name in sync with it.
+    pub fn set_format(&mut self, format: ArchiveFormat) {
+        let base = ArchiveFormat::strip_extension(&self.target).unwrap_or(&self.target);
+        self.target = format!("{}{}", base, format.extension());
+        self.options.format = format;
+    }
+
+    pub fn start(&mut self) {
+        let base = self.base.clone();
+        let sources: Vec<PathBuf> = self.sources.iter().map(PathBuf::from).collect();
+        let target = self.target.clone();
+        let options = self.options.clone();
+        self.job = Some(Job::spawn(move |p| {
+            crate::archive::create(Path::new(&base), &sources, Path::new(&target), &options, p)
+        }));
+    }
+}
+
+#[derive(Debug)]
+pub struct Checksums {
+    pub paths: Vec<String>,
+    pub algorithms: Vec<Algorithm>,
+    /// A hash pasted by the user to compare the results against.
+    pub expected: String,
+    pub job: Job<Vec<FileChecksums>>,
+}
+
+impl Checksums {
+    pub fn new(paths: Vec<String>) -> Self {
+        let algorithms = vec![Algorithm::Sha256];
+        Self {
+            job: Self::spawn(&paths, &algorithms),
+            paths,
+            algorithms,
+            expected: String::new(),
+        }
+    }
+
+    fn spawn(paths: &[String], algorithms: &[Algorithm]) -> Job<Vec<FileChecksums>> {
+        let (paths, algorithms) = (paths.to_vec(), algorithms.to_vec());
+        Job::spawn(move |p| checksum::hash_files(&paths, &algorithms, p))
+    }
+
+    pub fn rerun(&mut self) {
+        self.job.cancel();
+        self.job = Self::spawn(&self.paths, &self.algorithms);
+    }
+}
+
+#[derive(Debug)]
+pub struct CreateManifest {
+    pub dir: String,
+    pub algorithm: Algorithm,
+    pub filter: ListingFilter,
+    pub job: Option<Job<std::io::Result<WrittenManifest>>>,
+}
+
+impl CreateManifest {
+    pub fn start(&mut self) {
+        let (dir, algorithm, filter) = (self.dir.clone(), self.algorithm, self.filter);
+        self.job = Some(Job::spawn(move |p| {
+            checksum::write_manifest(Path::new(&dir), algorithm, filter, p)
+        }));
+    }
+}
+
+#[derive(Debug)]
+pub struct VerifyManifest {
+    pub manifest: String,
+    pub job: Job<std::io::Result<Vec<VerifyEntry>>>,
+}
+
+impl VerifyManifest {
+    pub fn new(manifest: &str) -> Self {
+        let job_manifest = manifest.to_string();
+        Self {
+            manifest: manifest.to_string(),
+            job: Job::spawn(move |p| checksum::verify_manifest(Path::new(&job_manifest), p)),
+        }
+    }
+}
+
+/// Commands run one after another in the background, their outputs are shown once all exited.
+#[derive(Debug)]
+pub struct CommandOutput {
+    pub name: String,
+    /// The label of every command with what it printed.
+    pub job: Job<Vec<(String, Result<std::process::Output, String>)>>,
+}
+
+impl CommandOutput {
+    /// `commands` come with a label telling them apart, e.g. the entry they run for.
+    pub fn new(name: &str, commands: Vec<(String, std::process::Command)>) -> Self {
+        let job_name = name.to_string();
+        Self {
+            name: name.to_string(),
+            job: Job::spawn(move |p| {
+                let mut outputs = vec![];
+                for (label, mut command) in commands {
+                    if p.is_cancelled() {
+                        break;
+                    }
+                    p.set_message(&label);
+                    let output = command.output().map_err(|e| format!("could not run {}: {}", job_name, e));
+                    outputs.push((label, output));
+                }
+                outputs
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TestArchive {
+    pub archive: String,
+    pub job: Job<std::io::Result<Vec<String>>>,
+}
+
+impl TestArchive {
+    pub fn new(archive: &str) -> Self {
+        let job_archive = archive.to_string();
+        Self {
+            archive: archive.to_string(),
+            job: Job::spawn(move |p| crate::archive::test(&job_archive, p)),
+        }
+    }
+}
+
+/// Adds files from disk into an existing zip archive.
+#[derive(Debug)]
+pub struct AddToArchive {
+    pub sources: Vec<String>,
+    /// The archive, optionally followed by a folder inside it like `/a/b.zip/docs`.
+    pub target: String,
+    pub follow_links: bool,
+    pub filter: ListingFilter,
+    pub job: Option<Job<anyhow::Result<()>>>,
+}
+
+impl AddToArchive {
+    pub fn new(sources: Vec<String>, target: String, filter: ListingFilter) -> Self {
+        Self {
+            sources,
+            target,
+            follow_links: false,
+            filter,
+            job: None,
+        }
+    }
+
+    pub fn start(&mut self) {
+        let sources: Vec<PathBuf> = self.sources.iter().map(PathBuf::from).collect();
+        let target = crate::archive::split_path(&self.target)
+            .filter(|(archive, _)| ArchiveFormat::of(archive) == Some(ArchiveFormat::Zip));
+        let (follow_links, filter) = (self.follow_links, self.filter);
+        self.job = Some(Job::spawn(move |p| {
+            let Some((archive, inner)) = target else {
+                anyhow::bail!("not an existing zip archive");
+            };
+            crate::zip::add_paths(Path::new(&archive), &inner, &sources, follow_links, filter, p)
+        }));
+    }
 }
 
 #[derive(Debug)]
@@ -39,10 +374,37 @@ pub struct Renaming {
     pub duplicate: bool,
 }
 
+#[derive(Debug)]
+pub struct Duplicates {
+    pub roots: Vec<String>,
+    pub job: Job<Vec<DuplicateGroup>>,
+    pub message: Option<String>,
+    /// Trashing was asked for and waits for a second click.
+    pub confirm_trash: bool,
+}
+
+impl Duplicates {
+    pub fn new(roots: Vec<String>) -> Self {
+        let job_roots = roots.clone();
+        Self {
+            roots,
+            job: Job::spawn(move |p| crate::duplicates::find_duplicates(&job_roots, p)),
+            message: None,
+            confirm_trash: false,
+        }
+    }
+}
+
 pub struct Action {
     pub name: Box<dyn GetName>,
     pub can_execute: Box<dyn CanExecute>,
-    pub execute: Box<dyn Execute>,
+    pub execute: Box<dyn ExecuteSelection>,
+    /// Whether the action also makes sense for entries inside an archive.
+    pub read_only: bool,
+    /// Whether the action goes through the tab's filesystem and so works inside zip archives.
+    pub edits_zip: bool,
+    /// Whether the action needs the entries to be inside the tab's git repository.
+    pub needs_git: bool,
 }
 
 impl Action {
@@ -50,22 +412,65 @@ impl Action {
         name: impl GetName + 'static,
         can_execute: impl CanExecute + 'static,
         execute: impl Execute + 'static,
+    ) -> Self {
+        return Self::for_selection(name, can_execute, move |entries, s| {
+            for entry in entries.iter() {
+                execute(entry, s);
+            }
+        });
+    }
+
+    /// Like `new`, but `execute` is called once with the whole selection.
+    pub fn for_selection(
+        name: impl GetName + 'static,
+        can_execute: impl CanExecute + 'static,
+        execute: impl ExecuteSelection + 'static,
     ) -> Self {
         Self {
             name: Box::new(name),
             can_execute: Box::new(can_execute),
             execute: Box::new(execute),
+            read_only: false,
+            edits_zip: false,
+            needs_git: false,
         }
     }
 
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    pub fn edits_zip(mut self) -> Self {
+        self.edits_zip = true;
+        self
+    }
+
+    pub fn needs_git(mut self) -> Self {
+        self.needs_git = true;
+        self
+    }
+
+    /// `git_root` is the repository of the tab, known from its cached git status.
+    pub fn is_available(&self, entries: &Vec<&FileEntry>, is_main: bool, git_root: Option<&Path>) -> bool {
+        let in_archive = entries.iter().any(|e| e.archive.is_some());
+        let in_zip = entries.iter().all(|e| {
+            e.archive.as_deref().and_then(ArchiveFormat::of) == Some(ArchiveFormat::Zip)
+        });
+        let in_git = git_root.is_some_and(|root| entries.iter().all(|e| Path::new(&e.path).starts_with(root)));
+        (self.read_only || !in_archive || (self.edits_zip && in_zip))
+            && (!self.needs_git || in_git)
+            && (self.can_execute)(entries, is_main)
+    }
+
     pub fn constant(
         display_name: &'static str,
         restriction: Restriction,
         execute: impl Execute + 'static,
     ) -> Self {
         return Self::new(
-            |e| display_name.to_string(),
-            move |e, b| e.iter().all(|e| e.fullfills(&restriction, b)),
+            |_| display_name.to_string(),
+            move |e, b| restriction.matches(e, b),
             execute,
         );
     }
@@ -76,14 +481,12 @@ impl Action {
         restriction: Restriction,
     ) -> Self {
         return Self::new(
-            |e| display_name.to_string(),
-            move |e, b| e.iter().all(|e| e.fullfills(&restriction, b)),
+            |_| display_name.to_string(),
+            move |e, b| restriction.matches(e, b),
             move |e, s| {
-                let _ = std::process::Command::new("open")
-                    .arg("-a")
-                    .arg(app_name)
-                    .arg(&e.path)
-                    .status();
+                if let Err(err) = crate::launcher::open_with(app_name, &e.path) {
+                    s.message = Some(format!("could not open {} with {}: {}", e.file_name, app_name, err));
+                }
             },
         );
     }
@@ -92,21 +495,12 @@ impl Action {
 pub fn actions() -> Vec<Action> {
     let mut actions = vec![];
 
-    /*
-    if ui.button("make executable").clicked() {
-                                       std::process::Command::new("chmod")
-                                           .arg("755")
-                                           .arg(entry.path.clone())
-                                           .status();
-                                       ui.close_menu();
-                                   }
-    */
-    actions.push(Action::constant("add file", Restriction::Main, |e, s| {
+    actions.push(Action::constant("add file", Restriction::Main, |_, s| {
         s.add_entry = Some(("".into(), false));
-    }));
-    actions.push(Action::constant("add dir", Restriction::Main, |e, s| {
+    }).edits_zip());
+    actions.push(Action::constant("add dir", Restriction::Main, |_, s| {
         s.add_entry = Some(("".into(), true));
-    }));
+    }).edits_zip());
     actions.push(Action::constant(
         "rename",
         Restriction::Not(Box::new(Restriction::Main)),
@@ -117,7 +511,7 @@ pub fn actions() -> Vec<Action> {
                 duplicate: false,
             });
         },
-    ));
+    ).edits_zip());
     actions.push(Action::constant(
         "duplicate",
         Restriction::Not(Box::new(Restriction::Main)),
@@ -135,43 +529,250 @@ pub fn actions() -> Vec<Action> {
         },
     ));
     actions.push(Action::new(
-        |e| format!("extract zip archive"),
-        |e, m| !m && e.len() == 1 && e[0].file_type.is_file() && e[0].file_name.ends_with(".zip"),
+        |_| format!("extract archive"),
+        |e, m| !m && e.len() == 1 && e[0].file_type.is_file() && ArchiveFormat::of(&e[0].file_name).is_some(),
+        |e, s| {
+            s.extract_archive = Some(ExtractArchive::new(&e.path))
+        },
+    ));
+    actions.push(Action::new(
+        |_| format!("test archive"),
+        |e, m| !m && e.len() == 1 && e[0].file_type.is_file() && ArchiveFormat::of(&e[0].file_name).is_some(),
         |e, s| {
-            s.extract_zip_archive = Some(ExtractZipArchive {
-                source: e.path.to_string(),
-                target: e.path[..e.path.len() - 4].to_string(),
-                strip_toplevel: true,
-            })
+            s.test_archive = Some(TestArchive::new(&e.path));
         },
     ));
     actions.push(Action::new(
-        |e| format!("create zip archive"),
-        |e, m| !m && e.len() == 1 && e[0].file_type.is_dir(),
+        |_| format!("archive info"),
+        |e, m| !m && e.len() == 1 && e[0].file_type.is_file() && ArchiveFormat::of(&e[0].file_name).is_some(),
+        |e, s| {
+            s.open_view = Some(TabView::ArchiveInfo(ArchiveInfo::new(&e.path)));
+        },
+    ));
+    actions.push(Action::for_selection(
+        |e| match e.len() {
+            1 => format!("compress \"{}\"", e[0].file_name),
+            n => format!("compress {} items", n),
+        },
+        |e, m| !m && !e.is_empty(),
+        |e, s| {
+            s.create_archive = Some(CreateArchive::new(e, s.filter));
+        },
+    ));
+    actions.push(Action::for_selection(
+        |_| format!("add to zip archive…"),
+        |e, m| !m && !e.is_empty(),
         |e, s| {
-            s.zip_dir = Some(ZipDir {
-                source: e.path.to_string(),
-                target: format!("{}.zip", e.path),
-                method: zip::CompressionMethod::Deflated,
-            })
+            let parent = Path::new(&e[0].path).parent().and_then(|p| p.to_str()).unwrap_or("/");
+            s.add_to_archive = Some(AddToArchive::new(
+                e.iter().map(|e| e.path.clone()).collect(),
+                format!("{}/", parent.trim_end_matches('/')),
+                s.filter,
+            ));
         },
     ));
+    actions.push(Action::for_selection(
+        |_| format!("checksums"),
+        |e, m| !m && !e.is_empty() && e.iter().all(|e| e.file_type.is_file()),
+        |e, s| {
+            s.checksums = Some(Checksums::new(e.iter().map(|e| e.path.clone()).collect()));
+        },
+    ));
+    actions.push(Action::constant("create checksum manifest", Restriction::Folder, |e, s| {
+        s.create_manifest = Some(CreateManifest {
+            dir: e.path.clone(),
+            algorithm: Algorithm::Sha256,
+            filter: s.filter,
+            job: None,
+        });
+    }));
+    actions.push(Action::new(
+        |_| format!("verify checksums"),
+        |e, m| !m && e.len() == 1 && e[0].file_type.is_file() && Algorithm::of_manifest(&e[0].file_name).is_some(),
+        |e, s| {
+            s.verify_manifest = Some(VerifyManifest::new(&e.path));
+        },
+    ));
+    actions.push(Action::for_selection(
+        |_| format!("find duplicates"),
+        |e, m| Restriction::Folder.matches(e, m),
+        |e, s| {
+            s.duplicates = Some(Duplicates::new(
+                e.iter().map(|e| e.path.to_string()).collect(),
+            ));
+        },
+    ));
+    actions.push(Action::for_selection(
+        |_| format!("compare folders"),
+        |e, m| e.len() <= 2 && Restriction::Folder.matches(e, m),
+        |e, s| {
+            s.compare_dirs = Some(CompareRequest {
+                left: e[0].path.to_string(),
+                right: e.get(1).map(|e| e.path.to_string()).unwrap_or_default(),
+                content: false,
+            });
+        },
+    ));
+    actions.push(Action::for_selection(
+        |_| format!("synchronize folders"),
+        |e, m| e.len() <= 2 && Restriction::Folder.matches(e, m),
+        |e, s| {
+            s.sync = Some(SyncFolders {
+                source: e[0].path.to_string(),
+                target: e.get(1).map(|e| e.path.to_string()).unwrap_or_default(),
+                mode: SyncMode::Update,
+                include: "".into(),
+                exclude: ".DS_Store, .git".into(),
+                plan: None,
+                run: None,
+            });
+        },
+    ));
+    actions.push(Action::for_selection(
+        |_| format!("compare files"),
+        |e, m| !m && e.len() == 2 && e.iter().all(|e| e.file_type.is_file()),
+        |e, s| {
+            s.open_view = Some(TabView::Diff(CompareFiles::new(
+                e[0].path.to_string(),
+                e[1].path.to_string(),
+            )));
+        },
+    ));
+    actions.push(
+        Action::for_selection(
+            |_| format!("extract to…"),
+            |e, m| !m && e.iter().all(|e| e.archive.is_some()),
+            |e, s| {
+                let archive = e[0].archive.clone().unwrap_or_default();
+                s.extract_entries = Some(ExtractEntries {
+                    target: Path::new(&archive)
+                        .parent()
+                        .and_then(|p| p.to_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    entries: e
+                        .iter()
+                        .filter_map(|e| crate::archive::split_path(&e.path).map(|(_, inner)| inner))
+                        .collect(),
+                    archive,
+                    error: None,
+                });
+            },
+        )
+        .read_only(),
+    );
     actions.push(Action::new(
         |e| format!("copy {}", e[0].file_name),
         |e, m| !m && e.len() == 1,
-        |e, s| {
+        |e, _| {
             let mut ctx: clipboard::ClipboardContext = clipboard::ClipboardProvider::new().unwrap();
             clipboard::ClipboardProvider::set_contents(&mut ctx, e.file_name.to_string()).unwrap();
         },
-    ));
+    ).read_only());
     actions.push(Action::new(
-        |e| format!("copy path"),
+        |_| format!("copy path"),
         |e, m| !m && e.len() == 1,
-        |e, s| {
+        |e, _| {
             let mut ctx: clipboard::ClipboardContext = clipboard::ClipboardProvider::new().unwrap();
             clipboard::ClipboardProvider::set_contents(&mut ctx, e.path.to_string()).unwrap();
         },
+    ).read_only());
+    actions.push(Action::new(
+        |_| format!("go to link target"),
+        |e, m| !m && e.len() == 1 && e[0].is_symlink(),
+        |e, s| {
+            let Some(target) = e.resolved_target() else {
+                return;
+            };
+            let folder = if target.is_dir() {
+                Some(target.as_path())
+            } else {
+                target.parent()
+            };
+            s.navigate = folder.map(|f| f.to_str().unwrap_or_default().to_string());
+        },
+    ));
+    actions.push(Action::new(
+        |_| format!("create symbolic link"),
+        |e, m| !m && e.len() == 1,
+        |e, s| {
+            s.create_link = Some(CreateLink::new(e, true));
+        },
+    ));
+    actions.push(Action::new(
+        |_| format!("create hard link"),
+        |e, m| !m && e.len() == 1 && e[0].file_type.is_file(),
+        |e, s| {
+            s.create_link = Some(CreateLink::new(e, false));
+        },
+    ));
+    let git_action = |name: &'static str, run: fn(&[String]) -> Result<(), String>| {
+        Action::for_selection(
+            move |_| name.to_string(),
+            |_, m| !m,
+            move |e, s| {
+                let paths: Vec<_> = e.iter().map(|e| e.path.to_string()).collect();
+                match run(&paths) {
+                    Ok(_) => s.relead = true,
+                    Err(e) => s.message = Some(e),
+                }
+            },
+        )
+        .needs_git()
+    };
+    actions.push(git_action("git stage", crate::git::stage));
+    actions.push(git_action("git unstage", crate::git::unstage));
+    actions.push(
+        Action::for_selection(
+            |_| format!("git discard changes"),
+            |_, m| !m,
+            |e, s| {
+                s.discard_changes = Some(e.iter().map(|e| e.path.to_string()).collect());
+            },
+        )
+        .needs_git(),
+    );
+    actions.push(Action::for_selection(
+        |_| format!("permissions"),
+        |_, _| true,
+        |e, s| {
+            s.permissions = Some(PermissionsEditor::new(
+                e.iter().map(|e| e.path.to_string()).collect(),
+            ));
+        },
+    ));
+    actions.push(Action::for_selection(
+        |_| format!("change timestamps"),
+        |_, m| !m,
+        |e, s| {
+            s.timestamps = Some(TimestampEditor::new(e.iter().map(|e| e.path.to_string()).collect()));
+        },
+    ));
+    actions.push(Action::for_selection(
+        |_| format!("properties"),
+        |_, _| true,
+        |e, s| {
+            s.properties = Some(Properties::new(e.iter().map(|e| e.path.to_string()).collect()));
+        },
+    ));
+    actions.push(Action::constant(
+        "make executable",
+        Restriction::And(
+            Box::new(Restriction::File),
+            Box::new(Restriction::Not(Box::new(Restriction::Main))),
+        ),
+        |e, s| {
+            if let Err(err) = crate::permissions::make_executable(&e.path) {
+                s.message = Some(format!("could not make {} executable: {}", e.file_name, err));
+            }
+            s.relead = true;
+        },
     ));
+    actions.push(Action::constant("open", Restriction::File, |e, s| {
+        if let Err(err) = crate::launcher::open_as(&e.path, e.mime()) {
+            s.message = Some(format!("could not open {}: {}", e.file_name, err));
+        }
+    }));
     actions.push(Action::open_with(
         "Visual Studio Code",
         "vscode",
@@ -193,14 +794,11 @@ pub fn actions() -> Vec<Action> {
         "delete",
         Restriction::Not(Box::new(Restriction::Main)),
         |e, s| {
-            if e.file_type.is_file() {
-                let _ = std::fs::remove_file(e.path.clone());
-            }
-            if e.file_type.is_dir() {
-                let _ = std::fs::remove_dir_all(e.path.clone());
+            if let Err(err) = s.fs.remove(&e.path) {
+                s.message = Some(format!("could not delete {}: {}", e.file_name, err));
             }
             s.relead = true;
         },
-    ));
+    ).edits_zip());
     return actions;
 }
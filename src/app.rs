@@ -1,10 +1,16 @@
-use std::{fs, path::Path};
+use std::path::Path;
 
 use egui::{Label, Sense, Widget};
 use egui_dock::{DockArea, DockState, NodeIndex, Style, SurfaceIndex};
-use serde::de;
 
-use crate::{actions::actions, files, tab::Tab, tabviewer::AppData};
+use crate::{
+    actions::AddToArchive,
+    archive::{self, ArchiveFormat},
+    files::{IgnoreMode, ListingFilter},
+    tab::Tab,
+    tabviewer::AppData,
+    vfs,
+};
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)]
@@ -41,7 +47,7 @@ impl App {
         //app.tabs.set_active_tab((SurfaceIndex(0), NodeIndex(0), TabIndex(0)));
         app.tabs
             .set_focused_node_and_surface((SurfaceIndex(0), NodeIndex(0)));
-        app.data.actions = actions();
+        app.data.reload_actions();
         return app;
     }
 }
@@ -77,7 +83,7 @@ impl eframe::App for App {
                         .ui(ui);
                     if resp.clicked() {
                         let tab = self.tabs.find_active_focused();
-                        if let Some((rect, tab)) = tab {
+                        if let Some((_, tab)) = tab {
                             tab.refresh(favorite);
                         }
                     }
@@ -96,9 +102,39 @@ impl eframe::App for App {
                 if let Some(to_remove) = to_remove {
                     self.data.favorites.remove(to_remove);
                 }
+                ui.separator();
+                ui.checkbox(&mut self.data.follow_symlinks, "follow symlinks")
+                    .on_hover_text("copy and move what links point to instead of the links");
+                if ui
+                    .checkbox(&mut self.data.embedded_icons, "built-in icons")
+                    .on_hover_text("use the icons of the app instead of the system icon theme")
+                    .changed()
+                {
+                    self.data.icons.clear();
+                }
+                let config = crate::custom_actions::config_path();
+                if ui
+                    .button("reload actions")
+                    .on_hover_text(format!(
+                        "custom actions are read from {}",
+                        config.map(|p| p.display().to_string()).unwrap_or_default()
+                    ))
+                    .clicked()
+                {
+                    self.data.reload_actions();
+                }
+                if let Some(error) = &self.data.actions_error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
             });
         });
-        egui::CentralPanel::default().show(ctx, |ui| {
+        self.data.focused_tab = self.tabs.find_active_focused().map(|(_, tab)| tab.id);
+        self.data.open_paths = self
+            .tabs
+            .iter_all_tabs()
+            .map(|(_, tab)| tab.path.clone())
+            .collect();
+        egui::CentralPanel::default().show(ctx, |_| {
             DockArea::new(&mut self.tabs)
                 .show_add_buttons(true)
                 .style({
@@ -116,31 +152,67 @@ impl eframe::App for App {
                     egui::Id::new(self.latest_tab_id),
                 ));
             });
+            for (path, view) in self.data.new_views.drain(..) {
+                self.latest_tab_id += 1;
+                self.tabs.push_to_focused_leaf(Tab::with_view(
+                    path,
+                    view,
+                    egui::Id::new(self.latest_tab_id),
+                ));
+            }
         });
 
         if let Some((source_path, files)) = &self.data.drag_paths {
             if let Some(dest_path) = &self.data.drop_path {
-                if source_path != dest_path {
+                let mut errors = vec![];
+                let mut add_to_zip = None;
+                let into_zip = archive::split_path(dest_path)
+                    .is_some_and(|(archive, _)| ArchiveFormat::of(&archive) == Some(ArchiveFormat::Zip));
+                if into_zip && source_path != dest_path {
+                    // files from disk are added to zip archives, entries of other archives are skipped
+                    let sources: Vec<String> = files
+                        .iter()
+                        .filter(|(path, _)| archive::split_path(path).is_none())
+                        .map(|(path, _)| path.clone())
+                        .collect();
+                    let filter = ListingFilter {
+                        show_hidden: true,
+                        ignored: IgnoreMode::Show,
+                    };
+                    let mut add = AddToArchive::new(sources, dest_path.clone(), filter);
+                    add.follow_links = self.data.follow_symlinks;
+                    add.start();
+                    add_to_zip = Some(add);
+                } else if source_path != dest_path && !vfs::for_path(dest_path).is_read_only() {
+                    // tar archives are read-only, entries can only be dragged out of them
                     let command = ctx.input(|i| i.modifiers.command);
+                    let to_fs = vfs::for_path(dest_path);
                     for (path, file_name) in files.iter() {
-                        let target = Path::new(dest_path).join(file_name);
-                        if command {
-                            if Path::new(path).is_dir() {
-                                let _ = files::copy_dir(Path::new(path), &target);
-                            } else {
-                                let _ = fs::copy(path, Path::new(dest_path).join(file_name));
-                            }
-                            
-                        } else {
-                            let _ = fs::rename(path, Path::new(dest_path).join(file_name));
+                        let from_fs = vfs::for_entry(path);
+                        // entries dragged out of an archive are copied, the archive stays as it is
+                        let keep_source = command || !from_fs.is_local();
+                        let target = vfs::join(dest_path, file_name);
+                        let result = vfs::transfer(&from_fs, path, &to_fs, &target, keep_source, self.data.follow_symlinks);
+                        if let Err(e) = result {
+                            errors.push(format!("{}: {}", file_name, e));
                         }
                     }
                 }
                 for ((_, _), tab) in self.tabs.iter_all_tabs_mut() {
                     tab.refresh_hard(tab.path.clone());
                 }
+                // the tab that received the drop shows the progress and what went wrong
+                if let Some((_, tab)) = self.tabs.iter_all_tabs_mut().find(|(_, tab)| Some(tab.id) == self.data.drop_tab) {
+                    if !errors.is_empty() {
+                        tab.state.message = Some(format!("could not drop {}", errors.join(", ")));
+                    }
+                    if let Some(add) = add_to_zip {
+                        tab.state.add_to_archive = Some(add);
+                    }
+                }
                 self.data.drag_paths = None;
                 self.data.drop_path = None;
+                self.data.drop_tab = None;
             }
         }
     }
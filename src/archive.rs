@@ -0,0 +1,1135 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Component, Path, PathBuf},
+    sync::OnceLock,
+};
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+
+use crate::{
+    files::{walk_sources, FileEntry, FileType, GlobFilter, ListingFilter},
+    job::{Job, Progress},
+    zip::ZipOptions,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+    TarBz2,
+}
+
+/// Recognized file name endings, the first one of each format is used for new archives.
+const EXTENSIONS: &[(&str, ArchiveFormat)] = &[
+    (".zip", ArchiveFormat::Zip),
+    (".tar", ArchiveFormat::Tar),
+    (".tar.gz", ArchiveFormat::TarGz),
+    (".tgz", ArchiveFormat::TarGz),
+    (".tar.xz", ArchiveFormat::TarXz),
+    (".txz", ArchiveFormat::TarXz),
+    (".tar.zst", ArchiveFormat::TarZst),
+    (".tzst", ArchiveFormat::TarZst),
+    (".tar.bz2", ArchiveFormat::TarBz2),
+    (".tbz2", ArchiveFormat::TarBz2),
+];
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 6] = [
+        ArchiveFormat::Zip,
+        ArchiveFormat::Tar,
+        ArchiveFormat::TarGz,
+        ArchiveFormat::TarXz,
+        ArchiveFormat::TarZst,
+        ArchiveFormat::TarBz2,
+    ];
+
+    pub fn of(file_name: &str) -> Option<Self> {
+        let name = file_name.to_lowercase();
+        EXTENSIONS
+            .iter()
+            .find(|(ext, _)| name.ends_with(ext))
+            .map(|(_, format)| *format)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        EXTENSIONS.iter().find(|(_, f)| f == self).unwrap().0
+    }
+
+    /// `path` without its archive extension, e.g. the folder to extract into.
+    pub fn strip_extension(path: &str) -> Option<&str> {
+        let name = path.to_lowercase();
+        let (ext, _) = EXTENSIONS.iter().find(|(ext, _)| name.ends_with(ext))?;
+        Some(&path[..path.len() - ext.len()])
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    /// Path inside the archive, without leading or trailing slashes.
+    pub path: String,
+    pub file_type: FileType,
+    pub len: u64,
+    pub modified: DateTime<Utc>,
+    pub symlink_target: Option<String>,
+}
+
+/// Splits a path like `/a/b.zip/c/d` into the archive on disk and the path inside it.
+pub fn split_path(path: &str) -> Option<(String, String)> {
+    let path = Path::new(path);
+    for archive in path.ancestors() {
+        let name = archive.file_name()?.to_str()?;
+        if ArchiveFormat::of(name).is_some() && archive.is_file() {
+            let inner = path.strip_prefix(archive).ok()?;
+            return Some((
+                archive.to_str()?.to_string(),
+                inner.to_str()?.trim_matches('/').to_string(),
+            ));
+        }
+    }
+    None
+}
+
+fn tar_reader(archive: &str, format: ArchiveFormat) -> io::Result<Box<dyn Read>> {
+    decompress(File::open(archive)?, format)
+}
+
+fn decompress<'a>(file: impl Read + 'a, format: ArchiveFormat) -> io::Result<Box<dyn Read + 'a>> {
+    let file = BufReader::new(file);
+    Ok(match format {
+        ArchiveFormat::TarGz => Box::new(flate2::read::GzDecoder::new(file)),
+        ArchiveFormat::TarXz => Box::new(xz2::read::XzDecoder::new(file)),
+        ArchiveFormat::TarZst => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+        ArchiveFormat::TarBz2 => Box::new(bzip2::read::BzDecoder::new(file)),
+        _ => Box::new(file),
+    })
+}
+
+/// Zip times have no zone, they are local time like `crate::zip` writes them.
+fn zip_time(time: Option<zip::DateTime>) -> DateTime<Utc> {
+    time.and_then(|t| {
+        let naive = NaiveDate::from_ymd_opt(t.year().into(), t.month().into(), t.day().into())?
+            .and_hms_opt(t.hour().into(), t.minute().into(), t.second().into())?;
+        Local.from_local_datetime(&naive).earliest()
+    })
+    .map(|t| t.to_utc())
+    .unwrap_or_default()
+}
+
+fn clean(path: &str) -> String {
+    path.trim_start_matches("./").trim_matches('/').to_string()
+}
+
+fn list_zip(archive: &str) -> io::Result<Vec<ArchiveEntry>> {
+    let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?))?;
+    let mut entries = vec![];
+    for i in 0..zip.len() {
+        let mut file = zip.by_index_raw(i)?;
+        let file_type = if file.is_dir() {
+            FileType::Dir
+        } else if file.is_symlink() {
+            FileType::Symlink
+        } else {
+            FileType::File
+        };
+        let mut symlink_target = None;
+        if file_type.is_symlink() {
+            let mut target = String::new();
+            // raw access only works for stored links, compressed ones just don't show a target
+            if file.compression() == zip::CompressionMethod::Stored {
+                file.read_to_string(&mut target)?;
+                symlink_target = Some(target);
+            }
+        }
+        entries.push(ArchiveEntry {
+            path: clean(file.name()),
+            file_type,
+            len: file.size(),
+            modified: zip_time(file.last_modified()),
+            symlink_target,
+        });
+    }
+    Ok(entries)
+}
+
+fn list_tar(archive: &str, format: ArchiveFormat) -> io::Result<Vec<ArchiveEntry>> {
+    let mut tar = tar::Archive::new(tar_reader(archive, format)?);
+    let mut entries = vec![];
+    for entry in tar.entries()? {
+        let entry = entry?;
+        let header = entry.header();
+        let entry_type = header.entry_type();
+        let file_type = if entry_type.is_dir() {
+            FileType::Dir
+        } else if entry_type.is_symlink() {
+            FileType::Symlink
+        } else {
+            FileType::File
+        };
+        entries.push(ArchiveEntry {
+            path: clean(entry.path()?.to_str().unwrap_or_default()),
+            file_type,
+            len: header.size()?,
+            modified: DateTime::from_timestamp(header.mtime()? as i64, 0).unwrap_or_default(),
+            symlink_target: entry
+                .link_name()?
+                .map(|l| l.to_str().unwrap_or_default().to_string()),
+        });
+    }
+    Ok(entries)
+}
+
+/// Lists every entry, adding folders that are only implied by the paths of their contents.
+pub fn list(archive: &str) -> io::Result<Vec<ArchiveEntry>> {
+    let format = format_of(archive)?;
+    let entries = match format {
+        ArchiveFormat::Zip => list_zip(archive)?,
+        _ => list_tar(archive, format)?,
+    };
+    let modified = fs::metadata(archive)?.modified()?.into();
+    let mut all: BTreeMap<String, ArchiveEntry> = BTreeMap::new();
+    for entry in entries {
+        for parent in Path::new(&entry.path).ancestors().skip(1) {
+            let parent = parent.to_str().unwrap_or_default();
+            if parent.is_empty() || all.contains_key(parent) {
+                continue;
+            }
+            all.insert(
+                parent.to_string(),
+                ArchiveEntry {
+                    path: parent.to_string(),
+                    file_type: FileType::Dir,
+                    len: 0,
+                    modified,
+                    symlink_target: None,
+                },
+            );
+        }
+        if !entry.path.is_empty() {
+            all.insert(entry.path.clone(), entry);
+        }
+    }
+    Ok(all.into_values().collect())
+}
+
+fn to_file_entry(archive: &str, entry: &ArchiveEntry) -> FileEntry {
+    FileEntry {
+        len: entry.len,
+        file_type: entry.file_type,
+        created: entry.modified,
+        modified: entry.modified,
+        accessed: entry.modified,
+        path: format!("{}/{}", archive, entry.path),
+        file_name: entry.path.rsplit('/').next().unwrap_or_default().to_string(),
+        symlink_target: entry.symlink_target.clone(),
+        ignored: false,
+        archive: Some(archive.to_string()),
+        mime: OnceLock::new(),
+        sniffed_mime: OnceLock::new(),
+    }
+}
+
+/// The entry for the folder or file `inner`, the archive itself for an empty path.
+pub fn stat(archive: &str, all: &[ArchiveEntry], inner: &str) -> io::Result<FileEntry> {
+    if inner.is_empty() {
+        let mut info = crate::files::get_meta(archive)?;
+        info.file_type = FileType::Dir;
+        info.archive = Some(archive.to_string());
+        return Ok(info);
+    }
+    all.iter()
+        .find(|e| e.path == inner)
+        .map(|e| to_file_entry(archive, e))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such entry in archive"))
+}
+
+/// The direct children of the folder `inner`.
+pub fn children(archive: &str, all: &[ArchiveEntry], inner: &str) -> Vec<FileEntry> {
+    all.iter()
+        .filter(|e| Path::new(&e.path).parent().unwrap_or(Path::new("")) == Path::new(inner))
+        .map(|e| to_file_entry(archive, e))
+        .collect()
+}
+
+pub fn read_entry(archive: &str, inner: &str) -> io::Result<Vec<u8>> {
+    let format = format_of(archive)?;
+    let mut content = vec![];
+    if format == ArchiveFormat::Zip {
+        let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?))?;
+        let index = (0..zip.len())
+            .find(|i| zip.name_for_index(*i).map(clean).as_deref() == Some(inner))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such entry in archive"))?;
+        zip.by_index(index)?.read_to_end(&mut content)?;
+        return Ok(content);
+    }
+    let mut tar = tar::Archive::new(tar_reader(archive, format)?);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if clean(entry.path()?.to_str().unwrap_or_default()) == inner {
+            entry.read_to_end(&mut content)?;
+            return Ok(content);
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, "no such entry in archive"))
+}
+
+/// Rejects paths that would escape the folder they are extracted to.
+pub fn safe_relative(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        .then(|| path.to_path_buf())
+}
+
+fn escape_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Creates the folders of `relative` below `target_dir`. Symlinks on the way are refused,
+/// an earlier entry could have pointed one anywhere.
+fn create_dirs_in(target_dir: &Path, relative: &Path) -> io::Result<()> {
+    let mut path = target_dir.to_path_buf();
+    for component in relative.components().filter(|c| *c != Component::CurDir) {
+        path.push(component);
+        match fs::symlink_metadata(&path) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(escape_error("path goes through a symbolic link"));
+            }
+            Ok(meta) if meta.is_dir() => {}
+            Ok(_) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, "not a folder")),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => fs::create_dir(&path)?,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Makes way for the entry at `relative` and returns where to write it. A symlink
+/// already there is removed instead of being written through.
+fn prepare_target(target_dir: &Path, relative: &Path) -> io::Result<PathBuf> {
+    if let Some(parent) = relative.parent() {
+        create_dirs_in(target_dir, parent)?;
+    }
+    let target = target_dir.join(relative);
+    if fs::symlink_metadata(&target).is_ok_and(|m| m.file_type().is_symlink()) {
+        fs::remove_file(&target)?;
+    }
+    Ok(target)
+}
+
+/// Whether a symlink at `relative` pointing to `link` resolves to somewhere below the
+/// folder `relative` is in. Only relative links that never climb out of it are accepted.
+fn link_stays_inside(relative: &Path, link: &Path) -> bool {
+    let parent = relative.parent().unwrap_or(Path::new(""));
+    let mut depth = parent.components().filter(|c| matches!(c, Component::Normal(_))).count();
+    for component in link.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Checks that `relative` exists below `target_dir` without going through a symlink.
+fn check_no_links_in(target_dir: &Path, relative: &Path) -> io::Result<()> {
+    let mut path = target_dir.to_path_buf();
+    for component in relative.components().filter(|c| *c != Component::CurDir) {
+        path.push(component);
+        if fs::symlink_metadata(&path)?.file_type().is_symlink() {
+            return Err(escape_error("link goes through a symbolic link"));
+        }
+    }
+    Ok(())
+}
+
+/// Unpacks a tar entry to `relative` below `target_dir`. Links are created here, `tar` would
+/// accept any target for them. `relative_of` maps paths in the archive like `target_of` does,
+/// it places the sources of hard links.
+fn unpack_tar_entry<R: Read>(
+    entry: &mut tar::Entry<R>,
+    target_dir: &Path,
+    relative: &Path,
+    relative_of: impl Fn(&str) -> io::Result<Option<PathBuf>>,
+) -> io::Result<()> {
+    let entry_type = entry.header().entry_type();
+    if entry_type.is_dir() {
+        create_dirs_in(target_dir, relative)?;
+        return entry.unpack(target_dir.join(relative)).map(|_| ());
+    }
+    if entry_type.is_symlink() {
+        let link = entry.link_name()?.ok_or_else(|| escape_error("symbolic link without a target"))?;
+        if !link_stays_inside(relative, &link) {
+            return Err(escape_error("symbolic link points outside the target folder"));
+        }
+        let target = prepare_target(target_dir, relative)?;
+        return std::os::unix::fs::symlink(link, target);
+    }
+    if entry_type.is_hard_link() {
+        let link = entry.link_name()?.ok_or_else(|| escape_error("hard link without a target"))?;
+        let source = relative_of(&clean(link.to_str().unwrap_or_default()))?
+            .ok_or_else(|| escape_error("hard link to a folder"))?;
+        check_no_links_in(target_dir, &source)?;
+        let target = prepare_target(target_dir, relative)?;
+        return fs::hard_link(target_dir.join(source), target);
+    }
+    let target = prepare_target(target_dir, relative)?;
+    entry.unpack(target).map(|_| ())
+}
+
+/// Copies `inner` (a file or a whole folder) out of the archive into `target_dir`.
+pub fn extract_entry(archive: &str, inner: &str, target_dir: &Path) -> io::Result<()> {
+    let format = format_of(archive)?;
+    let base = Path::new(inner).parent().unwrap_or(Path::new(""));
+    let wanted = |path: &str| path == inner || path.starts_with(&format!("{}/", inner));
+    let relative_of = |path: &str| -> io::Result<PathBuf> {
+        let relative = Path::new(path).strip_prefix(base).unwrap_or(Path::new(path));
+        safe_relative(relative.to_str().unwrap_or_default())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unsafe path in archive"))
+    };
+
+    if format == ArchiveFormat::Zip {
+        let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?))?;
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            let path = clean(file.name());
+            if !wanted(&path) {
+                continue;
+            }
+            extract_zip_file(&mut file, target_dir, &relative_of(&path)?)?;
+        }
+        return Ok(());
+    }
+
+    let mut tar = tar::Archive::new(tar_reader(archive, format)?);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let path = clean(entry.path()?.to_str().unwrap_or_default());
+        if !wanted(&path) {
+            continue;
+        }
+        let relative = relative_of(&path)?;
+        unpack_tar_entry(&mut entry, target_dir, &relative, |p| relative_of(p).map(Some))?;
+    }
+    Ok(())
+}
+
+fn format_of(archive: &str) -> io::Result<ArchiveFormat> {
+    let name = Path::new(archive).file_name().unwrap_or_default().to_str().unwrap_or_default();
+    ArchiveFormat::of(name).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not an archive"))
+}
+
+fn write_tar<W: Write>(
+    writer: W,
+    entries: &[(ignore::DirEntry, PathBuf)],
+    follow_links: bool,
+    progress: &Progress,
+) -> io::Result<W> {
+    let mut tar = tar::Builder::new(writer);
+    tar.follow_symlinks(follow_links);
+    progress.set_total(entries.len() as u64);
+    for (entry, name) in entries {
+        if progress.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        progress.set_message(name.to_string_lossy());
+        tar.append_path_with_name(entry.path(), name)?;
+        progress.add(1);
+    }
+    tar.into_inner()
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateOptions {
+    pub format: ArchiveFormat,
+    /// Only used for zip.
+    pub zip: ZipOptions,
+    pub follow_links: bool,
+    pub filter: ListingFilter,
+    /// Glob patterns of entries to leave out.
+    pub exclude: String,
+}
+
+/// Packs `sources` with everything inside them into `dst_file`, naming entries relative to `base`.
+pub fn create(
+    base: &Path,
+    sources: &[PathBuf],
+    dst_file: &Path,
+    options: &CreateOptions,
+    progress: &Progress,
+) -> anyhow::Result<()> {
+    if let Some(missing) = sources.iter().find(|s| !s.exists()) {
+        anyhow::bail!("{} does not exist", missing.display());
+    }
+    let exclude = GlobFilter::new("", &options.exclude)?;
+    let mut entries = walk_sources(base, sources, options.follow_links, options.filter, &exclude);
+    // the archive may be written into one of the folders being packed
+    entries.retain(|(entry, _)| entry.path() != dst_file);
+    // written next to the target first, so an existing file is only replaced by a complete archive
+    let file_name = dst_file.file_name().unwrap_or_default().to_string_lossy();
+    let temp = dst_file.with_file_name(format!(".{}.tmp", file_name));
+    match write_archive(&entries, &temp, options, progress) {
+        Ok(()) => Ok(fs::rename(&temp, dst_file)?),
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+fn write_archive(
+    entries: &[(ignore::DirEntry, PathBuf)],
+    dst_file: &Path,
+    options: &CreateOptions,
+    progress: &Progress,
+) -> anyhow::Result<()> {
+    let follow_links = options.follow_links;
+    if options.format == ArchiveFormat::Zip {
+        return crate::zip::zip_entries(entries, dst_file, &options.zip, follow_links, progress);
+    }
+    let file = BufWriter::new(File::create(dst_file)?);
+    let file = match options.format {
+        ArchiveFormat::TarGz => {
+            let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            write_tar(encoder, entries, follow_links, progress)?.finish()?
+        }
+        ArchiveFormat::TarXz => {
+            write_tar(xz2::write::XzEncoder::new(file, 6), entries, follow_links, progress)?.finish()?
+        }
+        ArchiveFormat::TarZst => {
+            write_tar(zstd::Encoder::new(file, 0)?, entries, follow_links, progress)?.finish()?
+        }
+        ArchiveFormat::TarBz2 => {
+            let encoder = bzip2::write::BzEncoder::new(file, bzip2::Compression::default());
+            write_tar(encoder, entries, follow_links, progress)?.finish()?
+        }
+        _ => write_tar(file, entries, follow_links, progress)?,
+    };
+    file.into_inner()?.sync_all()?;
+    Ok(())
+}
+
+/// The single folder everything in the archive is inside of, if there is one.
+pub fn toplevel(all: &[ArchiveEntry]) -> Option<String> {
+    let mut roots = all.iter().filter(|e| !e.path.contains('/'));
+    let root = roots.next()?;
+    (roots.next().is_none() && root.file_type.is_dir()).then(|| root.path.clone())
+}
+
+/// Whether any entry of a zip archive needs a password, tar archives are never encrypted.
+pub fn is_encrypted(archive: &str) -> io::Result<bool> {
+    if format_of(archive)? != ArchiveFormat::Zip {
+        return Ok(false);
+    }
+    let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?))?;
+    for i in 0..zip.len() {
+        if zip.by_index_raw(i)?.encrypted() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ExtractOptions {
+    /// Leaves out a single folder enclosing everything.
+    pub strip_toplevel: bool,
+    pub password: Option<String>,
+    /// Paths inside the archive to extract, with everything below them. `None` extracts all.
+    pub only: Option<HashSet<String>>,
+}
+
+impl ExtractOptions {
+    fn wanted(&self, path: &str) -> bool {
+        let Some(only) = &self.only else {
+            return true;
+        };
+        Path::new(path)
+            .ancestors()
+            .any(|p| only.contains(p.to_str().unwrap_or_default()))
+    }
+}
+
+/// Opens a new file at `target`, never through a symlink put there in the meantime.
+fn create_file(target: &Path) -> io::Result<File> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(target)
+}
+
+/// Extracts a zip entry to `relative` below `target_dir` with the same checks as `unpack_tar_entry`.
+fn extract_zip_file(file: &mut zip::read::ZipFile, target_dir: &Path, relative: &Path) -> io::Result<()> {
+    if file.is_dir() {
+        return create_dirs_in(target_dir, relative);
+    }
+    if file.is_symlink() {
+        let mut link = String::new();
+        file.read_to_string(&mut link)?;
+        if !link_stays_inside(relative, Path::new(&link)) {
+            return Err(escape_error("symbolic link points outside the target folder"));
+        }
+        return std::os::unix::fs::symlink(link, prepare_target(target_dir, relative)?);
+    }
+    let mut out = create_file(&prepare_target(target_dir, relative)?)?;
+    io::copy(file, &mut out)?;
+    if let Some(mode) = file.unix_mode() {
+        // like for tar, setuid and setgid are dropped
+        out.set_permissions(fs::Permissions::from_mode(mode & 0o1777))?;
+    }
+    let modified = zip_time(file.last_modified());
+    filetime::set_file_handle_times(&out, None, Some(filetime::FileTime::from_unix_time(modified.timestamp(), 0)))
+}
+
+/// Extracts the archive into `target_dir` while streaming it from disk, keeping
+/// permissions, mtimes and symlinks. Entries that fail are skipped and returned
+/// as messages, only problems with the archive as a whole are an error.
+pub fn extract(
+    archive: &str,
+    target_dir: &Path,
+    options: &ExtractOptions,
+    progress: &Progress,
+) -> io::Result<Vec<String>> {
+    let format = format_of(archive)?;
+    let all = list(archive)?;
+    let strip = if options.strip_toplevel { toplevel(&all) } else { None };
+    let relative_of = |path: &str| -> io::Result<Option<PathBuf>> {
+        let relative = match &strip {
+            Some(root) if path == root => return Ok(None),
+            Some(root) => path.strip_prefix(&format!("{}/", root)).unwrap_or(path),
+            None => path,
+        };
+        let relative = safe_relative(relative)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "path escapes the target folder"))?;
+        Ok(Some(relative))
+    };
+    fs::create_dir_all(target_dir)?;
+    progress.set_total(all.iter().filter(|e| options.wanted(&e.path)).count() as u64);
+    let mut errors = vec![];
+
+    if format == ArchiveFormat::Zip {
+        let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?))?;
+        for i in 0..zip.len() {
+            if progress.is_cancelled() {
+                break;
+            }
+            let path = match zip.name_for_index(i) {
+                Some(name) => clean(name),
+                None => continue,
+            };
+            if path.is_empty() || !options.wanted(&path) {
+                continue;
+            }
+            progress.set_message(&path);
+            let result = relative_of(&path).and_then(|relative| {
+                let Some(relative) = relative else {
+                    return Ok(());
+                };
+                let mut file = match &options.password {
+                    Some(password) => zip.by_index_decrypt(i, password.as_bytes())?,
+                    None => zip.by_index(i)?,
+                };
+                extract_zip_file(&mut file, target_dir, &relative)
+            });
+            if let Err(e) = result {
+                errors.push(format!("{}: {}", path, e));
+            }
+            progress.add(1);
+        }
+        return Ok(errors);
+    }
+
+    let mut tar = tar::Archive::new(tar_reader(archive, format)?);
+    tar.set_preserve_permissions(true);
+    // setuid and setgid from a downloaded archive are never wanted, the sticky bit is harmless
+    tar.set_mask(0o6000);
+    tar.set_preserve_mtime(true);
+    for entry in tar.entries()? {
+        if progress.is_cancelled() {
+            break;
+        }
+        // a broken header means the rest of the stream can't be trusted either
+        let mut entry = entry?;
+        let path = clean(entry.path()?.to_str().unwrap_or_default());
+        if path.is_empty() || !options.wanted(&path) {
+            continue;
+        }
+        progress.set_message(&path);
+        let result = relative_of(&path).and_then(|relative| {
+            let Some(relative) = relative else {
+                return Ok(());
+            };
+            unpack_tar_entry(&mut entry, target_dir, &relative, relative_of)
+        });
+        if let Err(e) = result {
+            errors.push(format!("{}: {}", path, e));
+        }
+        progress.add(1);
+    }
+    Ok(errors)
+}
+
+#[derive(Debug, Clone)]
+pub struct EntryInfo {
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Only known for zip, tar archives are compressed as a whole.
+    pub compressed_size: Option<u64>,
+    pub method: String,
+    pub modified: DateTime<Utc>,
+    pub encrypted: bool,
+}
+
+impl EntryInfo {
+    pub fn ratio(&self) -> Option<f64> {
+        let compressed = self.compressed_size?;
+        (self.size > 0).then(|| compressed as f64 / self.size as f64)
+    }
+}
+
+fn method_name(format: ArchiveFormat) -> &'static str {
+    match format {
+        ArchiveFormat::Zip | ArchiveFormat::Tar => "Stored",
+        ArchiveFormat::TarGz => "Gzip",
+        ArchiveFormat::TarXz => "Xz",
+        ArchiveFormat::TarZst => "Zstd",
+        ArchiveFormat::TarBz2 => "Bzip2",
+    }
+}
+
+/// Every entry as stored in the archive, without implied folders.
+pub fn info(archive: &str) -> io::Result<Vec<EntryInfo>> {
+    let format = format_of(archive)?;
+    let mut entries = vec![];
+    if format == ArchiveFormat::Zip {
+        let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?))?;
+        for i in 0..zip.len() {
+            let file = zip.by_index_raw(i)?;
+            entries.push(EntryInfo {
+                path: clean(file.name()),
+                is_dir: file.is_dir(),
+                size: file.size(),
+                compressed_size: Some(file.compressed_size()),
+                method: file.compression().to_string(),
+                modified: zip_time(file.last_modified()),
+                encrypted: file.encrypted(),
+            });
+        }
+        return Ok(entries);
+    }
+    let mut tar = tar::Archive::new(tar_reader(archive, format)?);
+    for entry in tar.entries()? {
+        let entry = entry?;
+        let header = entry.header();
+        entries.push(EntryInfo {
+            path: clean(entry.path()?.to_str().unwrap_or_default()),
+            is_dir: header.entry_type().is_dir(),
+            size: header.size()?,
+            compressed_size: None,
+            method: method_name(format).to_string(),
+            modified: DateTime::from_timestamp(header.mtime()? as i64, 0).unwrap_or_default(),
+            encrypted: false,
+        });
+    }
+    Ok(entries)
+}
+
+/// Reads every entry to the end, which makes the zip crate check the CRC of each
+/// and the decompressors check their streams. Returns the problems found.
+pub fn test(archive: &str, progress: &Progress) -> io::Result<Vec<String>> {
+    let format = format_of(archive)?;
+    let mut problems = vec![];
+    if format == ArchiveFormat::Zip {
+        let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?))?;
+        progress.set_total(zip.len() as u64);
+        for i in 0..zip.len() {
+            if progress.is_cancelled() {
+                break;
+            }
+            let name = zip.name_for_index(i).unwrap_or_default().to_string();
+            progress.set_message(&name);
+            progress.add(1);
+            if zip.by_index_raw(i).is_ok_and(|f| f.encrypted()) {
+                problems.push(format!("{}: encrypted, not checked", name));
+                continue;
+            }
+            let result = zip.by_index(i).map_err(io::Error::from).and_then(|mut file| {
+                let expected = file.size();
+                let read = io::copy(&mut file, &mut io::sink())?;
+                if read != expected {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("truncated, {} of {} bytes", read, expected),
+                    ));
+                }
+                Ok(())
+            });
+            if let Err(e) = result {
+                problems.push(format!("{}: {}", name, e));
+            }
+        }
+        return Ok(problems);
+    }
+
+    let file = File::open(archive)?;
+    // tar has no index to count entries in, progress follows the position in the file
+    progress.set_total(file.metadata()?.len());
+    let mut tar = tar::Archive::new(decompress(CountingReader { inner: file, progress }, format)?);
+    for entry in tar.entries()? {
+        if progress.is_cancelled() {
+            break;
+        }
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                // nothing after a broken header or stream can be read anymore
+                problems.push(format!("archive is corrupt or truncated: {}", e));
+                break;
+            }
+        };
+        let name = clean(&entry.path().map(|p| p.to_string_lossy().to_string()).unwrap_or_default());
+        progress.set_message(&name);
+        let expected = entry.header().size().unwrap_or_default();
+        match io::copy(&mut entry, &mut io::sink()) {
+            Ok(read) if read != expected => {
+                problems.push(format!("{}: truncated, {} of {} bytes", name, read, expected))
+            }
+            Ok(_) => {}
+            Err(e) => {
+                problems.push(format!("{}: {}", name, e));
+                break;
+            }
+        }
+    }
+    Ok(problems)
+}
+
+/// Adds every byte read to `progress`.
+struct CountingReader<'a, R> {
+    inner: R,
+    progress: &'a Progress,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.add(read as u64);
+        Ok(read)
+    }
+}
+
+/// A result view listing the entries of an archive.
+#[derive(Debug)]
+pub struct ArchiveInfo {
+    pub archive: String,
+    pub entries: Job<io::Result<Vec<EntryInfo>>>,
+    pub message: Option<String>,
+    /// Saving found an existing text file and waits for a second click.
+    pub confirm_overwrite: bool,
+}
+
+impl ArchiveInfo {
+    pub fn new(archive: &str) -> Self {
+        let path = archive.to_string();
+        Self {
+            archive: archive.to_string(),
+            // compressed tar archives are read to the end to list them
+            entries: Job::spawn(move |_| info(&path)),
+            message: None,
+            confirm_overwrite: false,
+        }
+    }
+
+    /// Where "save as text" writes the listing.
+    pub fn text_path(&self) -> String {
+        format!("{}.txt", self.archive)
+    }
+
+    /// The listing as a plain text table.
+    pub fn to_text(&mut self) -> String {
+        let Some(Ok(entries)) = self.entries.poll() else {
+            return String::new();
+        };
+        let mut text = format!("{}\n\n", self.archive);
+        text += &format!(
+            "{:>12} {:>12} {:>6}  {:<10} {:<16}  {}\n",
+            "size", "compressed", "ratio", "method", "modified", "path"
+        );
+        for entry in entries.iter() {
+            text += &format!(
+                "{:>12} {:>12} {:>6}  {:<10} {:<16}  {}{}{}\n",
+                entry.size,
+                entry.compressed_size.map(|c| c.to_string()).unwrap_or_default(),
+                entry.ratio().map(|r| format!("{:.0}%", r * 100.)).unwrap_or_default(),
+                entry.method,
+                entry.modified.format("%Y-%m-%d %H:%M"),
+                entry.path,
+                if entry.is_dir { "/" } else { "" },
+                if entry.encrypted { " (encrypted)" } else { "" },
+            );
+        }
+        let size: u64 = entries.iter().map(|e| e.size).sum();
+        text += &format!("\n{} entries, {} bytes uncompressed\n", entries.len(), size);
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, is_dir: bool) -> ArchiveEntry {
+        ArchiveEntry {
+            path: path.to_string(),
+            file_type: if is_dir { FileType::Dir } else { FileType::File },
+            len: 0,
+            modified: DateTime::default(),
+            symlink_target: None,
+        }
+    }
+
+    /// Appends an entry without the checks `tar::Builder` does, like a hostile archive would.
+    fn append(tar: &mut tar::Builder<File>, kind: tar::EntryType, path: &str, link: &str, content: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(kind);
+        header.set_path(path).unwrap();
+        header.set_link_name_literal(link).unwrap();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append(&header, content).unwrap();
+    }
+
+    fn extract_tar(dir: &Path, build: impl FnOnce(&mut tar::Builder<File>)) -> Vec<String> {
+        let archive = dir.join("test.tar");
+        let mut tar = tar::Builder::new(File::create(&archive).unwrap());
+        build(&mut tar);
+        tar.finish().unwrap();
+        let target = dir.join("target");
+        extract(archive.to_str().unwrap(), &target, &ExtractOptions::default(), &Progress::default()).unwrap()
+    }
+
+    #[test]
+    fn safe_relative_rejects_escaping_paths() {
+        assert_eq!(safe_relative("a/b"), Some(PathBuf::from("a/b")));
+        assert_eq!(safe_relative("./a"), Some(PathBuf::from("./a")));
+        assert_eq!(safe_relative("../a"), None);
+        assert_eq!(safe_relative("a/../../b"), None);
+        assert_eq!(safe_relative("/etc/passwd"), None);
+    }
+
+    #[test]
+    fn split_path_finds_the_archive_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("docs.zip");
+        File::create(&archive).unwrap();
+        let archive = archive.to_str().unwrap();
+
+        assert_eq!(
+            split_path(&format!("{}/a/b.txt", archive)),
+            Some((archive.to_string(), "a/b.txt".to_string()))
+        );
+        assert_eq!(split_path(archive), Some((archive.to_string(), String::new())));
+        // a folder named like an archive is just a folder
+        fs::create_dir(dir.path().join("folder.zip")).unwrap();
+        assert_eq!(split_path(dir.path().join("folder.zip/a").to_str().unwrap()), None);
+    }
+
+    #[test]
+    fn toplevel_needs_a_single_enclosing_folder() {
+        assert_eq!(
+            toplevel(&[entry("root", true), entry("root/a", false)]),
+            Some("root".to_string())
+        );
+        assert_eq!(toplevel(&[entry("root", true), entry("other", false)]), None);
+        assert_eq!(toplevel(&[entry("file", false)]), None);
+        assert_eq!(toplevel(&[]), None);
+    }
+
+    #[test]
+    fn links_must_stay_inside_the_target() {
+        assert!(link_stays_inside(Path::new("a/link"), Path::new("../file")));
+        assert!(link_stays_inside(Path::new("link"), Path::new("./a/../b")));
+        assert!(!link_stays_inside(Path::new("link"), Path::new("../file")));
+        assert!(!link_stays_inside(Path::new("a/link"), Path::new("b/../../../file")));
+        assert!(!link_stays_inside(Path::new("link"), Path::new("/etc")));
+    }
+
+    #[test]
+    fn tar_links_cannot_escape_the_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        let errors = extract_tar(dir.path(), |tar| {
+            append(tar, tar::EntryType::Symlink, "abs", outside.to_str().unwrap(), b"");
+            append(tar, tar::EntryType::file(), "abs/evil", "", b"evil");
+            append(tar, tar::EntryType::Symlink, "up", "../outside", b"");
+            append(tar, tar::EntryType::Link, "hard", "../outside/secret", b"");
+            append(tar, tar::EntryType::file(), "dir/file", "", b"content");
+            append(tar, tar::EntryType::Symlink, "dir/link", "file", b"");
+            append(tar, tar::EntryType::Link, "dir/hard", "dir/file", b"");
+        });
+
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 0);
+        let target = dir.path().join("target");
+        // without the rejected link `abs` is an ordinary folder
+        assert!(fs::symlink_metadata(target.join("abs")).unwrap().is_dir());
+        assert_eq!(fs::read_to_string(target.join("dir/link")).unwrap(), "content");
+        assert_eq!(fs::read_to_string(target.join("dir/hard")).unwrap(), "content");
+    }
+
+    #[test]
+    fn tar_never_writes_through_existing_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        fs::write(outside.join("file"), "original").unwrap();
+        let target = dir.path().join("target");
+        fs::create_dir(&target).unwrap();
+        std::os::unix::fs::symlink(&outside, target.join("folder")).unwrap();
+        std::os::unix::fs::symlink(outside.join("file"), target.join("file")).unwrap();
+
+        let errors = extract_tar(dir.path(), |tar| {
+            append(tar, tar::EntryType::file(), "folder/evil", "", b"evil");
+            append(tar, tar::EntryType::file(), "file", "", b"replaced");
+        });
+
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(!outside.join("evil").exists());
+        assert_eq!(fs::read_to_string(outside.join("file")).unwrap(), "original");
+        assert_eq!(fs::read_to_string(target.join("file")).unwrap(), "replaced");
+    }
+
+    #[test]
+    fn tar_drops_setuid_and_setgid() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let errors = extract_tar(dir.path(), |tar| {
+            for (path, mode) in [("tool", 0o6755), ("shared", 0o1777)] {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(if path == "tool" { tar::EntryType::file() } else { tar::EntryType::dir() });
+                header.set_path(path).unwrap();
+                header.set_size(0);
+                header.set_mode(mode);
+                header.set_cksum();
+                tar.append(&header, &b""[..]).unwrap();
+            }
+        });
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        let mode = |name: &str| fs::metadata(dir.path().join("target").join(name)).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode("tool"), 0o755);
+        assert_eq!(mode("shared"), 0o1777);
+    }
+
+    #[test]
+    fn zip_keeps_mtimes_and_drops_setuid() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("tool");
+        fs::write(&source, "content").unwrap();
+        fs::set_permissions(&source, fs::Permissions::from_mode(0o4755)).unwrap();
+        // zip stores seconds with a resolution of two
+        let modified = filetime::FileTime::from_unix_time(1_623_760_496, 0);
+        filetime::set_file_mtime(&source, modified).unwrap();
+
+        let archive = dir.path().join("test.zip");
+        let entries = walk_sources(dir.path(), &[source], false, ListingFilter::default(), &GlobFilter::new("", "").unwrap());
+        crate::zip::zip_entries(&entries, &archive, &ZipOptions::default(), false, &Progress::default()).unwrap();
+        let listed = list(archive.to_str().unwrap()).unwrap();
+        assert_eq!(listed[0].modified.timestamp(), modified.unix_seconds());
+
+        let target = dir.path().join("target");
+        let errors = extract(archive.to_str().unwrap(), &target, &ExtractOptions::default(), &Progress::default()).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let meta = fs::metadata(target.join("tool")).unwrap();
+        assert_eq!(filetime::FileTime::from_last_modification_time(&meta), modified);
+        assert_eq!(meta.permissions().mode() & 0o7777, 0o755);
+    }
+
+    #[test]
+    fn failed_create_keeps_the_existing_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("file");
+        fs::write(&source, "content").unwrap();
+        let target = dir.path().join("test.tar");
+        fs::write(&target, "old").unwrap();
+        let progress = Progress::default();
+        progress.cancel();
+
+        for format in [ArchiveFormat::Zip, ArchiveFormat::Tar] {
+            let options = CreateOptions {
+                format,
+                zip: ZipOptions::default(),
+                follow_links: false,
+                filter: ListingFilter::default(),
+                exclude: String::new(),
+            };
+            assert!(create(dir.path(), std::slice::from_ref(&source), &target, &options, &progress).is_err());
+            assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+            assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+        }
+    }
+
+    #[test]
+    fn testing_a_tar_reports_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("test.tar.gz");
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&archive).unwrap(),
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_cksum();
+        tar.append_data(&mut header, "file", &b"data"[..]).unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let progress = Progress::default();
+        assert!(test(archive.to_str().unwrap(), &progress).unwrap().is_empty());
+        assert_eq!(progress.fraction(), 1.0);
+    }
+
+    #[test]
+    fn zip_links_cannot_escape_the_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        fs::write(outside.join("file"), "original").unwrap();
+        let target = dir.path().join("target");
+        fs::create_dir(&target).unwrap();
+        std::os::unix::fs::symlink(outside.join("file"), target.join("existing")).unwrap();
+
+        let archive = dir.path().join("test.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.add_symlink("abs", outside.to_str().unwrap(), options).unwrap();
+        zip.start_file("abs/evil", options).unwrap();
+        zip.write_all(b"evil").unwrap();
+        zip.add_symlink("up", "../outside", options).unwrap();
+        zip.start_file("existing", options).unwrap();
+        zip.write_all(b"replaced").unwrap();
+        zip.start_file("dir/file", options).unwrap();
+        zip.write_all(b"content").unwrap();
+        zip.add_symlink("dir/link", "file", options).unwrap();
+        zip.finish().unwrap();
+
+        let errors = extract(archive.to_str().unwrap(), &target, &ExtractOptions::default(), &Progress::default()).unwrap();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 1);
+        assert_eq!(fs::read_to_string(outside.join("file")).unwrap(), "original");
+        assert_eq!(fs::read_to_string(target.join("existing")).unwrap(), "replaced");
+        assert_eq!(fs::read_to_string(target.join("dir/link")).unwrap(), "content");
+    }
+}
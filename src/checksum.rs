@@ -0,0 +1,389 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::{files::ListingFilter, job::Progress};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake3,
+    Crc32,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 6] = [
+        Algorithm::Md5,
+        Algorithm::Sha1,
+        Algorithm::Sha256,
+        Algorithm::Sha512,
+        Algorithm::Blake3,
+        Algorithm::Crc32,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha1 => "SHA-1",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha512 => "SHA-512",
+            Algorithm::Blake3 => "BLAKE3",
+            Algorithm::Crc32 => "CRC32",
+        }
+    }
+
+    /// The usual file name of a manifest, as written by `sha256sum` and friends.
+    pub fn manifest_name(&self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5SUMS",
+            Algorithm::Sha1 => "SHA1SUMS",
+            Algorithm::Sha256 => "SHA256SUMS",
+            Algorithm::Sha512 => "SHA512SUMS",
+            Algorithm::Blake3 => "B3SUMS",
+            Algorithm::Crc32 => "CRC32SUMS",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "md5",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha512 => "sha512",
+            Algorithm::Blake3 => "b3",
+            Algorithm::Crc32 => "crc32",
+        }
+    }
+
+    fn hex_len(&self) -> usize {
+        match self {
+            Algorithm::Md5 => 32,
+            Algorithm::Sha1 => 40,
+            Algorithm::Sha256 | Algorithm::Blake3 => 64,
+            Algorithm::Sha512 => 128,
+            Algorithm::Crc32 => 8,
+        }
+    }
+
+    /// Guesses the algorithm of a manifest from its name, e.g. `SHA256SUMS` or `file.sha256`.
+    pub fn of_manifest(file_name: &str) -> Option<Self> {
+        let lower = file_name.to_lowercase();
+        Self::ALL.into_iter().find(|a| {
+            file_name.starts_with(a.manifest_name()) || lower.ends_with(&format!(".{}", a.extension()))
+        })
+    }
+
+    /// Guesses by the length of a hex hash, SHA-256 wins over BLAKE3.
+    fn of_hash(hash: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.hex_len() == hash.len())
+    }
+}
+
+enum Hasher {
+    Md5(Md5),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake3(Box<blake3::Hasher>),
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher {
+    fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Md5 => Hasher::Md5(Md5::new()),
+            Algorithm::Sha1 => Hasher::Sha1(Sha1::new()),
+            Algorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            Algorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            Algorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            Algorithm::Crc32 => Hasher::Crc32(crc32fast::Hasher::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(h) => h.update(data),
+            Hasher::Sha1(h) => h.update(data),
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+            Hasher::Crc32(h) => h.update(data),
+        }
+    }
+
+    fn finish(self) -> String {
+        match self {
+            Hasher::Md5(h) => format!("{:x}", h.finalize()),
+            Hasher::Sha1(h) => format!("{:x}", h.finalize()),
+            Hasher::Sha256(h) => format!("{:x}", h.finalize()),
+            Hasher::Sha512(h) => format!("{:x}", h.finalize()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+            Hasher::Crc32(h) => format!("{:08x}", h.finalize()),
+        }
+    }
+}
+
+/// Hashes a file with all `algorithms` in a single read, adding the bytes read to `progress`.
+pub fn hash_file(path: &Path, algorithms: &[Algorithm], progress: &Progress) -> io::Result<Vec<(Algorithm, String)>> {
+    let mut hashers: Vec<_> = algorithms.iter().map(|a| (*a, Hasher::new(*a))).collect();
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; 1 << 16];
+    loop {
+        if progress.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        for (_, hasher) in hashers.iter_mut() {
+            hasher.update(&buffer[..read]);
+        }
+        progress.add(read as u64);
+    }
+    Ok(hashers.into_iter().map(|(a, h)| (a, h.finish())).collect())
+}
+
+#[derive(Debug)]
+pub struct FileChecksums {
+    pub path: String,
+    pub hashes: Result<Vec<(Algorithm, String)>, String>,
+}
+
+pub fn hash_files(paths: &[String], algorithms: &[Algorithm], progress: &Progress) -> Vec<FileChecksums> {
+    let total = paths.iter().filter_map(|p| std::fs::metadata(p).ok()).map(|m| m.len()).sum();
+    progress.set_total(total);
+    paths
+        .iter()
+        .map(|path| {
+            progress.set_message(path);
+            FileChecksums {
+                path: path.clone(),
+                hashes: hash_file(Path::new(path), algorithms, progress).map_err(|e| e.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// What `write_manifest` wrote.
+#[derive(Debug)]
+pub struct WrittenManifest {
+    pub path: PathBuf,
+    /// The number of files listed.
+    pub count: usize,
+    /// Files left out because they could not be read, with the reason.
+    pub failed: Vec<String>,
+}
+
+/// Writes a `sha256sum`-style manifest of every file below `dir` into `dir`.
+pub fn write_manifest(
+    dir: &Path,
+    algorithm: Algorithm,
+    filter: ListingFilter,
+    progress: &Progress,
+) -> io::Result<WrittenManifest> {
+    let manifest = dir.join(algorithm.manifest_name());
+    let mut files: Vec<(PathBuf, PathBuf)> = filter
+        .walk(dir, false)
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()) && e.path() != manifest)
+        .filter_map(|e| Some((e.path().strip_prefix(dir).ok()?.to_path_buf(), e.path().to_path_buf())))
+        .collect();
+    files.sort();
+    progress.set_total(files.iter().filter_map(|(_, p)| p.metadata().ok()).map(|m| m.len()).sum());
+
+    let mut text = String::new();
+    let mut count = 0;
+    let mut failed = vec![];
+    for (relative, path) in files.iter() {
+        let relative = relative.to_string_lossy();
+        progress.set_message(relative.as_ref());
+        match hash_file(path, &[algorithm], progress) {
+            Ok(mut hashes) => {
+                text += &format!("{}  {}\n", hashes.remove(0).1, relative);
+                count += 1;
+            }
+            // a cancelled run leaves no half manifest behind
+            Err(e) if progress.is_cancelled() => return Err(e),
+            Err(e) => failed.push(format!("{}: {}", relative, e)),
+        }
+    }
+    std::fs::write(&manifest, text)?;
+    Ok(WrittenManifest { path: manifest, count, failed })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyStatus {
+    Ok,
+    Mismatch,
+    Missing,
+    Error(String),
+}
+
+#[derive(Debug)]
+pub struct VerifyEntry {
+    pub path: String,
+    pub status: VerifyStatus,
+}
+
+/// Parses `hash  path` lines, the `*` of binary mode entries is dropped.
+fn parse_manifest(manifest: &Path) -> io::Result<Vec<(String, String)>> {
+    let mut entries = vec![];
+    for line in BufReader::new(File::open(manifest)?).lines() {
+        let line = line?;
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((hash, path)) = line.split_once(char::is_whitespace) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid line: {}", line)));
+        };
+        let path = path.trim_start();
+        let path = path.strip_prefix('*').unwrap_or(path);
+        entries.push((hash.to_lowercase(), path.to_string()));
+    }
+    Ok(entries)
+}
+
+/// Checks every file listed in a manifest, paths are relative to the folder of the manifest.
+pub fn verify_manifest(manifest: &Path, progress: &Progress) -> io::Result<Vec<VerifyEntry>> {
+    let entries = parse_manifest(manifest)?;
+    let dir = manifest.parent().unwrap_or(Path::new("/"));
+    let file_name = manifest.file_name().unwrap_or_default().to_string_lossy();
+    let algorithm = Algorithm::of_manifest(&file_name)
+        .or_else(|| Algorithm::of_hash(&entries.first()?.0))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unknown checksum algorithm"))?;
+    progress.set_total(
+        entries
+            .iter()
+            .filter_map(|(_, p)| dir.join(p).metadata().ok())
+            .map(|m| m.len())
+            .sum(),
+    );
+
+    let mut results = vec![];
+    for (expected, relative) in entries {
+        if progress.is_cancelled() {
+            break;
+        }
+        progress.set_message(&relative);
+        let path = dir.join(&relative);
+        let status = if !path.exists() {
+            VerifyStatus::Missing
+        } else {
+            match hash_file(&path, &[algorithm], progress) {
+                Ok(hashes) if hashes[0].1 == expected => VerifyStatus::Ok,
+                Ok(_) => VerifyStatus::Mismatch,
+                Err(e) => VerifyStatus::Error(e.to_string()),
+            }
+        };
+        results.push(VerifyEntry { path: relative, status });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn hashes_match_known_vectors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc");
+        fs::write(&path, "abc").unwrap();
+        let hashes = hash_file(&path, &Algorithm::ALL, &Progress::default()).unwrap();
+        let expected = [
+            "900150983cd24fb0d6963f7d28e17f72",
+            "a9993e364706816aba3e25717850c26c9cd0d89d",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            "352441c2",
+        ];
+        for ((algorithm, hash), expected) in hashes.iter().zip(expected) {
+            assert_eq!(hash, expected, "{}", algorithm.name());
+            assert_eq!(hash.len(), algorithm.hex_len());
+        }
+    }
+
+    #[test]
+    fn manifests_are_parsed_like_sha256sum_writes_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("SHA256SUMS");
+        fs::write(&manifest, "# comment\n\nABCD  a file.txt\nef01 *bin/tool\n").unwrap();
+        assert_eq!(
+            parse_manifest(&manifest).unwrap(),
+            vec![
+                ("abcd".to_string(), "a file.txt".to_string()),
+                ("ef01".to_string(), "bin/tool".to_string()),
+            ]
+        );
+
+        fs::write(&manifest, "abcd\n").unwrap();
+        let err = parse_manifest(&manifest).unwrap_err();
+        assert_eq!(err.to_string(), "invalid line: abcd");
+
+        assert_eq!(Algorithm::of_manifest("SHA256SUMS"), Some(Algorithm::Sha256));
+        assert_eq!(Algorithm::of_manifest("release.MD5"), Some(Algorithm::Md5));
+        assert_eq!(Algorithm::of_manifest("notes.txt"), None);
+        assert_eq!(Algorithm::of_hash(&"0".repeat(64)), Some(Algorithm::Sha256));
+    }
+
+    #[test]
+    fn verifying_finds_changed_and_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["changed", "missing", "same"] {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+        let written = write_manifest(dir.path(), Algorithm::Sha1, ListingFilter::default(), &Progress::default()).unwrap();
+        assert_eq!((written.count, written.failed.len()), (3, 0));
+        fs::write(dir.path().join("changed"), "other").unwrap();
+        fs::remove_file(dir.path().join("missing")).unwrap();
+
+        let statuses: Vec<_> = verify_manifest(&written.path, &Progress::default())
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.path, e.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("changed".to_string(), VerifyStatus::Mismatch),
+                ("missing".to_string(), VerifyStatus::Missing),
+                ("same".to_string(), VerifyStatus::Ok),
+            ]
+        );
+    }
+
+    #[test]
+    fn unreadable_files_are_left_out_of_manifests() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a"), "a").unwrap();
+        let locked = dir.path().join("locked");
+        fs::write(&locked, "locked").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // permissions don't stop root
+        if File::open(&locked).is_ok() {
+            return;
+        }
+        let written = write_manifest(dir.path(), Algorithm::Md5, ListingFilter::default(), &Progress::default()).unwrap();
+        assert_eq!(written.count, 1);
+        assert_eq!(written.failed.len(), 1);
+        assert!(written.failed[0].starts_with("locked: "));
+        assert!(fs::read_to_string(&written.path).unwrap().ends_with("  a\n"));
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    files::{self, get_entries, FileEntry},
+    job::{Job, Progress},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difference {
+    LeftOnly,
+    RightOnly,
+    Different,
+    Same,
+    /// A folder whose contents could not be listed on one of the sides.
+    Error,
+}
+
+#[derive(Debug)]
+pub struct CompareEntry {
+    pub relative: String,
+    pub is_dir: bool,
+    pub difference: Difference,
+    pub left: Option<FileEntry>,
+    pub right: Option<FileEntry>,
+    pub reasons: Vec<&'static str>,
+    pub error: Option<String>,
+}
+
+fn content_hash(path: &str) -> io::Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?)?;
+    Ok(hasher.finalize())
+}
+
+/// Whether the entry is a real folder, links to folders are compared as links.
+fn is_dir(entry: &FileEntry) -> bool {
+    entry.file_type.is_dir() && !entry.is_symlink()
+}
+
+fn compare_files(left: &FileEntry, right: &FileEntry, content: bool) -> Vec<&'static str> {
+    let mut reasons = vec![];
+    if is_dir(left) != is_dir(right) || left.is_symlink() != right.is_symlink() {
+        reasons.push("type");
+        return reasons;
+    }
+    if left.is_symlink() {
+        if left.symlink_target != right.symlink_target {
+            reasons.push("target");
+        }
+        return reasons;
+    }
+    if is_dir(left) {
+        return reasons;
+    }
+    if left.len != right.len {
+        reasons.push("size");
+    }
+    if left.modified.timestamp() != right.modified.timestamp() {
+        reasons.push("modified");
+    }
+    if content && left.len == right.len {
+        match (content_hash(&left.path), content_hash(&right.path)) {
+            (Ok(a), Ok(b)) if a == b => {}
+            _ => reasons.push("content"),
+        }
+    }
+    reasons
+}
+
+fn compare_recursive(
+    left: &str,
+    right: &str,
+    relative: &str,
+    content: bool,
+    progress: &Progress,
+    result: &mut Vec<CompareEntry>,
+) -> io::Result<()> {
+    if progress.is_cancelled() {
+        return Ok(());
+    }
+    progress.set_message(if relative.is_empty() { "." } else { relative });
+
+    let mut pairs: BTreeMap<String, (Option<FileEntry>, Option<FileEntry>)> = BTreeMap::new();
+    for entry in get_entries(left)? {
+        let name = entry.file_name.clone();
+        pairs.entry(name).or_default().0 = Some(entry);
+    }
+    for entry in get_entries(right)? {
+        let name = entry.file_name.clone();
+        pairs.entry(name).or_default().1 = Some(entry);
+    }
+
+    for (name, (l, r)) in pairs {
+        let relative = if relative.is_empty() {
+            name
+        } else {
+            format!("{}/{}", relative, name)
+        };
+        progress.add(1);
+        match (l, r) {
+            (Some(l), Some(r)) => {
+                let mut reasons = compare_files(&l, &r, content);
+                // links aren't followed, they could lead back up and never end
+                let recurse = reasons.is_empty() && is_dir(&l);
+                let mut error = None;
+                if recurse {
+                    let first_child = result.len();
+                    // one unreadable folder shouldn't hide the differences everywhere else
+                    match compare_recursive(&l.path, &r.path, &relative, content, progress, result) {
+                        Ok(()) => {
+                            if result[first_child..].iter().any(|e| e.difference != Difference::Same) {
+                                reasons.push("contents");
+                            }
+                        }
+                        Err(e) => error = Some(e.to_string()),
+                    }
+                }
+                result.push(CompareEntry {
+                    relative,
+                    is_dir: is_dir(&l),
+                    difference: if error.is_some() {
+                        Difference::Error
+                    } else if reasons.is_empty() {
+                        Difference::Same
+                    } else {
+                        Difference::Different
+                    },
+                    left: Some(l),
+                    right: Some(r),
+                    reasons,
+                    error,
+                });
+            }
+            (l, r) => result.push(CompareEntry {
+                relative,
+                is_dir: is_dir(l.as_ref().or(r.as_ref()).unwrap()),
+                difference: if l.is_some() {
+                    Difference::LeftOnly
+                } else {
+                    Difference::RightOnly
+                },
+                left: l,
+                right: r,
+                reasons: vec![],
+                error: None,
+            }),
+        }
+    }
+    Ok(())
+}
+
+/// How many entries `compare_recursive` will visit, it doesn't descend into folders on one side only.
+fn count_pairs(left: &str, right: &str) -> u64 {
+    let relative_paths = |root: &str| -> HashSet<PathBuf> {
+        WalkDir::new(root)
+            .min_depth(1)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.path().strip_prefix(root).ok().map(Path::to_path_buf))
+            .collect()
+    };
+    let (left, right) = (relative_paths(left), relative_paths(right));
+    left.union(&right)
+        .filter(|p| match p.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => left.contains(parent) && right.contains(parent),
+            _ => true,
+        })
+        .count() as u64
+}
+
+/// Recursively compares two directories by name, size, modification time
+/// and optionally content hash.
+pub fn compare_dirs(
+    left: &str,
+    right: &str,
+    content: bool,
+    progress: &Progress,
+) -> io::Result<Vec<CompareEntry>> {
+    progress.set_total(count_pairs(left, right));
+    let mut result = vec![];
+    compare_recursive(left, right, "", content, progress, &mut result)?;
+    result.sort_by(|a, b| a.relative.cmp(&b.relative));
+    Ok(result)
+}
+
+/// Copies `relative` from below `from` to the same place below `to`.
+pub fn copy_entry(from: &str, to: &str, relative: &str) -> io::Result<()> {
+    let source = Path::new(from).join(relative);
+    let target = Path::new(to).join(relative);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    files::copy_entry(&source, &target, false)
+}
+
+#[derive(Debug)]
+pub struct CompareDirs {
+    pub left: String,
+    pub right: String,
+    pub content: bool,
+    pub job: Job<io::Result<Vec<CompareEntry>>>,
+    pub show_left_only: bool,
+    pub show_right_only: bool,
+    pub show_different: bool,
+    pub show_same: bool,
+    pub selected: HashSet<usize>,
+    pub message: Option<String>,
+}
+
+impl CompareDirs {
+    pub fn new(left: String, right: String, content: bool) -> Self {
+        Self {
+            job: Self::spawn(&left, &right, content),
+            left,
+            right,
+            content,
+            show_left_only: true,
+            show_right_only: true,
+            show_different: true,
+            show_same: false,
+            selected: Default::default(),
+            message: None,
+        }
+    }
+
+    fn spawn(left: &str, right: &str, content: bool) -> Job<io::Result<Vec<CompareEntry>>> {
+        let left = left.to_string();
+        let right = right.to_string();
+        Job::spawn(move |p| compare_dirs(&left, &right, content, p))
+    }
+
+    pub fn rerun(&mut self) {
+        self.job.cancel();
+        self.job = Self::spawn(&self.left, &self.right, self.content);
+        self.selected.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn finds_differences_below_folders() {
+        let left = tempfile::tempdir().unwrap();
+        let right = tempfile::tempdir().unwrap();
+        for dir in [left.path(), right.path()] {
+            fs::create_dir_all(dir.join("same")).unwrap();
+            fs::create_dir_all(dir.join("changed")).unwrap();
+            fs::write(dir.join("same/a"), "a").unwrap();
+            filetime::set_file_mtime(dir.join("same/a"), filetime::FileTime::from_unix_time(0, 0)).unwrap();
+        }
+        fs::write(left.path().join("changed/b"), "b").unwrap();
+        fs::write(right.path().join("c"), "c").unwrap();
+
+        let entries = compare_dirs(
+            left.path().to_str().unwrap(),
+            right.path().to_str().unwrap(),
+            true,
+            &Progress::default(),
+        )
+        .unwrap();
+        let found: Vec<_> = entries
+            .iter()
+            .map(|e| (e.relative.as_str(), e.difference, e.reasons.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("c", Difference::RightOnly, vec![]),
+                ("changed", Difference::Different, vec!["contents"]),
+                ("changed/b", Difference::LeftOnly, vec![]),
+                ("same", Difference::Same, vec![]),
+                ("same/a", Difference::Same, vec![]),
+            ]
+        );
+    }
+
+    fn compare(left: &Path, right: &Path) -> Vec<(String, Difference, Vec<&'static str>)> {
+        compare_dirs(left.to_str().unwrap(), right.to_str().unwrap(), false, &Progress::default())
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.relative, e.difference, e.reasons))
+            .collect()
+    }
+
+    #[test]
+    fn links_are_compared_by_target_and_not_followed() {
+        let left = tempfile::tempdir().unwrap();
+        let right = tempfile::tempdir().unwrap();
+        for dir in [left.path(), right.path()] {
+            fs::create_dir(dir.join("folder")).unwrap();
+            // would never end if followed
+            std::os::unix::fs::symlink(".", dir.join("folder/loop")).unwrap();
+        }
+        std::os::unix::fs::symlink("folder", left.path().join("link")).unwrap();
+        std::os::unix::fs::symlink("elsewhere", right.path().join("link")).unwrap();
+        fs::create_dir(right.path().join("linked")).unwrap();
+        std::os::unix::fs::symlink("folder", left.path().join("linked")).unwrap();
+
+        let progress = Progress::default();
+        let entries = compare_dirs(left.path().to_str().unwrap(), right.path().to_str().unwrap(), false, &progress).unwrap();
+        assert_eq!(progress.fraction(), 1.0);
+        let found: Vec<_> = entries.into_iter().map(|e| (e.relative, e.difference, e.reasons)).collect();
+        assert_eq!(
+            found,
+            [
+                ("folder".to_string(), Difference::Same, vec![]),
+                ("folder/loop".to_string(), Difference::Same, vec![]),
+                ("link".to_string(), Difference::Different, vec!["target"]),
+                ("linked".to_string(), Difference::Different, vec!["type"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn unreadable_folders_become_error_rows() {
+        use std::os::unix::fs::PermissionsExt;
+        let left = tempfile::tempdir().unwrap();
+        let right = tempfile::tempdir().unwrap();
+        for dir in [left.path(), right.path()] {
+            fs::create_dir(dir.join("locked")).unwrap();
+            fs::write(dir.join("locked/a"), "a").unwrap();
+        }
+        fs::write(left.path().join("b"), "b").unwrap();
+        let locked = right.path().join("locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // permissions don't stop root
+        let readable = fs::read_dir(&locked).is_ok();
+        let found = if readable { vec![] } else { compare(left.path(), right.path()) };
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        if readable {
+            return;
+        }
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0], ("b".to_string(), Difference::LeftOnly, vec![]));
+        assert_eq!((found[1].0.as_str(), found[1].1), ("locked", Difference::Error));
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    actions::{Action, ActionState, CommandOutput},
+    files::FileEntry,
+    restriction::Restriction,
+};
+
+/// An action from the config file, e.g.
+///
+/// ```toml
+/// [[action]]
+/// name = "open in vim"
+/// command = "alacritty -e vim {paths}"
+/// restriction = "file and (ext:rs,toml or mime:text/*)"
+/// selection = true
+/// ```
+///
+/// See `Restriction` for the words a restriction can use.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomAction {
+    pub name: String,
+    /// Split like a shell would, then `{path}`, `{name}`, `{dir}` and `{paths}` are replaced in every word.
+    pub command: String,
+    /// Defaults to `{dir}`.
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    #[serde(default)]
+    pub restriction: Option<String>,
+    /// Runs once with the whole selection instead of once per entry.
+    #[serde(default)]
+    pub selection: bool,
+    /// Waits for the command and shows what it printed.
+    #[serde(default)]
+    pub capture_output: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Config {
+    #[serde(default, rename = "action")]
+    actions: Vec<CustomAction>,
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("mac-explorer").join("actions.toml"))
+}
+
+/// Reads the custom actions, a missing config file is no error.
+pub fn load() -> Result<Vec<CustomAction>, String> {
+    let Some(path) = config_path() else {
+        return Ok(vec![]);
+    };
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let config: Config = toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(config.actions)
+}
+
+fn dir_of(entry: &FileEntry) -> String {
+    Path::new(&entry.path)
+        .parent()
+        .and_then(|p| p.to_str())
+        .unwrap_or("/")
+        .to_string()
+}
+
+/// Replaces the placeholders in one word of a template, `{path}`, `{name}` and `{dir}`
+/// refer to the first entry. A word that is just `{paths}` becomes one argument per entry.
+pub fn expand_word(word: &str, entries: &[&FileEntry]) -> Vec<String> {
+    let paths: Vec<String> = entries.iter().map(|e| e.path.clone()).collect();
+    if word == "{paths}" {
+        return paths;
+    }
+    let Some(first) = entries.first() else {
+        return vec![word.to_string()];
+    };
+    vec![word
+        .replace("{paths}", &paths.join(" "))
+        .replace("{path}", &first.path)
+        .replace("{name}", &first.file_name)
+        .replace("{dir}", &dir_of(first))]
+}
+
+impl CustomAction {
+    fn command(&self, entries: &[&FileEntry]) -> Result<Command, String> {
+        let words = shlex::split(&self.command).ok_or_else(|| format!("invalid command {}", self.command))?;
+        let mut args = words.iter().flat_map(|w| expand_word(w, entries));
+        let program = args.next().ok_or_else(|| format!("empty command in {}", self.name))?;
+        let working_dir = match &self.working_dir {
+            Some(dir) => expand_word(dir, entries).join(" "),
+            None => entries.first().map(|e| dir_of(e)).unwrap_or_default(),
+        };
+        let mut command = Command::new(program);
+        command.args(args).current_dir(working_dir);
+        Ok(command)
+    }
+
+    /// The commands to run with their labels, one per entry or one for the whole selection.
+    fn commands(&self, entries: &[&FileEntry]) -> Result<Vec<(String, Command)>, String> {
+        if self.selection {
+            return Ok(vec![(self.name.clone(), self.command(entries)?)]);
+        }
+        entries
+            .iter()
+            .map(|e| Ok((e.file_name.clone(), self.command(&[e])?)))
+            .collect()
+    }
+
+    fn run(&self, entries: &[&FileEntry], s: &mut ActionState) {
+        let commands = match self.commands(entries) {
+            Ok(commands) => commands,
+            Err(e) => {
+                s.message = Some(e);
+                return;
+            }
+        };
+        if self.capture_output {
+            // a single window collects the outputs of every entry
+            s.command_output = Some(CommandOutput::new(&self.name, commands));
+            return;
+        }
+        for (_, mut command) in commands {
+            if let Err(e) = command.spawn() {
+                s.message = Some(format!("could not run {}: {}", self.name, e));
+            }
+        }
+    }
+
+    pub fn into_action(self) -> Result<Action, String> {
+        let restriction: Restriction = match &self.restriction {
+            Some(r) => r.parse().map_err(|e| format!("{}: {}", self.name, e))?,
+            None => Restriction::None,
+        };
+        let name = self.name.clone();
+        let can_execute = move |e: &Vec<&FileEntry>, m: bool| restriction.matches(e, m);
+        Ok(Action::for_selection(move |_| name.clone(), can_execute, move |e, s| self.run(e, s)))
+    }
+}
+
+/// The built-in actions followed by the ones from the config file that could be used,
+/// with why the config file or some of its actions could not.
+pub fn all_actions() -> (Vec<Action>, Option<String>) {
+    let mut actions = crate::actions::actions();
+    let custom = match load() {
+        Ok(custom) => custom,
+        Err(e) => return (actions, Some(e)),
+    };
+    // one broken action doesn't take the others with it
+    let mut errors = vec![];
+    for action in custom {
+        match action.into_action() {
+            Ok(action) => actions.push(action),
+            Err(e) => errors.push(e),
+        }
+    }
+    let error = (!errors.is_empty()).then(|| format!("skipped actions: {}", errors.join("; ")));
+    (actions, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+
+    fn entry(path: &str) -> FileEntry {
+        FileEntry {
+            len: 0,
+            file_type: crate::files::FileType::File,
+            created: Default::default(),
+            modified: Default::default(),
+            accessed: Default::default(),
+            path: path.to_string(),
+            file_name: Path::new(path).file_name().unwrap().to_str().unwrap().to_string(),
+            symlink_target: None,
+            ignored: false,
+            archive: None,
+            mime: OnceLock::new(),
+            sniffed_mime: OnceLock::new(),
+        }
+    }
+
+    fn action(command: &str) -> CustomAction {
+        CustomAction {
+            name: "test".to_string(),
+            command: command.to_string(),
+            working_dir: None,
+            restriction: None,
+            selection: false,
+            capture_output: false,
+        }
+    }
+
+    fn args(command: &Command) -> Vec<String> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|a| a.to_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn placeholders_refer_to_the_first_entry() {
+        let (a, b) = (entry("/docs/a b.txt"), entry("/docs/c.txt"));
+        let entries = [&a, &b];
+        assert_eq!(expand_word("{paths}", &entries), vec!["/docs/a b.txt", "/docs/c.txt"]);
+        assert_eq!(expand_word("--files={paths}", &entries), vec!["--files=/docs/a b.txt /docs/c.txt"]);
+        assert_eq!(expand_word("{dir}/{name}.bak", &entries), vec!["/docs/a b.txt.bak"]);
+        assert_eq!(expand_word("--in={path}", &entries), vec!["--in=/docs/a b.txt"]);
+        assert_eq!(expand_word("{name}", &[]), vec!["{name}"]);
+        assert!(expand_word("{paths}", &[]).is_empty());
+    }
+
+    #[test]
+    fn commands_are_split_before_placeholders_are_replaced() {
+        let file = entry("/docs/a b.txt");
+        let command = action(r#"sh -c 'echo "$1"' - {path} "{name} copy""#).command(&[&file]).unwrap();
+        assert_eq!(args(&command), vec!["sh", "-c", "echo \"$1\"", "-", "/docs/a b.txt", "a b.txt copy"]);
+        assert_eq!(command.get_current_dir(), Some(Path::new("/docs")));
+
+        let mut custom = action("ls");
+        custom.working_dir = Some("{dir}/sub".to_string());
+        assert_eq!(custom.command(&[&file]).unwrap().get_current_dir(), Some(Path::new("/docs/sub")));
+
+        assert_eq!(action("echo 'open").command(&[&file]).unwrap_err(), "invalid command echo 'open");
+        assert_eq!(action("").command(&[&file]).unwrap_err(), "empty command in test");
+    }
+
+    #[test]
+    fn per_entry_actions_get_one_command_each() {
+        let (a, b) = (entry("/docs/a"), entry("/docs/b"));
+        let labelled = |custom: &CustomAction| -> Vec<(String, Vec<String>)> {
+            custom
+                .commands(&[&a, &b])
+                .unwrap()
+                .iter()
+                .map(|(label, command)| (label.clone(), args(command)))
+                .collect()
+        };
+        let mut custom = action("wc {paths}");
+        assert_eq!(
+            labelled(&custom),
+            vec![
+                ("a".to_string(), vec!["wc".to_string(), "/docs/a".to_string()]),
+                ("b".to_string(), vec!["wc".to_string(), "/docs/b".to_string()]),
+            ]
+        );
+        custom.selection = true;
+        let all = vec!["wc".to_string(), "/docs/a".to_string(), "/docs/b".to_string()];
+        assert_eq!(labelled(&custom), vec![("test".to_string(), all)]);
+    }
+
+    #[test]
+    fn invalid_restrictions_name_their_action() {
+        let mut custom = action("ls");
+        custom.restriction = Some("file and".to_string());
+        assert_eq!(custom.into_action().err().unwrap(), "test: unexpected end");
+
+        let mut custom = action("ls");
+        custom.restriction = Some("folder".to_string());
+        let action = custom.into_action().unwrap();
+        let file = entry("/docs/a");
+        assert!(!(action.can_execute)(&vec![&file], false));
+    }
+}
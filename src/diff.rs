@@ -0,0 +1,355 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+};
+
+use similar::{capture_diff_slices, group_diff_ops, Algorithm, ChangeTag, DiffTag, TextDiff};
+
+use crate::job::{Job, Progress};
+
+const CONTEXT_LINES: usize = 3;
+/// Larger files are only compared byte by byte.
+pub const MAX_TEXT_DIFF_SIZE: u64 = 8 * 1024 * 1024;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub struct Side {
+    pub number: usize,
+    pub changed: bool,
+    /// The line split into parts, `true` marks parts that differ within the line.
+    pub segments: Vec<(bool, String)>,
+}
+
+#[derive(Debug)]
+pub struct Row {
+    pub left: Option<Side>,
+    pub right: Option<Side>,
+}
+
+#[derive(Debug)]
+pub struct Hunk {
+    pub header: String,
+    pub rows: Vec<Row>,
+}
+
+#[derive(Debug)]
+pub enum FileDiff {
+    Text(Vec<Hunk>),
+    Binary {
+        first_difference: Option<u64>,
+        left_len: u64,
+        right_len: u64,
+    },
+}
+
+fn is_binary(content: &[u8]) -> bool {
+    content[..content.len().min(8000)].contains(&0) || std::str::from_utf8(content).is_err()
+}
+
+fn normalize(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn side(number: usize, changed: bool, line: &str) -> Side {
+    Side {
+        number,
+        changed,
+        segments: vec![(false, line.to_string())],
+    }
+}
+
+/// Splits a changed line pair into segments, emphasizing the differing words.
+fn inline_sides(left_number: usize, left: &str, right_number: usize, right: &str) -> Row {
+    let diff = TextDiff::from_words(left, right);
+    let mut left_segments = vec![];
+    let mut right_segments = vec![];
+    for change in diff.iter_all_changes() {
+        let value = change.value().to_string();
+        match change.tag() {
+            ChangeTag::Equal => {
+                left_segments.push((false, value.clone()));
+                right_segments.push((false, value));
+            }
+            ChangeTag::Delete => left_segments.push((true, value)),
+            ChangeTag::Insert => right_segments.push((true, value)),
+        }
+    }
+    Row {
+        left: Some(Side {
+            number: left_number,
+            changed: true,
+            segments: left_segments,
+        }),
+        right: Some(Side {
+            number: right_number,
+            changed: true,
+            segments: right_segments,
+        }),
+    }
+}
+
+pub fn diff_text(left: &str, right: &str, ignore_whitespace: bool) -> Vec<Hunk> {
+    let left: Vec<&str> = left.lines().collect();
+    let right: Vec<&str> = right.lines().collect();
+    let ops = if ignore_whitespace {
+        let l: Vec<_> = left.iter().map(|l| normalize(l)).collect();
+        let r: Vec<_> = right.iter().map(|l| normalize(l)).collect();
+        capture_diff_slices(Algorithm::Myers, &l, &r)
+    } else {
+        capture_diff_slices(Algorithm::Myers, &left, &right)
+    };
+
+    let mut hunks = vec![];
+    for group in group_diff_ops(ops, CONTEXT_LINES) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old = first.old_range().start..last.old_range().end;
+        let new = first.new_range().start..last.new_range().end;
+        let mut rows = vec![];
+        for op in group.iter() {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            match tag {
+                DiffTag::Equal => {
+                    for (o, n) in old_range.zip(new_range) {
+                        rows.push(Row {
+                            left: Some(side(o + 1, false, left[o])),
+                            right: Some(side(n + 1, false, right[n])),
+                        });
+                    }
+                }
+                DiffTag::Delete => {
+                    for o in old_range {
+                        rows.push(Row {
+                            left: Some(side(o + 1, true, left[o])),
+                            right: None,
+                        });
+                    }
+                }
+                DiffTag::Insert => {
+                    for n in new_range {
+                        rows.push(Row {
+                            left: None,
+                            right: Some(side(n + 1, true, right[n])),
+                        });
+                    }
+                }
+                DiffTag::Replace => {
+                    let len = old_range.len().max(new_range.len());
+                    for i in 0..len {
+                        let o = old_range.start + i;
+                        let n = new_range.start + i;
+                        rows.push(match (old_range.contains(&o), new_range.contains(&n)) {
+                            (true, true) => inline_sides(o + 1, left[o], n + 1, right[n]),
+                            (true, false) => Row {
+                                left: Some(side(o + 1, true, left[o])),
+                                right: None,
+                            },
+                            _ => Row {
+                                left: None,
+                                right: Some(side(n + 1, true, right[n])),
+                            },
+                        });
+                    }
+                }
+            }
+        }
+        hunks.push(Hunk {
+            header: format!(
+                "@@ -{},{} +{},{} @@",
+                old.start + 1,
+                old.len(),
+                new.start + 1,
+                new.len()
+            ),
+            rows,
+        });
+    }
+    hunks
+}
+
+/// Returns the offset of the first byte that differs, `None` for identical contents.
+pub fn first_difference(left: &[u8], right: &[u8]) -> Option<u64> {
+    match left.iter().zip(right).position(|(a, b)| a != b) {
+        Some(offset) => Some(offset as u64),
+        None if left.len() != right.len() => Some(left.len().min(right.len()) as u64),
+        None => None,
+    }
+}
+
+/// Reads until `buf` is full or the reader is exhausted, returning the bytes read.
+fn read_chunk(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Compares two readers chunk by chunk without holding either in memory.
+fn stream_first_difference(
+    mut left: impl Read,
+    mut right: impl Read,
+    progress: &Progress,
+) -> io::Result<Option<u64>> {
+    let mut left_buf = vec![0; CHUNK_SIZE];
+    let mut right_buf = vec![0; CHUNK_SIZE];
+    let mut offset = 0u64;
+    loop {
+        if progress.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        let l = read_chunk(&mut left, &mut left_buf)?;
+        let r = read_chunk(&mut right, &mut right_buf)?;
+        if let Some(at) = first_difference(&left_buf[..l], &right_buf[..r]) {
+            return Ok(Some(offset + at));
+        }
+        if l == 0 {
+            return Ok(None);
+        }
+        offset += l as u64;
+        progress.add((l + r) as u64);
+    }
+}
+
+pub fn diff_files(left: &str, right: &str, ignore_whitespace: bool, progress: &Progress) -> io::Result<FileDiff> {
+    let left_len = std::fs::metadata(left)?.len();
+    let right_len = std::fs::metadata(right)?.len();
+    progress.set_total(left_len + right_len);
+
+    if left_len <= MAX_TEXT_DIFF_SIZE && right_len <= MAX_TEXT_DIFF_SIZE {
+        progress.set_message(format!("reading {}", left));
+        let left_content = std::fs::read(left)?;
+        progress.add(left_content.len() as u64);
+        progress.set_message(format!("reading {}", right));
+        let right_content = std::fs::read(right)?;
+        progress.add(right_content.len() as u64);
+        if !is_binary(&left_content) && !is_binary(&right_content) {
+            progress.set_message("comparing");
+            return Ok(FileDiff::Text(diff_text(
+                std::str::from_utf8(&left_content).unwrap_or_default(),
+                std::str::from_utf8(&right_content).unwrap_or_default(),
+                ignore_whitespace,
+            )));
+        }
+        return Ok(FileDiff::Binary {
+            first_difference: first_difference(&left_content, &right_content),
+            left_len: left_content.len() as u64,
+            right_len: right_content.len() as u64,
+        });
+    }
+
+    progress.set_message("comparing");
+    let first_difference = stream_first_difference(File::open(left)?, File::open(right)?, progress)?;
+    Ok(FileDiff::Binary {
+        first_difference,
+        left_len,
+        right_len,
+    })
+}
+
+#[derive(Debug)]
+pub struct CompareFiles {
+    pub left: String,
+    pub right: String,
+    pub ignore_whitespace: bool,
+    pub unified: bool,
+    pub current_hunk: usize,
+    pub scroll_to_hunk: bool,
+    pub diff: Job<io::Result<FileDiff>>,
+}
+
+impl CompareFiles {
+    pub fn new(left: String, right: String) -> Self {
+        let diff = Self::spawn(&left, &right, false);
+        Self {
+            left,
+            right,
+            ignore_whitespace: false,
+            unified: false,
+            current_hunk: 0,
+            scroll_to_hunk: false,
+            diff,
+        }
+    }
+
+    fn spawn(left: &str, right: &str, ignore_whitespace: bool) -> Job<io::Result<FileDiff>> {
+        let left = left.to_string();
+        let right = right.to_string();
+        Job::spawn(move |p| diff_files(&left, &right, ignore_whitespace, p))
+    }
+
+    pub fn rerun(&mut self) {
+        self.diff.cancel();
+        self.diff = Self::spawn(&self.left, &self.right, self.ignore_whitespace);
+        self.current_hunk = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_diff_groups_changes_into_hunks() {
+        let left = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let right = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        let hunks = diff_text(left, right, false);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].header, "@@ -1,5 +1,5 @@");
+        let changed = &hunks[0].rows[1];
+        assert_eq!(changed.left.as_ref().unwrap().segments, [(true, "b".to_string())]);
+        assert_eq!(changed.right.as_ref().unwrap().segments, [(true, "B".to_string())]);
+        let added = hunks[1].rows.last().unwrap();
+        assert!(added.left.is_none());
+        assert_eq!(added.right.as_ref().unwrap().number, 11);
+    }
+
+    #[test]
+    fn text_diff_can_ignore_whitespace() {
+        assert_eq!(diff_text("a  b\n", "a b\n", false).len(), 1);
+        assert!(diff_text("a  b\n", "a b\n", true).is_empty());
+    }
+
+    #[test]
+    fn finds_the_first_difference() {
+        assert_eq!(first_difference(b"abc", b"abc"), None);
+        assert_eq!(first_difference(b"abc", b"abd"), Some(2));
+        assert_eq!(first_difference(b"ab", b"abc"), Some(2));
+    }
+
+    #[test]
+    fn streams_the_first_difference_across_chunks() {
+        let left = vec![0u8; CHUNK_SIZE * 2 + 10];
+        let mut right = left.clone();
+        right[CHUNK_SIZE + 5] = 1;
+        let progress = Progress::default();
+        assert_eq!(
+            stream_first_difference(&left[..], &right[..], &progress).unwrap(),
+            Some(CHUNK_SIZE as u64 + 5)
+        );
+        assert_eq!(stream_first_difference(&left[..], &left[..], &progress).unwrap(), None);
+        assert_eq!(
+            stream_first_difference(&left[..CHUNK_SIZE], &left[..], &progress).unwrap(),
+            Some(CHUNK_SIZE as u64)
+        );
+    }
+
+    #[test]
+    fn binary_files_are_not_diffed_as_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        std::fs::write(&left, b"ab\0cd").unwrap();
+        std::fs::write(&right, b"ab\0ce").unwrap();
+        let diff = diff_files(left.to_str().unwrap(), right.to_str().unwrap(), false, &Progress::default());
+        assert!(matches!(
+            diff.unwrap(),
+            FileDiff::Binary { first_difference: Some(4), left_len: 5, right_len: 5 }
+        ));
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, Read},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use walkdir::WalkDir;
+
+use crate::job::Progress;
+
+const PARTIAL_HASH_LEN: usize = 16 * 1024;
+
+#[derive(Debug)]
+pub struct DuplicateGroup {
+    pub len: u64,
+    pub paths: Vec<String>,
+    pub keep: usize,
+}
+
+impl DuplicateGroup {
+    pub fn reclaimable(&self) -> u64 {
+        self.len * (self.paths.len() as u64 - 1)
+    }
+
+    pub fn to_remove(&self) -> impl Iterator<Item = &String> {
+        self.paths
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != self.keep)
+            .map(|(_, p)| p)
+    }
+}
+
+fn partial_hash(path: &Path) -> io::Result<blake3::Hash> {
+    let mut buffer = vec![0; PARTIAL_HASH_LEN];
+    let mut file = File::open(path)?;
+    let mut read = 0;
+    while read < buffer.len() {
+        let n = file.read(&mut buffer[read..])?;
+        if n == 0 {
+            break;
+        }
+        read += n;
+    }
+    Ok(blake3::hash(&buffer[..read]))
+}
+
+fn full_hash(path: &Path) -> io::Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?)?;
+    Ok(hasher.finalize())
+}
+
+fn regroup(
+    groups: Vec<Vec<PathBuf>>,
+    progress: &Progress,
+    hash: impl Fn(&Path) -> io::Result<blake3::Hash>,
+) -> Vec<Vec<PathBuf>> {
+    let mut result = vec![];
+    for group in groups {
+        let mut by_hash: HashMap<blake3::Hash, Vec<PathBuf>> = HashMap::new();
+        for path in group {
+            if progress.is_cancelled() {
+                return vec![];
+            }
+            // unreadable files simply can't be proven to be duplicates
+            if let Ok(h) = hash(&path) {
+                by_hash.entry(h).or_default().push(path);
+            }
+            progress.add(1);
+        }
+        result.extend(by_hash.into_values().filter(|g| g.len() > 1));
+    }
+    result
+}
+
+/// Finds files with identical content below `roots`, comparing size first,
+/// then a hash of the first few KB and finally a hash of the full content.
+pub fn find_duplicates(roots: &[String], progress: &Progress) -> Vec<DuplicateGroup> {
+    progress.set_message("scanning");
+    // hard links share their content, deleting one of them would not free anything
+    let mut seen: HashSet<(u64, u64)> = HashSet::new();
+    let mut by_len: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for root in roots {
+        for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
+            if progress.is_cancelled() {
+                return vec![];
+            }
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.len() == 0 || !seen.insert((meta.dev(), meta.ino())) {
+                continue;
+            }
+            by_len
+                .entry(meta.len())
+                .or_default()
+                .push(entry.into_path());
+        }
+    }
+
+    let candidates: Vec<_> = by_len.into_values().filter(|g| g.len() > 1).collect();
+    progress.set_message("comparing beginnings");
+    progress.set_total(candidates.iter().map(|g| g.len() as u64).sum());
+    let candidates = regroup(candidates, progress, partial_hash);
+
+    progress.set_message("comparing contents");
+    progress.set_total(candidates.iter().map(|g| g.len() as u64).sum());
+    let candidates = regroup(candidates, progress, full_hash);
+
+    let mut groups: Vec<_> = candidates
+        .into_iter()
+        .map(|mut paths| {
+            paths.sort();
+            DuplicateGroup {
+                len: fs::metadata(&paths[0]).map(|m| m.len()).unwrap_or_default(),
+                paths: paths
+                    .into_iter()
+                    .map(|p| p.to_str().unwrap_or_default().to_string())
+                    .collect(),
+                keep: 0,
+            }
+        })
+        .collect();
+    groups.sort_by_key(|g| std::cmp::Reverse(g.reclaimable()));
+    groups
+}
+
+pub fn trash_duplicates(group: &DuplicateGroup) -> anyhow::Result<()> {
+    trash::delete_all(group.to_remove())?;
+    Ok(())
+}
+
+/// Replaces every duplicate of the kept file with a hard link to it.
+pub fn link_duplicates(group: &DuplicateGroup) -> io::Result<()> {
+    let keep = Path::new(&group.paths[group.keep]);
+    for path in group.to_remove() {
+        let path = Path::new(path);
+        let temp = path.with_file_name(format!(
+            ".{}.mac-explorer-link",
+            path.file_name().unwrap_or_default().to_str().unwrap_or_default()
+        ));
+        fs::hard_link(keep, &temp)?;
+        if let Err(e) = fs::rename(&temp, path) {
+            let _ = fs::remove_file(&temp);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(root: &Path) -> Vec<DuplicateGroup> {
+        find_duplicates(&[root.to_str().unwrap().to_string()], &Progress::default())
+    }
+
+    #[test]
+    fn groups_files_with_the_same_content() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a"), "same content").unwrap();
+        fs::write(dir.path().join("sub/b"), "same content").unwrap();
+        // same length, different content
+        fs::write(dir.path().join("c"), "same_content").unwrap();
+        fs::write(dir.path().join("empty1"), "").unwrap();
+        fs::write(dir.path().join("empty2"), "").unwrap();
+
+        let groups = find(dir.path());
+        assert_eq!(groups.len(), 1);
+        let names: Vec<_> = groups[0]
+            .paths
+            .iter()
+            .map(|p| Path::new(p).strip_prefix(dir.path()).unwrap())
+            .collect();
+        assert_eq!(names, [Path::new("a"), Path::new("sub/b")]);
+        assert_eq!(groups[0].reclaimable(), 12);
+    }
+
+    #[test]
+    fn hard_links_are_one_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a"), "content").unwrap();
+        fs::hard_link(dir.path().join("a"), dir.path().join("b")).unwrap();
+        assert!(find(dir.path()).is_empty());
+
+        fs::write(dir.path().join("c"), "content").unwrap();
+        let groups = find(dir.path());
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].paths.len(), 2);
+    }
+
+    #[test]
+    fn linking_keeps_the_chosen_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a"), "content").unwrap();
+        fs::write(dir.path().join("b"), "content").unwrap();
+        let mut groups = find(dir.path());
+        groups[0].keep = 1;
+        link_duplicates(&groups[0]).unwrap();
+
+        let a = fs::metadata(dir.path().join("a")).unwrap();
+        let b = fs::metadata(dir.path().join("b")).unwrap();
+        assert_eq!(a.ino(), b.ino());
+        assert!(find(dir.path()).is_empty());
+    }
+}
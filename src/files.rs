@@ -1,16 +1,36 @@
-use std::{cmp::Ordering, fs, io, path::Path, sync::Arc};
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fs, io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
 use chrono::{DateTime, Utc};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::Gitignore;
 
 use crate::tab::TabSorting;
 
 
-pub fn get_meta(path: &str) -> io::Result<FileEntry> {
-    let p = Path::new(path);
-    let meta = fs::metadata(path)?;
-    let file_type = meta.file_type();
-    let created: DateTime<Utc> = meta.created()?.into();
+fn entry_from_path(path: &Path) -> io::Result<FileEntry> {
+    let link_meta = fs::symlink_metadata(path)?;
+    let symlink_target = if link_meta.file_type().is_symlink() {
+        Some(
+            fs::read_link(path)?
+                .to_str()
+                .unwrap_or_default()
+                .to_string(),
+        )
+    } else {
+        None
+    };
+    // broken links are listed with the metadata of the link itself
+    let meta = fs::metadata(path).unwrap_or(link_meta);
+    let file_type = FileType::from(meta.file_type());
     let modified: DateTime<Utc> = meta.modified()?.into();
+    let created: DateTime<Utc> = meta.created().map(|c| c.into()).unwrap_or(modified);
     let accessed: DateTime<Utc> = meta.accessed()?.into();
     let len = meta.len();
 
@@ -20,38 +40,37 @@ pub fn get_meta(path: &str) -> io::Result<FileEntry> {
         created,
         modified,
         accessed,
-        path: path.to_string(),
-        file_name: p
+        path: path.to_str().unwrap_or_default().to_string(),
+        file_name: path
             .file_name()
             .unwrap_or_default()
             .to_str()
             .unwrap_or_default()
             .to_string(),
+        symlink_target,
+        ignored: false,
+        archive: None,
+        mime: OnceLock::new(),
+        sniffed_mime: OnceLock::new(),
     });
 }
 
+pub fn get_meta(path: &str) -> io::Result<FileEntry> {
+    let mut entry = entry_from_path(Path::new(path))?;
+    entry.path = path.to_string();
+    Ok(entry)
+}
+
 pub fn get_entries(path: &str) -> io::Result<Vec<FileEntry>> {
     let mut files = vec![];
     for entry in fs::read_dir(path)? {
-        let entry = entry?;
-        let path = entry.path().to_str().unwrap_or_default().to_string();
-        let file_name = entry.file_name().into_string().unwrap_or_default();
-        let meta = entry.metadata()?;
-        let file_type = meta.file_type();
-        let created: DateTime<Utc> = meta.created()?.into();
-        let modified: DateTime<Utc> = meta.modified()?.into();
-        let accessed: DateTime<Utc> = meta.accessed()?.into();
-        let len = meta.len();
-
-        files.push(FileEntry {
-            len,
-            file_type,
-            created,
-            modified,
-            accessed,
-            path,
-            file_name,
-        });
+        // an entry that vanished or can't be read shouldn't hide the rest of the folder
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if let Ok(file) = entry_from_path(&entry.path()) {
+            files.push(file);
+        }
     }
     files.sort_by(|a, b| {
         let type_ord = a.file_type.is_file().cmp(&b.file_type.is_file());
@@ -84,6 +103,9 @@ pub fn sort(files : &mut Vec<FileEntry>, sorting : &TabSorting){
                 crate::tab::SortingColumn::Size => {
                     return a.len.cmp(&b.len);
                 },
+                crate::tab::SortingColumn::Kind => {
+                    return a.kind().cmp(&b.kind()).then_with(|| a.file_name.cmp(&b.file_name));
+                },
             }
             
         }
@@ -113,36 +135,127 @@ pub fn bytes_to_human_readable(bytes: u64) -> String {
 #[derive(Debug)]
 pub struct FileEntry {
     pub len: u64,
-    pub file_type: fs::FileType,
+    pub file_type: FileType,
     pub created: DateTime<Utc>,
     pub modified: DateTime<Utc>,
     pub accessed: DateTime<Utc>,
     pub path: String,
     pub file_name: String,
+    pub symlink_target: Option<String>,
+    /// Matched by a `.gitignore` or `.ignore` file, only computed when the tab asks for it.
+    pub ignored: bool,
+    /// The archive this entry is stored in, `None` for entries on disk.
+    pub archive: Option<String>,
+    /// The MIME type by name, filled on first use by `mime()`.
+    pub mime: OnceLock<String>,
+    /// The MIME type by content, set once the tab's background sniffing gets to this entry.
+    pub sniffed_mime: OnceLock<String>,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Restriction {
-    None,
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
     File,
-    Folder,
-    Main,
-    Not(Box<Restriction>),
-    And(Box<Restriction>, Box<Restriction>),
+    Dir,
+    Symlink,
+}
+
+impl FileType {
+    pub fn is_file(&self) -> bool {
+        *self == FileType::File
+    }
+
+    pub fn is_dir(&self) -> bool {
+        *self == FileType::Dir
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        *self == FileType::Symlink
+    }
+}
+
+impl From<fs::FileType> for FileType {
+    fn from(file_type: fs::FileType) -> Self {
+        if file_type.is_dir() {
+            FileType::Dir
+        } else if file_type.is_symlink() {
+            FileType::Symlink
+        } else {
+            FileType::File
+        }
+    }
 }
 impl FileEntry {
-    pub fn fullfills(&self, restriction : &Restriction, is_main : bool) -> bool {
-        match restriction {
-            Restriction::None => true,
-            Restriction::File => self.file_type.is_file(),
-            Restriction::Folder => self.file_type.is_dir(),
-            Restriction::Main => is_main,
-            Restriction::Not(rec) => !self.fullfills(rec, is_main),
-            Restriction::And(a, b) => self.fullfills(a, is_main) && self.fullfills(b, is_main),
+    /// The sniffed MIME type once known, the one by name until then. Reading files is too
+    /// slow for the ui thread, and named pipes would block it forever.
+    pub fn mime(&self) -> &str {
+        if let Some(mime) = self.sniffed_mime.get() {
+            return mime;
         }
+        self.mime.get_or_init(|| {
+            if self.file_type.is_dir() {
+                crate::mime::DIRECTORY.to_string()
+            } else if self.is_broken_symlink() {
+                crate::mime::SYMLINK.to_string()
+            } else {
+                crate::mime::from_name(&self.file_name)
+            }
+        })
+    }
+
+    /// What the entry is in words, like "Folder" or "PNG image".
+    pub fn kind(&self) -> String {
+        crate::mime::describe(self.mime())
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.file_name.starts_with('.')
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.symlink_target.is_some()
     }
+
+    /// A link whose target doesn't exist, `file_type` is then the link's own type.
+    pub fn is_broken_symlink(&self) -> bool {
+        self.file_type.is_symlink()
+    }
+
+    /// The absolute path a symlink points to.
+    pub fn resolved_target(&self) -> Option<PathBuf> {
+        let target = Path::new(self.symlink_target.as_ref()?);
+        Some(Path::new(&self.path).parent()?.join(target))
+    }
+}
+
+pub fn copy_dir(src: &Path, dst: &Path, follow_links: bool) -> io::Result<()> {
+    let mut ancestors = HashSet::new();
+    copy_dir_inner(src, dst, follow_links, &mut ancestors)
+}
+
+fn copy_dir_inner(
+    src: &Path,
+    dst: &Path,
+    follow_links: bool,
+    ancestors: &mut HashSet<PathBuf>,
+) -> io::Result<()> {
+    // a followed link pointing to one of the folders being copied would recurse forever,
+    // the same folder reached through two links elsewhere is copied twice
+    let canonical = fs::canonicalize(src)?;
+    if ancestors.contains(&canonical) {
+        return Ok(());
+    }
+    ancestors.insert(canonical.clone());
+    let result = copy_dir_contents(src, dst, follow_links, ancestors);
+    ancestors.remove(&canonical);
+    result
 }
 
-pub fn copy_dir(src: &Path, dst: &Path) -> io::Result<()> {
+fn copy_dir_contents(
+    src: &Path,
+    dst: &Path,
+    follow_links: bool,
+    ancestors: &mut HashSet<PathBuf>,
+) -> io::Result<()> {
     // Create the destination directory if it doesn't exist
     if !dst.exists() {
         fs::create_dir_all(dst)?;
@@ -151,13 +264,21 @@ pub fn copy_dir(src: &Path, dst: &Path) -> io::Result<()> {
     // Read the entries within the source directory
     for entry in fs::read_dir(src)? {
         let entry = entry?;
-        let file_type = entry.file_type()?;
+        let mut file_type = entry.file_type()?;
         let src_path = entry.path();
         let dst_path = dst.join(entry.file_name());
 
+        if file_type.is_symlink() {
+            if !follow_links {
+                std::os::unix::fs::symlink(fs::read_link(&src_path)?, &dst_path)?;
+                continue;
+            }
+            file_type = fs::metadata(&src_path)?.file_type();
+        }
+
         // If it's a directory, recurse into it
         if file_type.is_dir() {
-            copy_dir(&src_path, &dst_path)?;
+            copy_dir_inner(&src_path, &dst_path, follow_links, ancestors)?;
         }
         // Otherwise, copy the file
         else {
@@ -166,4 +287,251 @@ pub fn copy_dir(src: &Path, dst: &Path) -> io::Result<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Copies a file, folder or link, either recreating links or copying what they point to.
+pub fn copy_entry(src: &Path, dst: &Path, follow_links: bool) -> io::Result<()> {
+    if !follow_links && fs::symlink_metadata(src)?.file_type().is_symlink() {
+        return std::os::unix::fs::symlink(fs::read_link(src)?, dst);
+    }
+    if src.is_dir() {
+        copy_dir(src, dst, follow_links)
+    } else {
+        fs::copy(src, dst).map(|_| ())
+    }
+}
+
+/// Fails when `dst` exists, unless it is `src` itself like when only the case of a
+/// name changes on a case-insensitive disk.
+pub fn ensure_free(src: &Path, dst: &Path) -> io::Result<()> {
+    let Ok(existing) = fs::symlink_metadata(dst) else {
+        return Ok(());
+    };
+    let source = fs::symlink_metadata(src)?;
+    if (existing.dev(), existing.ino()) == (source.dev(), source.ino()) {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("{} already exists", dst.display()),
+    ))
+}
+
+/// Moves an entry; when following links, a link is replaced by a copy of its target.
+/// An existing entry at `dst` is never replaced.
+pub fn move_entry(src: &Path, dst: &Path, follow_links: bool) -> io::Result<()> {
+    ensure_free(src, dst)?;
+    if follow_links && fs::symlink_metadata(src)?.file_type().is_symlink() {
+        copy_entry(src, dst, true)?;
+        return fs::remove_file(src);
+    }
+    fs::rename(src, dst)
+}
+
+/// Include/exclude patterns, separated by commas or newlines. A pattern
+/// without a `/` matches a file or folder name at any depth.
+#[derive(Debug, Clone)]
+pub struct GlobFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl GlobFilter {
+    pub fn new(include: &str, exclude: &str) -> Result<Self, globset::Error> {
+        let include = Self::build(include)?;
+        Ok(Self {
+            include: (!include.is_empty()).then_some(include),
+            exclude: Self::build(exclude)?,
+        })
+    }
+
+    fn build(patterns: &str) -> Result<GlobSet, globset::Error> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns.split([',', '\n']).map(str::trim) {
+            let pattern = pattern.trim_end_matches('/');
+            if pattern.is_empty() {
+                continue;
+            }
+            builder.add(Glob::new(pattern)?);
+            if !pattern.contains('/') {
+                builder.add(Glob::new(&format!("**/{}", pattern))?);
+            }
+        }
+        builder.build()
+    }
+
+    /// Whether `relative` or one of the folders containing it is excluded.
+    pub fn is_excluded(&self, relative: &Path) -> bool {
+        relative
+            .ancestors()
+            .filter(|p| !p.as_os_str().is_empty())
+            .any(|p| self.exclude.is_match(p))
+    }
+
+    pub fn matches(&self, relative: &Path, is_dir: bool) -> bool {
+        if self.is_excluded(relative) {
+            return false;
+        }
+        is_dir || self.include.as_ref().is_none_or(|i| i.is_match(relative))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IgnoreMode {
+    Show,
+    Dim,
+    Hide,
+}
+
+/// Which entries a tab lists, also used for operations that walk folders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListingFilter {
+    pub show_hidden: bool,
+    pub ignored: IgnoreMode,
+}
+
+impl Default for ListingFilter {
+    fn default() -> Self {
+        Self {
+            show_hidden: false,
+            ignored: IgnoreMode::Show,
+        }
+    }
+}
+
+impl ListingFilter {
+    /// Marks ignored entries and drops the ones the filter hides.
+    pub fn apply(&self, dir: &str, entries: &mut Vec<FileEntry>) {
+        if !self.show_hidden {
+            entries.retain(|e| !e.is_hidden());
+        }
+        if self.ignored == IgnoreMode::Show {
+            return;
+        }
+        let rules = IgnoreRules::for_dir(Path::new(dir));
+        for entry in entries.iter_mut() {
+            entry.ignored = rules.is_ignored(Path::new(&entry.path), entry.file_type.is_dir());
+        }
+        if self.ignored == IgnoreMode::Hide {
+            entries.retain(|e| !e.ignored);
+        }
+    }
+
+    /// A recursive walker that skips what the filter hides.
+    pub fn walk(&self, root: &Path, follow_links: bool) -> ignore::Walk {
+        let respect_ignore = self.ignored == IgnoreMode::Hide;
+        ignore::WalkBuilder::new(root)
+            .hidden(!self.show_hidden)
+            .ignore(respect_ignore)
+            .git_ignore(respect_ignore)
+            .git_exclude(respect_ignore)
+            .git_global(false)
+            .parents(respect_ignore)
+            .require_git(false)
+            .follow_links(follow_links)
+            .build()
+    }
+}
+
+/// Walks every source and everything below it, skipping what `filter` hides or `exclude` matches.
+/// Entries come with their path relative to `base`.
+pub fn walk_sources(
+    base: &Path,
+    sources: &[PathBuf],
+    follow_links: bool,
+    filter: ListingFilter,
+    exclude: &GlobFilter,
+) -> Vec<(ignore::DirEntry, PathBuf)> {
+    let mut entries = vec![];
+    for source in sources {
+        for entry in filter.walk(source, follow_links).filter_map(|e| e.ok()) {
+            let relative = entry.path().strip_prefix(base).unwrap_or(entry.path()).to_path_buf();
+            if !relative.as_os_str().is_empty() && !exclude.is_excluded(&relative) {
+                entries.push((entry, relative));
+            }
+        }
+    }
+    entries
+}
+
+/// The `.ignore` and `.gitignore` files of a folder and its parents, nearest first.
+pub struct IgnoreRules {
+    matchers: Vec<Gitignore>,
+}
+
+impl IgnoreRules {
+    pub fn for_dir(dir: &Path) -> Self {
+        let mut matchers = vec![];
+        for ancestor in dir.ancestors() {
+            for name in [".ignore", ".gitignore"] {
+                let file = ancestor.join(name);
+                if file.is_file() {
+                    matchers.push(Gitignore::new(file).0);
+                }
+            }
+            // ignore files outside of the repository don't apply
+            if ancestor.join(".git").exists() {
+                break;
+            }
+        }
+        Self { matchers }
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for matcher in self.matchers.iter() {
+            let m = matcher.matched_path_or_any_parents(path, is_dir);
+            if !m.is_none() {
+                return m.is_ignore();
+            }
+        }
+        false
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_filter_matches_names_at_any_depth() {
+        let filter = GlobFilter::new("*.rs, docs/*.md", "target, *.bak").unwrap();
+        assert!(filter.matches(Path::new("main.rs"), false));
+        assert!(filter.matches(Path::new("src/deep/lib.rs"), false));
+        assert!(filter.matches(Path::new("docs/readme.md"), false));
+        assert!(!filter.matches(Path::new("other/readme.md"), false));
+        assert!(!filter.matches(Path::new("notes.txt"), false));
+        // folders are kept so that the files inside them can still match
+        assert!(filter.matches(Path::new("src"), true));
+    }
+
+    #[test]
+    fn glob_filter_excludes_everything_inside_excluded_folders() {
+        let filter = GlobFilter::new("", "target/\nbuild/cache, *.bak").unwrap();
+        assert!(filter.matches(Path::new("notes.txt"), false));
+        assert!(!filter.matches(Path::new("target"), true));
+        assert!(!filter.matches(Path::new("sub/target/debug/app"), false));
+        assert!(!filter.matches(Path::new("build/cache/x"), false));
+        assert!(filter.matches(Path::new("sub/build/cache/x"), false));
+        assert!(!filter.matches(Path::new("a/b.bak"), false));
+    }
+
+    #[test]
+    fn copying_with_links_followed_keeps_shared_folders_and_cuts_cycles() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("shared")).unwrap();
+        fs::write(src.join("shared/file"), "content").unwrap();
+        std::os::unix::fs::symlink("shared", src.join("first")).unwrap();
+        std::os::unix::fs::symlink("shared", src.join("second")).unwrap();
+        std::os::unix::fs::symlink("..", src.join("shared/up")).unwrap();
+
+        let dst = dir.path().join("dst");
+        copy_dir(&src, &dst, true).unwrap();
+        for name in ["shared", "first", "second"] {
+            assert_eq!(fs::read_to_string(dst.join(name).join("file")).unwrap(), "content");
+            // the link back to the copied folder is left out
+            assert!(!dst.join(name).join("up").exists());
+        }
+    }
+}
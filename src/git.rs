@@ -0,0 +1,281 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use crate::job::Job;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    Conflicted,
+    Modified,
+    Staged,
+    Untracked,
+    Ignored,
+    /// A folder with changed entries somewhere inside.
+    ContainsChanges,
+}
+
+impl FileStatus {
+    pub fn letter(&self) -> &'static str {
+        match self {
+            FileStatus::Conflicted => "U",
+            FileStatus::Modified => "M",
+            FileStatus::Staged => "S",
+            FileStatus::Untracked => "?",
+            FileStatus::Ignored => "!",
+            FileStatus::ContainsChanges => "•",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileStatus::Conflicted => "conflicted",
+            FileStatus::Modified => "modified",
+            FileStatus::Staged => "staged",
+            FileStatus::Untracked => "untracked",
+            FileStatus::Ignored => "ignored",
+            FileStatus::ContainsChanges => "contains changes",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GitStatus {
+    pub branch: Option<String>,
+    /// Paths relative to the work tree root, folders without a trailing slash.
+    pub files: HashMap<PathBuf, FileStatus>,
+}
+
+pub fn repo_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|p| p.join(".git").exists())
+        .map(|p| p.to_path_buf())
+}
+
+fn parse_status(xy: &[u8]) -> FileStatus {
+    match (xy[0], xy[1]) {
+        (b'?', b'?') => FileStatus::Untracked,
+        (b'!', b'!') => FileStatus::Ignored,
+        (b'U', _) | (_, b'U') | (b'A', b'A') | (b'D', b'D') => FileStatus::Conflicted,
+        (_, b' ') => FileStatus::Staged,
+        _ => FileStatus::Modified,
+    }
+}
+
+pub fn status(root: &Path) -> io::Result<GitStatus> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["status", "--porcelain=v1", "-z", "--branch", "--ignored=matching"])
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(parse_porcelain(&output.stdout))
+}
+
+/// Parses the output of `git status --porcelain=v1 -z --branch`.
+fn parse_porcelain(output: &[u8]) -> GitStatus {
+    let mut status = GitStatus::default();
+    let mut records = output.split(|b| *b == 0);
+    while let Some(record) = records.next() {
+        if record.len() < 4 {
+            continue;
+        }
+        let path = String::from_utf8_lossy(&record[3..]).to_string();
+        if record.starts_with(b"## ") {
+            let branch = path.split("...").next().unwrap_or_default();
+            status.branch = Some(branch.trim_start_matches("No commits yet on ").to_string());
+            continue;
+        }
+        // renames and copies are followed by the original path
+        if record[0] == b'R' || record[0] == b'C' {
+            records.next();
+        }
+        status
+            .files
+            .insert(PathBuf::from(path.trim_end_matches('/')), parse_status(&record[..2]));
+    }
+    status
+}
+
+impl GitStatus {
+    /// The status of an entry, inherited from an untracked or ignored parent folder.
+    pub fn entry_status(&self, relative: &Path, is_dir: bool) -> Option<FileStatus> {
+        for ancestor in relative.ancestors() {
+            match self.files.get(ancestor) {
+                Some(s) if ancestor == relative => return Some(*s),
+                Some(s @ (FileStatus::Untracked | FileStatus::Ignored)) => return Some(*s),
+                _ => {}
+            }
+        }
+        if is_dir
+            && self
+                .files
+                .iter()
+                .any(|(p, s)| *s != FileStatus::Ignored && p.starts_with(relative))
+        {
+            return Some(FileStatus::ContainsChanges);
+        }
+        None
+    }
+}
+
+#[derive(Debug)]
+pub struct GitState {
+    pub root: PathBuf,
+    pub status: Option<GitStatus>,
+    job: Option<Job<io::Result<GitStatus>>>,
+    last_refresh: Instant,
+}
+
+impl GitState {
+    pub fn for_path(path: &str) -> Option<Self> {
+        let root = repo_root(Path::new(path))?;
+        let mut state = Self {
+            root,
+            status: None,
+            job: None,
+            last_refresh: Instant::now(),
+        };
+        state.refresh();
+        Some(state)
+    }
+
+    pub fn refresh(&mut self) {
+        let root = self.root.clone();
+        self.job = Some(Job::spawn(move |_| status(&root)));
+        self.last_refresh = Instant::now();
+    }
+
+    /// Picks up a finished status and starts a new one once the current is stale.
+    /// Returns whether a status is still being computed.
+    pub fn update(&mut self) -> bool {
+        if let Some(job) = &mut self.job {
+            let Some(result) = job.poll() else {
+                return true;
+            };
+            if let Ok(status) = std::mem::replace(result, Ok(GitStatus::default())) {
+                self.status = Some(status);
+            }
+            self.job = None;
+        }
+        if self.last_refresh.elapsed() > REFRESH_INTERVAL {
+            self.refresh();
+        }
+        false
+    }
+
+    pub fn branch(&self) -> Option<&str> {
+        self.status.as_ref()?.branch.as_deref()
+    }
+
+    pub fn entry_status(&self, path: &str, is_dir: bool) -> Option<FileStatus> {
+        let relative = Path::new(path).strip_prefix(&self.root).ok()?;
+        self.status.as_ref()?.entry_status(relative, is_dir)
+    }
+}
+
+fn run(root: &Path, args: &[&str], paths: &[&Path]) -> Result<(), String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .arg("--")
+        .args(paths)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Runs a git command for the given paths, once per work tree they belong to.
+fn run_for_paths(args: &[&str], paths: &[String]) -> Result<(), String> {
+    let mut by_root: HashMap<PathBuf, Vec<&Path>> = HashMap::new();
+    for path in paths {
+        let path = Path::new(path);
+        let root = repo_root(path).ok_or_else(|| format!("{} is not in a git repository", path.display()))?;
+        by_root.entry(root).or_default().push(path);
+    }
+    for (root, paths) in by_root {
+        run(&root, args, &paths)?;
+    }
+    Ok(())
+}
+
+pub fn stage(paths: &[String]) -> Result<(), String> {
+    run_for_paths(&["add"], paths)
+}
+
+pub fn unstage(paths: &[String]) -> Result<(), String> {
+    run_for_paths(&["restore", "--staged"], paths)
+}
+
+/// Throws away unstaged changes of tracked files.
+pub fn discard(paths: &[String]) -> Result<(), String> {
+    run_for_paths(&["restore"], paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(output: &str) -> GitStatus {
+        parse_porcelain(output.as_bytes())
+    }
+
+    #[test]
+    fn reads_branch_and_file_states() {
+        let status = parse(
+            "## main...origin/main [ahead 1]\0M  staged.rs\0 M modified.rs\0MM both.rs\0\
+             ?? new.rs\0!! target/\0UU conflict.rs\0AA added.rs\0",
+        );
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        let state = |path: &str| status.files.get(Path::new(path)).copied();
+        assert_eq!(state("staged.rs"), Some(FileStatus::Staged));
+        assert_eq!(state("modified.rs"), Some(FileStatus::Modified));
+        assert_eq!(state("both.rs"), Some(FileStatus::Modified));
+        assert_eq!(state("new.rs"), Some(FileStatus::Untracked));
+        assert_eq!(state("target"), Some(FileStatus::Ignored));
+        assert_eq!(state("conflict.rs"), Some(FileStatus::Conflicted));
+        assert_eq!(state("added.rs"), Some(FileStatus::Conflicted));
+    }
+
+    #[test]
+    fn renames_are_listed_under_the_new_name() {
+        let status = parse("R  new name.rs\0old name.rs\0 M other.rs\0");
+        assert_eq!(status.files.len(), 2);
+        assert_eq!(status.files.get(Path::new("new name.rs")), Some(&FileStatus::Staged));
+        assert_eq!(status.files.get(Path::new("other.rs")), Some(&FileStatus::Modified));
+    }
+
+    #[test]
+    fn repositories_without_commits_have_a_branch() {
+        assert_eq!(parse("## No commits yet on main\0").branch.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn folders_show_what_is_inside() {
+        let status = parse(" M src/app.rs\0?? notes/\0!! target/\0");
+        let entry = |path: &str, is_dir: bool| status.entry_status(Path::new(path), is_dir);
+        assert_eq!(entry("src", true), Some(FileStatus::ContainsChanges));
+        assert_eq!(entry("src/app.rs", false), Some(FileStatus::Modified));
+        assert_eq!(entry("notes/todo.md", false), Some(FileStatus::Untracked));
+        assert_eq!(entry("target/debug", true), Some(FileStatus::Ignored));
+        // only ignored entries inside don't count as changes
+        assert_eq!(entry("", true), Some(FileStatus::ContainsChanges));
+        assert_eq!(entry("docs", true), None);
+    }
+}
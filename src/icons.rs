@@ -0,0 +1,452 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use egui::{ColorImage, TextureHandle, TextureOptions};
+use egui_extras::image::{load_image_bytes, load_svg_bytes_with_size};
+
+use crate::{archive::ArchiveFormat, files::FileEntry};
+
+/// Rasterized size of the icons, twice the row height so they stay sharp on retina screens.
+const SIZE: u32 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    C,
+    Cpp,
+    Java,
+    Go,
+    Shell,
+    Web,
+    Data,
+}
+
+/// Extensions of source files, the MIME database doesn't know many of them.
+const LANGUAGES: &[(&str, Language)] = &[
+    ("rs", Language::Rust),
+    ("py", Language::Python),
+    ("js", Language::JavaScript),
+    ("mjs", Language::JavaScript),
+    ("jsx", Language::JavaScript),
+    ("ts", Language::TypeScript),
+    ("tsx", Language::TypeScript),
+    ("c", Language::C),
+    ("h", Language::C),
+    ("cpp", Language::Cpp),
+    ("cc", Language::Cpp),
+    ("hpp", Language::Cpp),
+    ("java", Language::Java),
+    ("go", Language::Go),
+    ("sh", Language::Shell),
+    ("bash", Language::Shell),
+    ("zsh", Language::Shell),
+    ("html", Language::Web),
+    ("css", Language::Web),
+    ("json", Language::Data),
+    ("toml", Language::Data),
+    ("yaml", Language::Data),
+    ("yml", Language::Data),
+    ("xml", Language::Data),
+];
+
+impl Language {
+    fn of(entry: &FileEntry) -> Option<Language> {
+        let extension = Path::new(&entry.file_name).extension()?.to_str()?.to_lowercase();
+        LANGUAGES.iter().find(|(e, _)| *e == extension).map(|(_, l)| *l)
+    }
+
+    /// Tints the embedded code icon, roughly the colors the languages use for their logos.
+    fn color(&self) -> &'static str {
+        match self {
+            Language::Rust => "#ce422b",
+            Language::Python => "#3572a5",
+            Language::JavaScript => "#d4b100",
+            Language::TypeScript => "#3178c6",
+            Language::C => "#555555",
+            Language::Cpp => "#f34b7d",
+            Language::Java => "#b07219",
+            Language::Go => "#00add8",
+            Language::Shell => "#4eaa25",
+            Language::Web => "#e34c26",
+            Language::Data => "#8a6ac2",
+        }
+    }
+
+    /// Icon names of the freedesktop theme, most themes only have some of them.
+    fn theme_names(&self) -> &'static [&'static str] {
+        match self {
+            Language::Rust => &["text-rust", "text-x-rust"],
+            Language::Python => &["text-x-python"],
+            Language::JavaScript => &["application-javascript", "text-javascript"],
+            Language::TypeScript => &["application-typescript", "text-x-typescript"],
+            Language::C => &["text-x-csrc", "text-x-c"],
+            Language::Cpp => &["text-x-c++src", "text-x-cpp"],
+            Language::Java => &["text-x-java"],
+            Language::Go => &["text-x-go"],
+            Language::Shell => &["application-x-shellscript", "text-x-script"],
+            Language::Web => &["text-html", "text-css"],
+            Language::Data => &["application-json", "text-x-toml", "application-xml"],
+        }
+    }
+}
+
+/// The icon shown in front of an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Icon {
+    Folder,
+    Symlink,
+    Archive,
+    Image,
+    Audio,
+    Video,
+    Code(Language),
+    Text,
+    Document,
+    Executable,
+    File,
+}
+
+impl Icon {
+    pub fn of(entry: &FileEntry) -> Icon {
+        if entry.symlink_target.is_some() {
+            return Icon::Symlink;
+        }
+        if entry.file_type.is_dir() {
+            return Icon::Folder;
+        }
+        if ArchiveFormat::of(&entry.file_name).is_some() {
+            return Icon::Archive;
+        }
+        if let Some(language) = Language::of(entry) {
+            return Icon::Code(language);
+        }
+        let mime = entry.mime();
+        let (kind, sub) = mime.split_once('/').unwrap_or((mime, ""));
+        match (kind, sub) {
+            ("image", _) => Icon::Image,
+            ("audio", _) => Icon::Audio,
+            ("video", _) => Icon::Video,
+            ("application", "x-shellscript" | "x-sh") => Icon::Code(Language::Shell),
+            (
+                "application",
+                "x-executable" | "x-pie-executable" | "x-mach-binary" | "x-msdownload" | "vnd.microsoft.portable-executable",
+            ) => Icon::Executable,
+            (
+                "application",
+                "zip" | "gzip" | "x-gzip" | "x-tar" | "x-xz" | "zstd" | "x-bzip2" | "x-7z-compressed" | "x-rar"
+                | "vnd.rar",
+            ) => Icon::Archive,
+            ("application", "pdf" | "msword" | "rtf" | "epub+zip") => Icon::Document,
+            ("application", sub)
+                if sub.starts_with("vnd.openxmlformats")
+                    || sub.starts_with("vnd.oasis.opendocument")
+                    || sub.starts_with("vnd.ms-") =>
+            {
+                Icon::Document
+            }
+            ("text", _) => Icon::Text,
+            _ => Icon::File,
+        }
+    }
+
+    /// Names to look up in the freedesktop theme, the most specific first.
+    fn theme_names(&self) -> &'static [&'static str] {
+        match self {
+            Icon::Folder => &["folder"],
+            Icon::Symlink => &["inode-symlink", "emblem-symbolic-link"],
+            Icon::Archive => &["package-x-generic", "application-x-archive"],
+            Icon::Image => &["image-x-generic"],
+            Icon::Audio => &["audio-x-generic"],
+            Icon::Video => &["video-x-generic"],
+            Icon::Code(language) => language.theme_names(),
+            Icon::Text => &["text-x-generic"],
+            Icon::Document => &["x-office-document"],
+            Icon::Executable => &["application-x-executable"],
+            Icon::File => &["application-octet-stream", "unknown"],
+        }
+    }
+
+    fn embedded(&self) -> ColorImage {
+        let svg: &str = match self {
+            Icon::Folder => include_str!("../assets/icons/folder.svg"),
+            Icon::Symlink => include_str!("../assets/icons/symlink.svg"),
+            Icon::Archive => include_str!("../assets/icons/archive.svg"),
+            Icon::Image => include_str!("../assets/icons/image.svg"),
+            Icon::Audio => include_str!("../assets/icons/audio.svg"),
+            Icon::Video => include_str!("../assets/icons/video.svg"),
+            Icon::Code(language) => {
+                return rasterize(&include_str!("../assets/icons/code.svg").replace("currentColor", language.color()))
+            }
+            Icon::Text => include_str!("../assets/icons/text.svg"),
+            Icon::Document => include_str!("../assets/icons/document.svg"),
+            Icon::Executable => include_str!("../assets/icons/executable.svg"),
+            Icon::File => include_str!("../assets/icons/file.svg"),
+        };
+        rasterize(svg)
+    }
+}
+
+fn rasterize(svg: &str) -> ColorImage {
+    // the embedded icons are valid, an empty image would only hide a broken one
+    load_svg_bytes_with_size(svg.as_bytes(), Some(egui::SizeHint::Size(SIZE, SIZE)))
+        .unwrap_or_else(|_| ColorImage::new([SIZE as usize; 2], egui::Color32::TRANSPARENT))
+}
+
+fn load_file(path: &Path) -> Option<ColorImage> {
+    let bytes = fs::read(path).ok()?;
+    if path.extension().is_some_and(|e| e == "svg") {
+        load_svg_bytes_with_size(&bytes, Some(egui::SizeHint::Size(SIZE, SIZE))).ok()
+    } else {
+        load_image_bytes(&bytes).ok()
+    }
+}
+
+/// Where icon themes are installed, the user's own first.
+fn icon_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join(".icons"));
+    }
+    if let Some(data) = dirs::data_dir() {
+        dirs.push(data.join("icons"));
+    }
+    let system = std::env::var("XDG_DATA_DIRS").unwrap_or("/usr/local/share:/usr/share".to_string());
+    dirs.extend(system.split(':').filter(|s| !s.is_empty()).map(|d| Path::new(d).join("icons")));
+    dirs
+}
+
+/// The theme chosen in the GTK settings, the freedesktop way without asking a desktop service.
+fn configured_theme() -> Option<String> {
+    let settings = fs::read_to_string(dirs::config_dir()?.join("gtk-3.0").join("settings.ini")).ok()?;
+    settings.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "gtk-icon-theme-name").then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+#[derive(Debug)]
+struct ThemeDir {
+    path: PathBuf,
+    size: u32,
+    scalable: bool,
+}
+
+/// A freedesktop icon theme with the themes it inherits from.
+#[derive(Debug, Default)]
+pub struct IconTheme {
+    /// The directories of all themes in lookup order, the best size of each theme first.
+    dirs: Vec<ThemeDir>,
+}
+
+impl IconTheme {
+    /// Loads the configured theme, `None` on systems without icon themes like macOS.
+    pub fn system() -> Option<IconTheme> {
+        let roots = icon_dirs();
+        let mut themes = vec![];
+        let mut pending: Vec<String> = configured_theme().into_iter().collect();
+        pending.extend(["Adwaita".to_string(), "breeze".to_string(), "hicolor".to_string()]);
+        let mut theme = IconTheme::default();
+        while !pending.is_empty() {
+            let name = pending.remove(0);
+            if themes.contains(&name) {
+                continue;
+            }
+            themes.push(name.clone());
+            let Some(root) = roots.iter().map(|r| r.join(&name)).find(|r| r.join("index.theme").is_file()) else {
+                continue;
+            };
+            let Ok(index) = fs::read_to_string(root.join("index.theme")) else {
+                continue;
+            };
+            let (dirs, inherits) = parse_index(&root, &index);
+            theme.dirs.extend(dirs);
+            // inherited themes come right after this one, before the fallbacks
+            for (i, parent) in inherits.into_iter().enumerate() {
+                pending.insert(i, parent);
+            }
+        }
+        (!theme.dirs.is_empty()).then_some(theme)
+    }
+
+    pub fn find(&self, names: &[&str]) -> Option<PathBuf> {
+        names.iter().find_map(|name| {
+            self.dirs.iter().find_map(|dir| {
+                ["svg", "png"]
+                    .iter()
+                    .map(|ext| dir.path.join(format!("{}.{}", name, ext)))
+                    .find(|p| p.is_file())
+            })
+        })
+    }
+}
+
+/// The icon directories of one theme sorted by how close they are to `SIZE`, and its parents.
+fn parse_index(root: &Path, index: &str) -> (Vec<ThemeDir>, Vec<String>) {
+    let mut dirs = vec![];
+    let mut inherits = vec![];
+    let mut section = String::new();
+    for line in index.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        if section == "Icon Theme" && key == "Inherits" {
+            inherits = value.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
+        } else if section != "Icon Theme" && key == "Size" {
+            // only mimetypes and places hold icons for files, not actions or apps
+            let context_ok = section.contains("mimetypes") || section.contains("places");
+            if context_ok && root.join(&section).is_dir() {
+                dirs.push(ThemeDir {
+                    path: root.join(&section),
+                    size: value.parse().unwrap_or_default(),
+                    scalable: section.contains("scalable"),
+                });
+            }
+        }
+    }
+    dirs.sort_by_key(|d| if d.scalable { 0 } else { d.size.abs_diff(SIZE) + 1 });
+    (dirs, inherits)
+}
+
+/// Icons uploaded as textures, each loaded once the first time a row needs it.
+#[derive(Default)]
+pub struct IconCache {
+    textures: HashMap<Icon, TextureHandle>,
+    /// Looked up once, `Some(None)` when there is no theme.
+    theme: Option<Option<IconTheme>>,
+}
+
+impl IconCache {
+    /// Forgets all textures, e.g. after switching between the system theme and the embedded icons.
+    pub fn clear(&mut self) {
+        self.textures.clear();
+        self.theme = None;
+    }
+
+    pub fn get(&mut self, ctx: &egui::Context, icon: Icon, system_theme: bool) -> &TextureHandle {
+        let theme = self.theme.get_or_insert_with(|| if system_theme { IconTheme::system() } else { None });
+        self.textures.entry(icon).or_insert_with(|| {
+            let image = theme
+                .as_ref()
+                .and_then(|t| t.find(icon.theme_names()))
+                .and_then(|path| load_file(&path))
+                .unwrap_or_else(|| icon.embedded());
+            ctx.load_texture(format!("icon-{:?}", icon), image, TextureOptions::LINEAR)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+
+    use crate::files::FileType;
+
+    fn entry(name: &str, file_type: FileType) -> FileEntry {
+        FileEntry {
+            len: 0,
+            file_type,
+            created: Default::default(),
+            modified: Default::default(),
+            accessed: Default::default(),
+            path: format!("/docs/{}", name),
+            file_name: name.to_string(),
+            symlink_target: None,
+            ignored: false,
+            archive: None,
+            mime: OnceLock::new(),
+            sniffed_mime: OnceLock::new(),
+        }
+    }
+
+    fn icon(name: &str) -> Icon {
+        Icon::of(&entry(name, FileType::File))
+    }
+
+    #[test]
+    fn icons_follow_type_name_and_mime() {
+        assert_eq!(Icon::of(&entry("src", FileType::Dir)), Icon::Folder);
+        let mut link = entry("src", FileType::Dir);
+        link.symlink_target = Some("elsewhere".to_string());
+        assert_eq!(Icon::of(&link), Icon::Symlink);
+        // archives and code are known by name before the MIME type is asked
+        assert_eq!(icon("backup.tar.zst"), Icon::Archive);
+        assert_eq!(icon("main.RS"), Icon::Code(Language::Rust));
+        assert_eq!(icon("config.yml"), Icon::Code(Language::Data));
+        assert_eq!(icon("photo.jpg"), Icon::Image);
+        assert_eq!(icon("song.mp3"), Icon::Audio);
+        assert_eq!(icon("clip.mp4"), Icon::Video);
+        assert_eq!(icon("paper.pdf"), Icon::Document);
+        assert_eq!(icon("sheet.xlsx"), Icon::Document);
+        assert_eq!(icon("notes.txt"), Icon::Text);
+        assert_eq!(icon("data.bin"), Icon::File);
+    }
+
+    #[test]
+    fn sniffed_mime_types_win_over_the_name() {
+        let script = entry("install", FileType::File);
+        script.sniffed_mime.set("application/x-shellscript".to_string()).unwrap();
+        assert_eq!(Icon::of(&script), Icon::Code(Language::Shell));
+        let program = entry("tool", FileType::File);
+        program.sniffed_mime.set("application/x-pie-executable".to_string()).unwrap();
+        assert_eq!(Icon::of(&program), Icon::Executable);
+    }
+
+    #[test]
+    fn embedded_icons_render() {
+        let icons = [
+            Icon::Folder,
+            Icon::Symlink,
+            Icon::Archive,
+            Icon::Image,
+            Icon::Audio,
+            Icon::Video,
+            Icon::Code(Language::Rust),
+            Icon::Text,
+            Icon::Document,
+            Icon::Executable,
+            Icon::File,
+        ];
+        for icon in icons {
+            let image = icon.embedded();
+            assert_eq!(image.size, [SIZE as usize; 2], "{:?}", icon);
+            assert!(image.pixels.iter().any(|p| p.a() > 0), "{:?} is empty", icon);
+        }
+        // code icons are tinted in the color of their language
+        assert_ne!(Icon::Code(Language::Rust).embedded().pixels, Icon::Code(Language::Go).embedded().pixels);
+    }
+
+    #[test]
+    fn themes_prefer_scalable_then_close_sizes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("Test");
+        for section in ["16x16/mimetypes", "32x32/mimetypes", "48x48/places", "scalable/mimetypes", "32x32/apps"] {
+            fs::create_dir_all(root.join(section)).unwrap();
+        }
+        let index = "[Icon Theme]\nName=Test\nInherits=Parent, hicolor\nDirectories=16x16/mimetypes,32x32/mimetypes\n\n\
+                     [16x16/mimetypes]\nSize=16\n\n[32x32/mimetypes]\nSize=32\n\n[48x48/places]\nSize=48\n\n\
+                     [scalable/mimetypes]\nSize=64\n\n[32x32/apps]\nSize=32\n\n[64x64/mimetypes]\nSize=64\n";
+        let (dirs, inherits) = parse_index(&root, index);
+        let sections: Vec<_> = dirs.iter().map(|d| d.path.strip_prefix(&root).unwrap().to_str().unwrap()).collect();
+        // equally close sizes keep the order of the index
+        assert_eq!(sections, vec!["scalable/mimetypes", "32x32/mimetypes", "16x16/mimetypes", "48x48/places"]);
+        assert_eq!(inherits, vec!["Parent", "hicolor"]);
+
+        fs::write(root.join("16x16/mimetypes/text-x-generic.png"), "").unwrap();
+        fs::write(root.join("32x32/mimetypes/text-x-generic.svg"), "").unwrap();
+        let theme = IconTheme { dirs };
+        assert_eq!(theme.find(&["text-x-python", "text-x-generic"]), Some(root.join("32x32/mimetypes/text-x-generic.svg")));
+        assert_eq!(theme.find(&["folder"]), None);
+    }
+}
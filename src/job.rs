@@ -0,0 +1,103 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+#[derive(Default)]
+pub struct Progress {
+    done: AtomicU64,
+    total: AtomicU64,
+    cancelled: AtomicBool,
+    message: Mutex<String>,
+}
+
+impl Progress {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_total(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+        self.done.store(0, Ordering::Relaxed);
+    }
+
+    pub fn add(&self, done: u64) {
+        self.done.fetch_add(done, Ordering::Relaxed);
+    }
+
+    pub fn set_message(&self, message: impl Into<String>) {
+        *self.message.lock().unwrap() = message.into();
+    }
+
+    pub fn message(&self) -> String {
+        self.message.lock().unwrap().clone()
+    }
+
+    pub fn fraction(&self) -> f32 {
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            return 0.0;
+        }
+        self.done.load(Ordering::Relaxed) as f32 / total as f32
+    }
+}
+
+/// Runs a closure on a background thread and hands its result back to the ui thread.
+pub struct Job<T> {
+    pub progress: Arc<Progress>,
+    receiver: Receiver<T>,
+    result: Option<T>,
+}
+
+impl<T: Send + 'static> Job<T> {
+    pub fn spawn(f: impl FnOnce(&Progress) -> T + Send + 'static) -> Self {
+        let progress = Arc::new(Progress::default());
+        let (sender, receiver) = mpsc::channel();
+        let thread_progress = progress.clone();
+        thread::spawn(move || {
+            let _ = sender.send(f(&thread_progress));
+        });
+        Self {
+            progress,
+            receiver,
+            result: None,
+        }
+    }
+}
+
+impl<T> Job<T> {
+    pub fn poll(&mut self) -> Option<&mut T> {
+        if self.result.is_none() {
+            if let Ok(result) = self.receiver.try_recv() {
+                self.result = Some(result);
+            }
+        }
+        self.result.as_mut()
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        self.poll().is_none()
+    }
+
+    pub fn cancel(&self) {
+        self.progress.cancel();
+    }
+}
+
+impl<T> fmt::Debug for Job<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Job")
+            .field("done", &self.result.is_some())
+            .field("cancelled", &self.progress.is_cancelled())
+            .finish()
+    }
+}
@@ -0,0 +1,360 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+/// An application that can open files, on Linux read from a `.desktop` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct App {
+    pub name: String,
+    /// The `Exec=` line with its field codes like `%F`.
+    pub exec: String,
+    pub mime_types: Vec<String>,
+    pub categories: Vec<String>,
+    /// The file name of the desktop file, e.g. `firefox.desktop`.
+    pub id: String,
+}
+
+impl App {
+    /// Replaces the field codes in one word of `exec`. `%F` and `%U` on their own become one
+    /// argument per path, inside a word like `--file=%f` every code takes the first path.
+    /// Returns no argument for words that were only codes without a value.
+    fn expand(&self, word: &str, paths: &[&str], used_paths: &mut bool) -> Vec<String> {
+        if word == "%F" || word == "%U" {
+            *used_paths = true;
+            return paths.iter().map(|p| p.to_string()).collect();
+        }
+        let mut expanded = String::new();
+        let mut chars = word.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('%') => expanded.push('%'),
+                Some('f' | 'u' | 'F' | 'U') => {
+                    expanded.push_str(paths.first().copied().unwrap_or_default());
+                    *used_paths = true;
+                }
+                Some('c') => expanded.push_str(&self.name),
+                // deprecated, icon and unknown codes are dropped
+                _ => {}
+            }
+        }
+        match expanded.is_empty() && !word.is_empty() {
+            true => vec![],
+            false => vec![expanded],
+        }
+    }
+
+    /// Splits `exec` into the program and its arguments, replacing the field codes with `paths`.
+    fn command(&self, paths: &[&str]) -> io::Result<Command> {
+        let words = shlex::split(&self.exec)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("invalid Exec in {}", self.id)))?;
+        let mut used_paths = false;
+        let mut args = vec![];
+        for word in words.iter() {
+            args.extend(self.expand(word, paths, &mut used_paths));
+        }
+        if args.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("empty Exec in {}", self.id)));
+        }
+        let mut command = Command::new(args.remove(0));
+        command.args(args);
+        // apps without a field code, like most terminals, at least start in the folder
+        if !used_paths {
+            if let Some(dir) = paths.first().map(Path::new).filter(|p| p.is_dir()) {
+                command.current_dir(dir);
+            }
+        }
+        Ok(command)
+    }
+
+    pub fn launch(&self, paths: &[&str]) -> io::Result<()> {
+        self.command(paths)?.spawn().map(|_| ())
+    }
+}
+
+/// The values of the `[Desktop Entry]` group, `None` for hidden entries and non-applications.
+fn parse_desktop_file(path: &Path) -> Option<App> {
+    let text = fs::read_to_string(path).ok()?;
+    let mut in_entry = false;
+    let (mut name, mut exec, mut mime_types, mut categories) = (None, None, vec![], vec![]);
+    let list = |value: &str| -> Vec<String> {
+        value.split(';').filter(|s| !s.is_empty()).map(str::to_string).collect()
+    };
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_entry) else {
+            continue;
+        };
+        match key.trim() {
+            "Name" => name = Some(value.trim().to_string()),
+            "Exec" => exec = Some(value.trim().to_string()),
+            "MimeType" => mime_types = list(value),
+            "Categories" => categories = list(value),
+            "Type" if value.trim() != "Application" => return None,
+            "Hidden" | "NoDisplay" if value.trim() == "true" => return None,
+            _ => {}
+        }
+    }
+    Some(App {
+        name: name?,
+        exec: exec?,
+        mime_types,
+        categories,
+        id: path.file_name()?.to_string_lossy().to_string(),
+    })
+}
+
+/// Where desktop files live, the user's own first so they shadow system ones.
+fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    if let Some(data) = dirs::data_dir() {
+        dirs.push(data);
+    }
+    let system = std::env::var("XDG_DATA_DIRS").unwrap_or("/usr/local/share:/usr/share".to_string());
+    dirs.extend(system.split(':').filter(|s| !s.is_empty()).map(PathBuf::from));
+    dirs
+}
+
+/// All installed applications, an empty list on systems without desktop files.
+/// Read once, the menus ask for them every frame.
+pub fn apps() -> &'static [App] {
+    static APPS: OnceLock<Vec<App>> = OnceLock::new();
+    APPS.get_or_init(|| read_apps(&data_dirs()))
+}
+
+fn read_apps(data_dirs: &[PathBuf]) -> Vec<App> {
+    let mut apps: Vec<App> = vec![];
+    for dir in data_dirs {
+        let Ok(entries) = fs::read_dir(dir.join("applications")) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|e| e == "desktop") {
+                if let Some(app) = parse_desktop_file(&path) {
+                    if !apps.iter().any(|a| a.id == app.id) {
+                        apps.push(app);
+                    }
+                }
+            }
+        }
+    }
+    apps.sort_by_key(|a| a.name.to_lowercase());
+    apps
+}
+
+/// The `mimeapps.list` files, the user's own first.
+fn mimeapps_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = dirs::config_dir().map(|c| c.join("mimeapps.list")).into_iter().collect();
+    files.extend(data_dirs().iter().map(|d| d.join("applications").join("mimeapps.list")));
+    files
+}
+
+/// The desktop file ids set as default for `mime` in `files`.
+fn default_ids(files: &[PathBuf], mime: &str) -> Vec<String> {
+    let mut ids = vec![];
+    for file in files {
+        let Ok(text) = fs::read_to_string(file) else {
+            continue;
+        };
+        let mut in_defaults = false;
+        for line in text.lines() {
+            if line.starts_with('[') {
+                in_defaults = line.trim() == "[Default Applications]";
+            } else if let Some(value) = line.strip_prefix(mime).and_then(|l| l.strip_prefix('=')) {
+                if in_defaults {
+                    ids.extend(value.split(';').filter(|s| !s.is_empty()).map(str::to_string));
+                }
+            }
+        }
+    }
+    ids
+}
+
+/// The applications that claim `mime`, the default ones first.
+pub fn apps_for_mime(mime: &str) -> Vec<App> {
+    let kind = format!("{}/*", mime.split('/').next().unwrap_or_default());
+    let mut apps: Vec<App> = apps()
+        .iter()
+        .filter(|a| a.mime_types.iter().any(|m| m == mime || *m == kind))
+        .cloned()
+        .collect();
+    let defaults = default_ids(&mimeapps_files(), mime);
+    apps.sort_by_key(|a| defaults.iter().position(|d| *d == a.id).unwrap_or(usize::MAX));
+    apps
+}
+
+/// Finds an application by its name, e.g. "Visual Studio Code".
+/// "Finder" and "Terminal" also find the file manager and terminal of other systems.
+pub fn find_app(name: &str) -> Option<App> {
+    let apps = apps();
+    let category = match name {
+        "Finder" => Some("FileManager"),
+        "Terminal" => Some("TerminalEmulator"),
+        _ => None,
+    };
+    apps.iter()
+        .find(|a| a.name.eq_ignore_ascii_case(name))
+        .or_else(|| apps.iter().find(|a| category.is_some_and(|c| a.categories.iter().any(|x| x == c))))
+        .cloned()
+}
+
+/// Opens `path` with its default application.
+pub fn open(path: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(path).spawn().map(|_| ())
+}
+
+/// Opens `path` with the preferred application for its detected `mime`,
+/// so files with a wrong or missing extension still open in the right app.
+/// Falls back to `open` where there are no desktop files.
+pub fn open_as(path: &str, mime: &str) -> io::Result<()> {
+    if cfg!(target_os = "macos") || cfg!(windows) {
+        return open(path);
+    }
+    match apps_for_mime(mime).first() {
+        Some(app) => app.launch(&[path]),
+        None => open(path),
+    }
+}
+
+/// Opens `path` with the application called `app_name`.
+pub fn open_with(app_name: &str, path: &str) -> io::Result<()> {
+    if cfg!(target_os = "macos") {
+        return Command::new("open").arg("-a").arg(app_name).arg(path).spawn().map(|_| ());
+    }
+    match find_app(app_name) {
+        Some(app) => app.launch(&[path]),
+        None => Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not installed", app_name))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(exec: &str) -> App {
+        App {
+            name: "Viewer".to_string(),
+            exec: exec.to_string(),
+            mime_types: vec![],
+            categories: vec![],
+            id: "viewer.desktop".to_string(),
+        }
+    }
+
+    fn args(exec: &str, paths: &[&str]) -> Vec<String> {
+        let command = app(exec).command(paths).unwrap();
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|a| a.to_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn field_codes_are_replaced_inside_words() {
+        let paths = ["/docs/a b.txt", "/docs/c.txt"];
+        assert_eq!(args("viewer %F", &paths), vec!["viewer", "/docs/a b.txt", "/docs/c.txt"]);
+        assert_eq!(args("viewer %u", &paths), vec!["viewer", "/docs/a b.txt"]);
+        assert_eq!(args("viewer --file=%f", &paths), vec!["viewer", "--file=/docs/a b.txt"]);
+        assert_eq!(args("viewer \"%u\"", &paths), vec!["viewer", "/docs/a b.txt"]);
+        assert_eq!(args("viewer --title=%c %i 100%%", &paths), vec!["viewer", "--title=Viewer", "100%"]);
+        assert_eq!(args("viewer --icon=%i %f", &[]), vec!["viewer", "--icon="]);
+    }
+
+    #[test]
+    fn apps_without_field_codes_start_in_the_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().to_str().unwrap();
+        let command = app("terminal --new-window").command(&[folder]).unwrap();
+        assert_eq!(command.get_current_dir(), Some(dir.path()));
+        let command = app("viewer --dir=%f").command(&[folder]).unwrap();
+        assert_eq!(command.get_current_dir(), None);
+
+        assert_eq!(app("viewer 'open").command(&[]).unwrap_err().to_string(), "invalid Exec in viewer.desktop");
+        assert_eq!(app("%i").command(&[]).unwrap_err().to_string(), "empty Exec in viewer.desktop");
+    }
+
+    #[test]
+    fn desktop_files_are_read_from_their_entry_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, text: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, text).unwrap();
+            path
+        };
+        let path = write(
+            "viewer.desktop",
+            "[Desktop Entry]\nType=Application\nName = Viewer\nExec=viewer %F\n\
+             MimeType=image/png;image/*;\nCategories=Graphics;\n\n[Desktop Action new]\nName=New Window\n",
+        );
+        assert_eq!(
+            parse_desktop_file(&path),
+            Some(App {
+                name: "Viewer".to_string(),
+                exec: "viewer %F".to_string(),
+                mime_types: vec!["image/png".to_string(), "image/*".to_string()],
+                categories: vec!["Graphics".to_string()],
+                id: "viewer.desktop".to_string(),
+            })
+        );
+        let hidden = write("hidden.desktop", "[Desktop Entry]\nName=Hidden\nExec=hidden\nNoDisplay=true\n");
+        assert_eq!(parse_desktop_file(&hidden), None);
+        let link = write("link.desktop", "[Desktop Entry]\nType=Link\nName=Link\nExec=link\n");
+        assert_eq!(parse_desktop_file(&link), None);
+        let no_exec = write("no-exec.desktop", "[Desktop Entry]\nName=Nothing\n");
+        assert_eq!(parse_desktop_file(&no_exec), None);
+    }
+
+    #[test]
+    fn own_desktop_files_shadow_system_ones() {
+        let (user, system) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        for (dir, name) in [(&user, "Mine"), (&system, "System"), (&system, "Other")] {
+            let applications = dir.path().join("applications");
+            fs::create_dir_all(&applications).unwrap();
+            let id = if name == "Other" { "other" } else { "viewer" };
+            let text = format!("[Desktop Entry]\nName={}\nExec={}\n", name, id);
+            fs::write(applications.join(format!("{}.desktop", id)), text).unwrap();
+        }
+        fs::write(system.path().join("applications/notes.txt"), "[Desktop Entry]\nName=Notes\nExec=x\n").unwrap();
+
+        let apps = read_apps(&[user.path().to_path_buf(), system.path().to_path_buf()]);
+        let names: Vec<&str> = apps.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["Mine", "Other"]);
+    }
+
+    #[test]
+    fn defaults_come_from_the_default_applications_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let (user, system) = (dir.path().join("user.list"), dir.path().join("system.list"));
+        fs::write(
+            &user,
+            "[Added Associations]\nimage/png=added.desktop;\n\n[Default Applications]\n\
+             image/png=viewer.desktop;editor.desktop;\nimage/pngx=wrong.desktop\n",
+        )
+        .unwrap();
+        fs::write(&system, "[Default Applications]\nimage/png=system.desktop\n").unwrap();
+
+        let files = [user, dir.path().join("missing.list"), system];
+        assert_eq!(default_ids(&files, "image/png"), vec!["viewer.desktop", "editor.desktop", "system.desktop"]);
+        assert!(default_ids(&files, "text/plain").is_empty());
+    }
+}
@@ -1,8 +1,26 @@
 pub mod app;
 pub mod actions;
+pub mod archive;
+pub mod checksum;
+pub mod compare;
+pub mod custom_actions;
+pub mod diff;
+pub mod duplicates;
 pub mod files;
+pub mod git;
+pub mod icons;
+pub mod job;
+pub mod launcher;
+pub mod mime;
+pub mod palette;
+pub mod permissions;
+pub mod properties;
+pub mod restriction;
+pub mod sync;
 pub mod tab;
 pub mod tabviewer;
+pub mod timestamps;
+pub mod vfs;
 pub mod zip;
 
 fn main() {
@@ -0,0 +1,135 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::job::Progress;
+
+pub const DIRECTORY: &str = "inode/directory";
+pub const SYMLINK: &str = "inode/symlink";
+const UNKNOWN: &str = "application/octet-stream";
+
+/// Content results that say little, the extension knows better if it knows anything,
+/// e.g. `text/x-rust` for a `text/plain` file or a `.docx` that is a zip archive.
+const GENERIC: [&str; 4] = [UNKNOWN, "text/plain", "application/zip", "application/x-zerosize"];
+
+/// The MIME type by extension, for entries that can't be read like those inside archives.
+pub fn from_name(name: &str) -> String {
+    mime_guess::from_path(name).first_or_octet_stream().to_string()
+}
+
+/// The MIME type of a file by its magic bytes, falling back to the extension.
+/// Uses the shared-mime-info database where it is installed, like the file managers of Linux,
+/// and the signatures built into `infer` everywhere else.
+pub fn guess(path: &Path) -> String {
+    // reading a named pipe or a device would block or never end
+    if !fs::metadata(path).is_ok_and(|m| m.is_file()) {
+        return from_name(&path.to_string_lossy());
+    }
+    let content = tree_magic_mini::from_filepath(path)
+        .map(str::to_string)
+        .or_else(|| Some(infer::get_from_path(path).ok()??.mime_type().to_string()));
+    let by_name = from_name(&path.to_string_lossy());
+    match content {
+        Some(content) if !GENERIC.contains(&content.as_str()) => content,
+        Some(content) if by_name == UNKNOWN => content,
+        _ => by_name,
+    }
+}
+
+/// Guesses the MIME types of a listing by content, meant to run as a background job.
+pub fn sniff(paths: Vec<String>, progress: &Progress) -> HashMap<String, String> {
+    progress.set_total(paths.len() as u64);
+    let mut mimes = HashMap::new();
+    for path in paths {
+        if progress.is_cancelled() {
+            break;
+        }
+        let mime = guess(Path::new(&path));
+        mimes.insert(path, mime);
+        progress.add(1);
+    }
+    mimes
+}
+
+const DESCRIPTIONS: &[(&str, &str)] = &[
+    (DIRECTORY, "Folder"),
+    (SYMLINK, "Broken link"),
+    (UNKNOWN, "Binary"),
+    ("application/x-zerosize", "Empty file"),
+    ("text/plain", "Plain text"),
+    ("text/markdown", "Markdown"),
+    ("text/html", "HTML document"),
+    ("text/css", "CSS stylesheet"),
+    ("text/csv", "CSV table"),
+    ("text/javascript", "JavaScript source"),
+    ("application/javascript", "JavaScript source"),
+    ("text/x-rust", "Rust source"),
+    ("text/rust", "Rust source"),
+    ("text/x-python", "Python source"),
+    ("text/x-c", "C source"),
+    ("text/x-csrc", "C source"),
+    ("text/x-chdr", "C header"),
+    ("text/x-c++src", "C++ source"),
+    ("text/x-java", "Java source"),
+    ("text/x-go", "Go source"),
+    ("application/x-shellscript", "Shell script"),
+    ("application/x-sh", "Shell script"),
+    ("application/json", "JSON"),
+    ("application/toml", "TOML"),
+    ("application/xml", "XML"),
+    ("text/xml", "XML"),
+    ("application/pdf", "PDF document"),
+    ("application/msword", "Word document"),
+    ("application/vnd.openxmlformats-officedocument.wordprocessingml.document", "Word document"),
+    ("application/vnd.oasis.opendocument.text", "OpenDocument text"),
+    ("application/zip", "Zip archive"),
+    ("application/gzip", "Gzip archive"),
+    ("application/x-gzip", "Gzip archive"),
+    ("application/x-tar", "Tar archive"),
+    ("application/x-xz", "XZ archive"),
+    ("application/zstd", "Zstandard archive"),
+    ("application/x-bzip2", "Bzip2 archive"),
+    ("application/x-7z-compressed", "7-Zip archive"),
+    ("application/x-executable", "Executable"),
+    ("application/x-pie-executable", "Executable"),
+    ("application/x-mach-binary", "Executable"),
+    ("application/x-sharedlib", "Shared library"),
+];
+
+/// A short human readable kind like "PNG image" or "Rust source".
+pub fn describe(mime: &str) -> String {
+    if let Some((_, description)) = DESCRIPTIONS.iter().find(|(m, _)| *m == mime) {
+        return description.to_string();
+    }
+    let (kind, sub) = mime.split_once('/').unwrap_or((mime, ""));
+    let sub = sub.trim_start_matches("x-").trim_start_matches("vnd.");
+    let sub = sub.split(['+', '.']).next().unwrap_or(sub);
+    match kind {
+        "image" | "audio" | "video" | "font" => format!("{} {}", sub.to_uppercase(), kind),
+        "text" => format!("{} text", sub.to_uppercase()),
+        _ => format!("{} file", sub.to_uppercase()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_wins_over_a_generic_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("image.txt");
+        fs::write(&path, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01\x08\x02\0\0\0").unwrap();
+        let paths = vec![path.to_str().unwrap().to_string()];
+        assert_eq!(sniff(paths.clone(), &Progress::default())[&paths[0]], "image/png");
+    }
+
+    #[test]
+    fn named_pipes_are_guessed_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pipe.json");
+        let c_path = std::ffi::CString::new(path.to_str().unwrap()).unwrap();
+        // SAFETY: the path is a valid nul-terminated string
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) }, 0);
+        // would block forever if the pipe was opened
+        assert_eq!(guess(&path), "application/json");
+    }
+}
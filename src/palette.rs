@@ -0,0 +1,154 @@
+use egui::KeyboardShortcut;
+
+/// How many commands the palette remembers as recently used.
+const MAX_RECENT: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandKind {
+    /// An index into `AppData::actions`.
+    Action(usize),
+    NewTab,
+    GoToFavorite(String),
+    GoUp,
+    Refresh,
+    ReloadActions,
+    ToggleHidden,
+}
+
+#[derive(Debug, Clone)]
+pub struct Command {
+    pub label: String,
+    pub shortcut: Option<KeyboardShortcut>,
+    pub kind: CommandKind,
+}
+
+impl Command {
+    pub fn new(label: impl Into<String>, kind: CommandKind) -> Self {
+        Self {
+            label: label.into(),
+            shortcut: None,
+            kind,
+        }
+    }
+
+    pub fn with_shortcut(mut self, shortcut: KeyboardShortcut) -> Self {
+        self.shortcut = Some(shortcut);
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Palette {
+    pub query: String,
+    /// The highlighted row of the filtered list.
+    pub selected: usize,
+}
+
+/// Scores `text` if all characters of `query` appear in it in order, ignoring case.
+/// Consecutive characters and characters at the start of words score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + text[position..].iter().position(|c| *c == q)?;
+        score += 1;
+        if previous == Some(found.wrapping_sub(1)) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    // shorter labels win among equal matches
+    Some(score * 100 - text.len() as i64)
+}
+
+/// The indices of the commands matching `query`, best first.
+/// Recently used commands come first when the query is empty and break ties otherwise.
+pub fn filter(commands: &[Command], query: &str, recent: &[String]) -> Vec<usize> {
+    let recency = |c: &Command| recent.iter().position(|r| *r == c.label).unwrap_or(usize::MAX);
+    let mut matches: Vec<(usize, i64)> = commands
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| Some((i, fuzzy_score(query, &c.label)?)))
+        .collect();
+    if query.trim().is_empty() {
+        matches.sort_by_key(|(i, _)| recency(&commands[*i]));
+    } else {
+        matches.sort_by_key(|(i, score)| (-score, recency(&commands[*i])));
+    }
+    matches.into_iter().map(|(i, _)| i).collect()
+}
+
+/// Moves `label` to the front of the recently used commands.
+pub fn remember(recent: &mut Vec<String>, label: &str) {
+    recent.retain(|r| r != label);
+    recent.insert(0, label.to_string());
+    recent.truncate(MAX_RECENT);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(labels: &[&str]) -> Vec<Command> {
+        labels.iter().map(|l| Command::new(*l, CommandKind::Refresh)).collect()
+    }
+
+    #[test]
+    fn scores_need_every_character_in_order() {
+        assert!(fuzzy_score("nt", "New Tab").is_some());
+        assert!(fuzzy_score("NEW tab", "new tab").is_some());
+        assert_eq!(fuzzy_score("tn", "New Tab"), None);
+        assert_eq!(fuzzy_score("x", "New Tab"), None);
+        assert!(fuzzy_score("", "anything").is_some());
+    }
+
+    #[test]
+    fn runs_and_word_starts_score_higher() {
+        let run = fuzzy_score("ref", "Refresh").unwrap();
+        let scattered = fuzzy_score("ref", "Reload Favorites").unwrap();
+        assert!(run > scattered);
+        assert!(fuzzy_score("h", "go home").unwrap() > fuzzy_score("h", "goxhome").unwrap());
+        // equal matches prefer the shorter label
+        assert!(fuzzy_score("go", "Go up").unwrap() > fuzzy_score("go", "Go to home").unwrap());
+    }
+
+    #[test]
+    fn filter_ranks_by_score_then_recency() {
+        let commands = commands(&["Go up", "Refresh", "Reload actions", "Toggle hidden"]);
+        assert_eq!(filter(&commands, "ref", &[]), vec![1]);
+        assert_eq!(filter(&commands, "re", &[]), vec![1, 2]);
+        // a tie is broken by the more recent command
+        let tied = self::commands(&["Reload b", "Reload a"]);
+        assert_eq!(filter(&tied, "rel", &[]), vec![0, 1]);
+        assert_eq!(filter(&tied, "rel", &["Reload a".to_string()]), vec![1, 0]);
+    }
+
+    #[test]
+    fn empty_queries_list_recent_commands_first() {
+        let commands = commands(&["Go up", "Refresh", "Toggle hidden"]);
+        let recent = vec!["Toggle hidden".to_string(), "Refresh".to_string()];
+        assert_eq!(filter(&commands, " ", &recent), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn remember_moves_to_the_front_and_caps_the_list() {
+        let mut recent = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        remember(&mut recent, "c");
+        assert_eq!(recent, vec!["c", "a", "b"]);
+        remember(&mut recent, "d");
+        assert_eq!(recent, vec!["d", "c", "a", "b"]);
+
+        for i in 0..30 {
+            remember(&mut recent, &i.to_string());
+        }
+        assert_eq!(recent.len(), MAX_RECENT);
+        assert_eq!(recent[0], "29");
+        assert_eq!(recent[MAX_RECENT - 1], "10");
+    }
+}
@@ -0,0 +1,327 @@
+use std::{
+    ffi::{CStr, CString},
+    fs, io,
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::Path,
+    ptr,
+};
+
+use walkdir::WalkDir;
+
+pub const SETUID: u32 = 0o4000;
+pub const SETGID: u32 = 0o2000;
+pub const STICKY: u32 = 0o1000;
+
+/// Buffer size for the reentrant `getpw*_r`/`getgr*_r` calls, enough for any sane entry.
+const LOOKUP_BUFFER: usize = 16 * 1024;
+
+/// Looks up a `passwd` entry through the system, which also knows users that are not
+/// in `/etc/passwd`, like those of macOS' directory services or LDAP.
+fn passwd(
+    lookup: impl FnOnce(*mut libc::passwd, *mut libc::c_char, usize, *mut *mut libc::passwd) -> libc::c_int,
+) -> Option<(String, u32)> {
+    let mut buffer = vec![0 as libc::c_char; LOOKUP_BUFFER];
+    // SAFETY: `passwd` is plain data, the lookup fills it with pointers into `buffer`
+    let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result = ptr::null_mut();
+    if lookup(&mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result) != 0 || result.is_null() {
+        return None;
+    }
+    // SAFETY: on success `pw_name` points to a nul-terminated string in `buffer`
+    let name = unsafe { CStr::from_ptr(entry.pw_name) };
+    Some((name.to_string_lossy().into_owned(), entry.pw_uid))
+}
+
+/// Like `passwd` for `group` entries.
+fn group(
+    lookup: impl FnOnce(*mut libc::group, *mut libc::c_char, usize, *mut *mut libc::group) -> libc::c_int,
+) -> Option<(String, u32)> {
+    let mut buffer = vec![0 as libc::c_char; LOOKUP_BUFFER];
+    // SAFETY: `group` is plain data, the lookup fills it with pointers into `buffer`
+    let mut entry: libc::group = unsafe { std::mem::zeroed() };
+    let mut result = ptr::null_mut();
+    if lookup(&mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result) != 0 || result.is_null() {
+        return None;
+    }
+    // SAFETY: on success `gr_name` points to a nul-terminated string in `buffer`
+    let name = unsafe { CStr::from_ptr(entry.gr_name) };
+    Some((name.to_string_lossy().into_owned(), entry.gr_gid))
+}
+
+pub fn user_name(uid: u32) -> Option<String> {
+    // SAFETY: all pointers are valid for the duration of the call
+    passwd(|e, b, l, r| unsafe { libc::getpwuid_r(uid, e, b, l, r) }).map(|(name, _)| name)
+}
+
+pub fn group_name(gid: u32) -> Option<String> {
+    // SAFETY: all pointers are valid for the duration of the call
+    group(|e, b, l, r| unsafe { libc::getgrgid_r(gid, e, b, l, r) }).map(|(name, _)| name)
+}
+
+/// Accepts a user name or a numeric id.
+pub fn parse_uid(user: &str) -> Option<u32> {
+    user.parse().ok().or_else(|| {
+        let name = CString::new(user).ok()?;
+        // SAFETY: all pointers are valid for the duration of the call
+        passwd(|e, b, l, r| unsafe { libc::getpwnam_r(name.as_ptr(), e, b, l, r) }).map(|(_, uid)| uid)
+    })
+}
+
+/// Accepts a group name or a numeric id.
+pub fn parse_gid(group_name: &str) -> Option<u32> {
+    group_name.parse().ok().or_else(|| {
+        let name = CString::new(group_name).ok()?;
+        // SAFETY: all pointers are valid for the duration of the call
+        group(|e, b, l, r| unsafe { libc::getgrnam_r(name.as_ptr(), e, b, l, r) }).map(|(_, gid)| gid)
+    })
+}
+
+/// Renders a mode like `ls -l` does, e.g. `rwxr-xr-x`.
+pub fn mode_string(mode: u32) -> String {
+    let mut s = String::new();
+    for (i, special, special_char) in [(6, SETUID, 's'), (3, SETGID, 's'), (0, STICKY, 't')] {
+        let bits = (mode >> i) & 0o7;
+        s.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        s.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        s.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    s
+}
+
+pub fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))
+}
+
+/// Adds the execute bit everywhere the read bit is set, like `chmod +x` with a default umask.
+pub fn make_executable(path: &str) -> io::Result<()> {
+    let mode = fs::metadata(path)?.permissions().mode();
+    set_mode(Path::new(path), mode | ((mode & 0o444) >> 2))
+}
+
+/// Permission bits shared by several entries, only the bits the user touched get written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModeEdit {
+    /// The bits set on every entry, and the new value of the edited bits.
+    pub mode: u32,
+    /// Bits set on some entries but not on others.
+    pub mixed: u32,
+    pub edited: u32,
+}
+
+impl ModeEdit {
+    fn new(modes: impl Iterator<Item = u32>, default: u32) -> Self {
+        let (mut all, mut any, mut empty) = (0o7777, 0, true);
+        for mode in modes {
+            all &= mode;
+            any |= mode;
+            empty = false;
+        }
+        if empty {
+            return Self { mode: default, mixed: 0, edited: 0 };
+        }
+        Self { mode: all, mixed: any ^ all, edited: 0 }
+    }
+
+    pub fn is_set(&self, bit: u32) -> bool {
+        self.mode & bit != 0
+    }
+
+    /// Set on some entries and not changed yet.
+    pub fn is_mixed(&self, bit: u32) -> bool {
+        self.mixed & bit != 0 && self.edited & bit == 0
+    }
+
+    pub fn set(&mut self, bit: u32, set: bool) {
+        if set {
+            self.mode |= bit;
+        } else {
+            self.mode &= !bit;
+        }
+        self.edited |= bit;
+    }
+
+    /// Replaces all bits, e.g. when typed as an octal number.
+    pub fn set_all(&mut self, mode: u32) {
+        self.mode = mode & 0o7777;
+        self.edited = 0o7777;
+    }
+
+    /// `current` with the edited bits replaced.
+    pub fn apply(&self, current: u32) -> u32 {
+        (current & !self.edited) | (self.mode & self.edited)
+    }
+}
+
+#[derive(Debug)]
+pub struct PermissionsEditor {
+    pub paths: Vec<String>,
+    pub file_mode: ModeEdit,
+    pub dir_mode: ModeEdit,
+    pub has_files: bool,
+    pub has_dirs: bool,
+    pub change_files: bool,
+    pub change_dirs: bool,
+    pub owner: String,
+    pub group: String,
+    pub recursive: bool,
+    pub errors: Vec<String>,
+}
+
+impl PermissionsEditor {
+    pub fn new(paths: Vec<String>) -> Self {
+        let metas: Vec<_> = paths.iter().filter_map(|p| fs::metadata(p).ok()).collect();
+        let modes = |dir: bool| {
+            metas
+                .iter()
+                .filter(move |m| m.is_dir() == dir)
+                .map(|m| m.permissions().mode() & 0o7777)
+        };
+        let has_files = metas.iter().any(|m| !m.is_dir());
+        let has_dirs = metas.iter().any(|m| m.is_dir());
+        let owner = metas.first().map(|m| m.uid());
+        let group = metas.first().map(|m| m.gid());
+        let same_owner = metas.iter().all(|m| Some(m.uid()) == owner);
+        let same_group = metas.iter().all(|m| Some(m.gid()) == group);
+        Self {
+            file_mode: ModeEdit::new(modes(false), 0o644),
+            dir_mode: ModeEdit::new(modes(true), 0o755),
+            has_files,
+            has_dirs,
+            change_files: has_files,
+            change_dirs: has_dirs,
+            owner: owner
+                .filter(|_| same_owner)
+                .map(|uid| user_name(uid).unwrap_or(uid.to_string()))
+                .unwrap_or_default(),
+            group: group
+                .filter(|_| same_group)
+                .map(|gid| group_name(gid).unwrap_or(gid.to_string()))
+                .unwrap_or_default(),
+            recursive: false,
+            errors: vec![],
+            paths,
+        }
+    }
+
+    fn apply_to(&self, path: &Path, is_dir: bool, owner: Option<u32>, group: Option<u32>) -> io::Result<()> {
+        let edit = match is_dir {
+            true if self.change_dirs => Some(self.dir_mode),
+            false if self.change_files => Some(self.file_mode),
+            _ => None,
+        };
+        let meta = fs::metadata(path)?;
+        if let Some(edit) = edit.filter(|e| e.edited != 0) {
+            let current = meta.permissions().mode() & 0o7777;
+            let mode = edit.apply(current);
+            if mode != current {
+                set_mode(path, mode)?;
+            }
+        }
+        let owner = owner.filter(|uid| *uid != meta.uid());
+        let group = group.filter(|gid| *gid != meta.gid());
+        if owner.is_some() || group.is_some() {
+            std::os::unix::fs::chown(path, owner, group)?;
+        }
+        Ok(())
+    }
+
+    /// Applies the modes and ownership, leaving empty owner/group fields untouched.
+    pub fn apply(&mut self) {
+        self.errors.clear();
+        let owner = self.owner.trim();
+        let group = self.group.trim();
+        let uid = parse_uid(owner);
+        let gid = parse_gid(group);
+        if !owner.is_empty() && uid.is_none() {
+            self.errors.push(format!("unknown user {}", owner));
+            return;
+        }
+        if !group.is_empty() && gid.is_none() {
+            self.errors.push(format!("unknown group {}", group));
+            return;
+        }
+        let mut errors = vec![];
+        for path in self.paths.iter() {
+            let walker = WalkDir::new(path).max_depth(if self.recursive { usize::MAX } else { 0 });
+            for entry in walker {
+                // chmod and chown act on what a link points to, which may be anywhere
+                if entry.as_ref().is_ok_and(|e| e.depth() > 0 && e.path_is_symlink()) {
+                    continue;
+                }
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        let failed = e.path().map(|p| p.display().to_string());
+                        errors.push(format!("{}: {}", failed.unwrap_or(path.to_string()), e));
+                        continue;
+                    }
+                };
+                if let Err(e) = self.apply_to(entry.path(), entry.file_type().is_dir(), uid, gid) {
+                    errors.push(format!("{}: {}", entry.path().display(), e));
+                }
+            }
+        }
+        self.errors = errors;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mode_strings_show_special_bits() {
+        assert_eq!(mode_string(0o755), "rwxr-xr-x");
+        assert_eq!(mode_string(0o640), "rw-r-----");
+        assert_eq!(mode_string(0o4755), "rwsr-xr-x");
+        assert_eq!(mode_string(0o2644), "rw-r-Sr--");
+        assert_eq!(mode_string(0o1777), "rwxrwxrwt");
+    }
+
+    #[test]
+    fn mode_edit_only_writes_edited_bits() {
+        let mut edit = ModeEdit::new([0o644, 0o664].into_iter(), 0);
+        assert_eq!(edit.mode, 0o644);
+        assert!(edit.is_mixed(0o020));
+        assert!(!edit.is_mixed(0o004));
+
+        edit.set(0o100, true);
+        assert_eq!(edit.apply(0o644), 0o744);
+        assert_eq!(edit.apply(0o664), 0o764);
+
+        edit.set(0o020, false);
+        assert!(!edit.is_mixed(0o020));
+        assert_eq!(edit.apply(0o664), 0o744);
+    }
+
+    #[test]
+    fn mode_edit_replaces_everything_when_typed() {
+        let mut edit = ModeEdit::new([0o644].into_iter(), 0);
+        edit.set_all(0o10700);
+        assert_eq!(edit.apply(0o4777), 0o700);
+    }
+
+    #[test]
+    fn mode_edit_defaults_without_entries() {
+        let edit = ModeEdit::new(std::iter::empty(), 0o755);
+        assert_eq!(edit.mode, 0o755);
+        assert_eq!(edit.apply(0o600), 0o600);
+    }
+
+    #[test]
+    fn apply_reports_the_failing_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+        let mut editor = PermissionsEditor::new(vec![missing.to_str().unwrap().to_string()]);
+        editor.change_files = true;
+        editor.file_mode.set(0o100, true);
+        editor.apply();
+        assert_eq!(editor.errors.len(), 1);
+        assert!(editor.errors[0].starts_with(missing.to_str().unwrap()));
+    }
+}
@@ -0,0 +1,258 @@
+use std::{fs, io, os::unix::fs::MetadataExt, path::Path};
+
+use chrono::{DateTime, Local};
+use walkdir::WalkDir;
+
+use crate::{
+    files::{bytes_to_human_readable, FileType},
+    job::{Job, Progress},
+    permissions::{group_name, mode_string, user_name},
+};
+
+/// Everything the file system knows about a single entry.
+#[derive(Debug)]
+pub struct ItemProperties {
+    pub path: String,
+    pub file_type: FileType,
+    pub len: u64,
+    pub mode: u32,
+    pub owner: String,
+    pub group: String,
+    pub inode: u64,
+    pub device: u64,
+    pub links: u64,
+    pub created: Option<DateTime<Local>>,
+    pub modified: DateTime<Local>,
+    pub accessed: DateTime<Local>,
+    /// The time the inode last changed, e.g. by chmod.
+    pub changed: DateTime<Local>,
+    pub symlink_target: Option<String>,
+    pub mime: Option<String>,
+}
+
+impl ItemProperties {
+    pub fn read(path: &str) -> io::Result<Self> {
+        let link_meta = fs::symlink_metadata(path)?;
+        let symlink_target = match link_meta.file_type().is_symlink() {
+            true => Some(fs::read_link(path)?.to_string_lossy().to_string()),
+            false => None,
+        };
+        // like the listing, links show their target unless it is broken
+        let meta = fs::metadata(path).unwrap_or(link_meta);
+        let file_type = FileType::from(meta.file_type());
+        let changed = DateTime::from_timestamp(meta.ctime(), meta.ctime_nsec() as u32)
+            .map(|t| t.with_timezone(&Local))
+            .unwrap_or_default();
+        Ok(Self {
+            path: path.to_string(),
+            file_type,
+            len: meta.len(),
+            mode: meta.mode() & 0o7777,
+            owner: user_name(meta.uid()).unwrap_or(meta.uid().to_string()),
+            group: group_name(meta.gid()).unwrap_or(meta.gid().to_string()),
+            inode: meta.ino(),
+            device: meta.dev(),
+            links: meta.nlink(),
+            created: meta.created().ok().map(|t| t.into()),
+            modified: meta.modified()?.into(),
+            accessed: meta.accessed()?.into(),
+            changed,
+            symlink_target,
+            mime: file_type.is_file().then(|| crate::mime::guess(Path::new(path))),
+        })
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match (self.file_type, self.symlink_target.is_some()) {
+            (FileType::Dir, false) => "folder",
+            (FileType::Dir, true) => "symlink to folder",
+            (FileType::File, false) => "file",
+            (FileType::File, true) => "symlink to file",
+            (FileType::Symlink, _) => "broken symlink",
+        }
+    }
+
+    pub fn permissions(&self) -> String {
+        format!("{} ({:04o})", mode_string(self.mode), self.mode)
+    }
+}
+
+/// Sizes and counts of everything below the selected entries, links are not followed.
+#[derive(Debug, Default)]
+pub struct Contents {
+    pub files: u64,
+    pub folders: u64,
+    pub bytes: u64,
+    /// Entries that could not be read, e.g. because of missing permissions.
+    pub unreadable: u64,
+}
+
+pub fn count_contents(paths: &[String], progress: &Progress) -> Contents {
+    let mut contents = Contents::default();
+    for path in paths {
+        for entry in WalkDir::new(path).min_depth(1) {
+            if progress.is_cancelled() {
+                return contents;
+            }
+            match entry.and_then(|e| Ok((e.file_type().is_dir(), e.metadata()?.len()))) {
+                Ok((true, _)) => contents.folders += 1,
+                Ok((false, len)) => {
+                    contents.files += 1;
+                    contents.bytes += len;
+                }
+                Err(_) => contents.unreadable += 1,
+            }
+        }
+    }
+    contents
+}
+
+/// `1234567` as `1,234,567`.
+pub fn group_digits(n: u64) -> String {
+    let digits = n.to_string();
+    let mut s = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            s.push(',');
+        }
+        s.push(c);
+    }
+    s
+}
+
+pub fn format_size(bytes: u64) -> String {
+    format!("{} ({} bytes)", bytes_to_human_readable(bytes), group_digits(bytes))
+}
+
+pub fn format_time(time: &DateTime<Local>) -> String {
+    time.format("%d/%m/%Y %H:%M:%S%.3f").to_string()
+}
+
+/// The properties window for one or many entries.
+#[derive(Debug)]
+pub struct Properties {
+    pub items: Vec<ItemProperties>,
+    pub errors: Vec<String>,
+    /// Only walks folders, a selection of plain files is summed up right away.
+    pub contents: Option<Job<Contents>>,
+}
+
+impl Properties {
+    pub fn new(paths: Vec<String>) -> Self {
+        let mut items = vec![];
+        let mut errors = vec![];
+        for path in paths.iter() {
+            match ItemProperties::read(path) {
+                Ok(item) => items.push(item),
+                Err(e) => errors.push(format!("{}: {}", path, e)),
+            }
+        }
+        let folders: Vec<String> = items
+            .iter()
+            .filter(|i| i.file_type.is_dir() && i.symlink_target.is_none())
+            .map(|i| i.path.clone())
+            .collect();
+        let contents = (!folders.is_empty()).then(|| Job::spawn(move |p| count_contents(&folders, p)));
+        Self { items, errors, contents }
+    }
+
+    /// The bytes of the selected files themselves, without folder contents.
+    pub fn own_size(&self) -> u64 {
+        self.items.iter().filter(|i| i.file_type.is_file()).map(|i| i.len).sum()
+    }
+
+    /// The value shared by all items, or "mixed".
+    pub fn common(&self, value: impl Fn(&ItemProperties) -> String) -> String {
+        let mut values = self.items.iter().map(value);
+        let first = values.next().unwrap_or_default();
+        match values.all(|v| v == first) {
+            true => first,
+            false => "mixed".to_string(),
+        }
+    }
+
+    pub fn modified_range(&self) -> Option<(DateTime<Local>, DateTime<Local>)> {
+        let min = self.items.iter().map(|i| i.modified).min()?;
+        let max = self.items.iter().map(|i| i.modified).max()?;
+        Some((min, max))
+    }
+
+    pub fn files(&self) -> Vec<String> {
+        self.items
+            .iter()
+            .filter(|i| i.file_type.is_file())
+            .map(|i| i.path.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    #[test]
+    fn digits_are_grouped_by_thousands() {
+        assert_eq!(group_digits(0), "0");
+        assert_eq!(group_digits(999), "999");
+        assert_eq!(group_digits(1000), "1,000");
+        assert_eq!(group_digits(1234567), "1,234,567");
+    }
+
+    #[test]
+    fn kinds_tell_links_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        fs::write(path("file"), "content").unwrap();
+        fs::create_dir(path("folder")).unwrap();
+        symlink("file", path("file link")).unwrap();
+        symlink("folder", path("folder link")).unwrap();
+        symlink("missing", path("broken")).unwrap();
+
+        let kind = |name: &str| ItemProperties::read(&path(name)).unwrap().kind();
+        assert_eq!(kind("file"), "file");
+        assert_eq!(kind("folder"), "folder");
+        assert_eq!(kind("file link"), "symlink to file");
+        assert_eq!(kind("folder link"), "symlink to folder");
+        assert_eq!(kind("broken"), "broken symlink");
+
+        let link = ItemProperties::read(&path("file link")).unwrap();
+        assert_eq!(link.symlink_target.as_deref(), Some("file"));
+        assert_eq!(link.len, 7);
+    }
+
+    #[test]
+    fn permissions_show_mode_and_octal() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o4750)).unwrap();
+        let item = ItemProperties::read(file.to_str().unwrap()).unwrap();
+        assert_eq!(item.permissions(), "rwsr-x--- (4750)");
+    }
+
+    #[test]
+    fn many_entries_are_summed_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        fs::write(path("a"), "aa").unwrap();
+        fs::write(path("b"), "bbb").unwrap();
+        fs::create_dir_all(path("folder/sub")).unwrap();
+        fs::write(path("folder/sub/c"), "cccc").unwrap();
+        // links below folders are counted as entries, not followed
+        symlink(dir.path(), path("folder/loop")).unwrap();
+
+        let properties = Properties::new(vec![path("a"), path("b"), path("folder"), path("missing")]);
+        assert_eq!(properties.items.len(), 3);
+        assert_eq!(properties.errors.len(), 1);
+        assert!(properties.errors[0].starts_with(&path("missing")));
+        assert_eq!(properties.own_size(), 5);
+        assert_eq!(properties.files(), vec![path("a"), path("b")]);
+        assert_eq!(properties.common(|i| i.owner.clone()), properties.items[0].owner);
+        assert_eq!(properties.common(|i| i.kind().to_string()), "mixed");
+
+        let contents = count_contents(&[path("folder")], &Progress::default());
+        assert_eq!((contents.folders, contents.files, contents.unreadable), (1, 2, 0));
+        assert_eq!(contents.bytes, 4 + fs::symlink_metadata(path("folder/loop")).unwrap().len());
+    }
+}
@@ -0,0 +1,449 @@
+use std::{fmt, os::unix::fs::PermissionsExt, path::Path, str::FromStr};
+
+use globset::{Glob, GlobMatcher};
+
+use crate::files::FileEntry;
+
+/// A compiled glob, compared by its pattern.
+#[derive(Debug, Clone)]
+pub struct GlobPattern(GlobMatcher);
+
+impl GlobPattern {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        Glob::new(pattern)
+            .map(|g| Self(g.compile_matcher()))
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl PartialEq for GlobPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.glob() == other.0.glob()
+    }
+}
+
+impl Eq for GlobPattern {}
+
+/// When an action applies. Everything but the `Count` variants is checked for every entry,
+/// an action is available if all selected entries match.
+///
+/// The textual form is used for custom actions, e.g. `file and (ext:rs,toml or size>1M)`.
+/// Words are `none`, `file`, `folder`, `main`, `symlink`, `executable`, `git`, `ext:a,b`,
+/// `glob:*.txt`, `mime:image/*`, `size>N`, `size<N`, `count=N`, `count>=N` and `under:~/dir`,
+/// combined with `not`, `and`, `or` and parentheses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Restriction {
+    None,
+    File,
+    Folder,
+    /// The folder of the tab itself, nothing is selected.
+    Main,
+    Symlink,
+    Executable,
+    InGitRepo,
+    /// Lowercase extensions without the dot.
+    Extension(Vec<String>),
+    /// Matched against the file name.
+    Glob(GlobPattern),
+    /// A MIME type like `text/plain`, or all of a kind with `image/*`.
+    Mime(String),
+    SizeAbove(u64),
+    SizeBelow(u64),
+    Count(usize),
+    CountAtLeast(usize),
+    /// The entry is below this folder.
+    Under(String),
+    Not(Box<Restriction>),
+    And(Box<Restriction>, Box<Restriction>),
+    Or(Box<Restriction>, Box<Restriction>),
+}
+
+impl Restriction {
+    pub fn matches(&self, entries: &[&FileEntry], is_main: bool) -> bool {
+        entries.iter().all(|e| self.matches_entry(e, is_main, entries.len()))
+    }
+
+    fn matches_entry(&self, entry: &FileEntry, is_main: bool, count: usize) -> bool {
+        match self {
+            Restriction::None => true,
+            Restriction::File => entry.file_type.is_file(),
+            Restriction::Folder => entry.file_type.is_dir(),
+            Restriction::Main => is_main,
+            Restriction::Symlink => entry.is_symlink(),
+            Restriction::Executable => {
+                entry.file_type.is_file()
+                    && std::fs::metadata(&entry.path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+            }
+            Restriction::InGitRepo => crate::git::repo_root(Path::new(&entry.path)).is_some(),
+            Restriction::Extension(extensions) => Path::new(&entry.file_name)
+                .extension()
+                .is_some_and(|ext| extensions.contains(&ext.to_string_lossy().to_lowercase())),
+            Restriction::Glob(glob) => glob.0.is_match(&entry.file_name),
+            Restriction::Mime(mime) => {
+                if !entry.file_type.is_file() {
+                    return false;
+                }
+                let actual = entry.mime();
+                match mime.strip_suffix("/*") {
+                    Some(kind) => actual.split('/').next() == Some(kind),
+                    None => actual == mime,
+                }
+            }
+            Restriction::SizeAbove(size) => entry.len > *size,
+            Restriction::SizeBelow(size) => entry.len < *size,
+            Restriction::Count(n) => count == *n,
+            Restriction::CountAtLeast(n) => count >= *n,
+            Restriction::Under(prefix) => Path::new(&entry.path).starts_with(expand_home(prefix)),
+            Restriction::Not(r) => !r.matches_entry(entry, is_main, count),
+            Restriction::And(a, b) => {
+                a.matches_entry(entry, is_main, count) && b.matches_entry(entry, is_main, count)
+            }
+            Restriction::Or(a, b) => {
+                a.matches_entry(entry, is_main, count) || b.matches_entry(entry, is_main, count)
+            }
+        }
+    }
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) => format!("{}{}", home.display(), rest),
+        _ => path.to_string(),
+    }
+}
+
+/// `10`, `4K`, `1.5M` or `2G`, units are powers of 1024.
+fn parse_size(text: &str) -> Result<u64, String> {
+    let upper = text.trim().to_uppercase();
+    let upper = upper.strip_suffix('B').unwrap_or(&upper);
+    let shift = match upper.chars().last() {
+        Some('K') => 10,
+        Some('M') => 20,
+        Some('G') => 30,
+        Some('T') => 40,
+        _ => 0,
+    };
+    let number = if shift == 0 { upper } else { &upper[..upper.len() - 1] };
+    let number: f64 = number.trim().parse().map_err(|_| format!("invalid size {}", text))?;
+    Ok((number * (1u64 << shift) as f64) as u64)
+}
+
+fn parse_count(text: &str) -> Result<usize, String> {
+    text.trim().parse().map_err(|_| format!("invalid count {}", text))
+}
+
+/// Splits into words and parentheses, double quotes keep spaces and parentheses in a word.
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_word = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if quoted => current.push(c),
+            '(' | ')' => {
+                if in_word {
+                    tokens.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+                tokens.push(c.to_string());
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    tokens.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quoted {
+        return Err("unclosed quote".to_string());
+    }
+    if in_word {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        let token = self.tokens.get(self.position).cloned().ok_or("unexpected end")?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn or(&mut self) -> Result<Restriction, String> {
+        let mut left = self.and()?;
+        while self.peek() == Some("or") {
+            self.position += 1;
+            left = Restriction::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Restriction, String> {
+        let mut left = self.unary()?;
+        while self.peek() == Some("and") {
+            self.position += 1;
+            left = Restriction::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Restriction, String> {
+        let token = self.next()?;
+        match token.as_str() {
+            "not" => Ok(Restriction::Not(Box::new(self.unary()?))),
+            "(" => {
+                let inner = self.or()?;
+                match self.next()?.as_str() {
+                    ")" => Ok(inner),
+                    other => Err(format!("expected ) but found {}", other)),
+                }
+            }
+            _ => atom(&token),
+        }
+    }
+}
+
+fn atom(token: &str) -> Result<Restriction, String> {
+    let restriction = match token {
+        "none" => Restriction::None,
+        "file" => Restriction::File,
+        "folder" => Restriction::Folder,
+        "main" => Restriction::Main,
+        "symlink" => Restriction::Symlink,
+        "executable" => Restriction::Executable,
+        "git" => Restriction::InGitRepo,
+        _ => {
+            if let Some(extensions) = token.strip_prefix("ext:") {
+                Restriction::Extension(
+                    extensions
+                        .split(',')
+                        .map(|e| e.trim_start_matches('.').to_lowercase())
+                        .collect(),
+                )
+            } else if let Some(glob) = token.strip_prefix("glob:") {
+                Restriction::Glob(GlobPattern::new(glob)?)
+            } else if let Some(mime) = token.strip_prefix("mime:") {
+                Restriction::Mime(mime.to_lowercase())
+            } else if let Some(size) = token.strip_prefix("size>") {
+                Restriction::SizeAbove(parse_size(size)?)
+            } else if let Some(size) = token.strip_prefix("size<") {
+                Restriction::SizeBelow(parse_size(size)?)
+            } else if let Some(count) = token.strip_prefix("count>=") {
+                Restriction::CountAtLeast(parse_count(count)?)
+            } else if let Some(count) = token.strip_prefix("count=") {
+                Restriction::Count(parse_count(count)?)
+            } else if let Some(prefix) = token.strip_prefix("under:") {
+                Restriction::Under(prefix.to_string())
+            } else {
+                return Err(format!("unknown restriction {}", token));
+            }
+        }
+    };
+    Ok(restriction)
+}
+
+impl FromStr for Restriction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+        if parser.tokens.is_empty() {
+            return Ok(Restriction::None);
+        }
+        let restriction = parser.or()?;
+        match parser.peek() {
+            Some(token) => Err(format!("unexpected {}", token)),
+            None => Ok(restriction),
+        }
+    }
+}
+
+/// Quotes words that would otherwise be split when parsed again.
+fn quote(word: &str) -> String {
+    match word.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+        true => format!("\"{}\"", word),
+        false => word.to_string(),
+    }
+}
+
+impl fmt::Display for Restriction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // `and` binds stronger than `or`, so only `or` needs parentheses below `and` and `not`
+        let grouped = |r: &Restriction| match r {
+            Restriction::Or(..) => format!("({})", r),
+            _ => r.to_string(),
+        };
+        match self {
+            Restriction::None => write!(f, "none"),
+            Restriction::File => write!(f, "file"),
+            Restriction::Folder => write!(f, "folder"),
+            Restriction::Main => write!(f, "main"),
+            Restriction::Symlink => write!(f, "symlink"),
+            Restriction::Executable => write!(f, "executable"),
+            Restriction::InGitRepo => write!(f, "git"),
+            Restriction::Extension(extensions) => write!(f, "ext:{}", quote(&extensions.join(","))),
+            Restriction::Glob(glob) => write!(f, "glob:{}", quote(glob.0.glob().glob())),
+            Restriction::Mime(mime) => write!(f, "mime:{}", mime),
+            Restriction::SizeAbove(size) => write!(f, "size>{}", size),
+            Restriction::SizeBelow(size) => write!(f, "size<{}", size),
+            Restriction::Count(n) => write!(f, "count={}", n),
+            Restriction::CountAtLeast(n) => write!(f, "count>={}", n),
+            Restriction::Under(prefix) => write!(f, "under:{}", quote(prefix)),
+            Restriction::Not(r) => match r.as_ref() {
+                Restriction::And(..) => write!(f, "not ({})", r),
+                _ => write!(f, "not {}", grouped(r)),
+            },
+            // both group to the left, a right operand of the same kind keeps its parentheses
+            Restriction::And(a, b) if matches!(b.as_ref(), Restriction::And(..)) => {
+                write!(f, "{} and ({})", grouped(a), b)
+            }
+            Restriction::And(a, b) => write!(f, "{} and {}", grouped(a), grouped(b)),
+            Restriction::Or(a, b) if matches!(b.as_ref(), Restriction::Or(..)) => write!(f, "{} or ({})", a, b),
+            Restriction::Or(a, b) => write!(f, "{} or {}", a, b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+
+    use crate::files::FileType;
+
+    fn parse(text: &str) -> Restriction {
+        text.parse().unwrap()
+    }
+
+    fn boxed(a: Restriction, b: Restriction) -> (Box<Restriction>, Box<Restriction>) {
+        (Box::new(a), Box::new(b))
+    }
+
+    fn entry(name: &str, len: u64) -> FileEntry {
+        FileEntry {
+            len,
+            file_type: FileType::File,
+            created: Default::default(),
+            modified: Default::default(),
+            accessed: Default::default(),
+            path: format!("/docs/{}", name),
+            file_name: name.to_string(),
+            symlink_target: None,
+            ignored: false,
+            archive: None,
+            mime: OnceLock::new(),
+            sniffed_mime: OnceLock::new(),
+        }
+    }
+
+    #[test]
+    fn and_binds_stronger_than_or() {
+        let (file, folder) = boxed(Restriction::File, Restriction::Folder);
+        let (a, b) = boxed(Restriction::Main, Restriction::Symlink);
+        assert_eq!(
+            parse("file and folder or main and symlink"),
+            Restriction::Or(Box::new(Restriction::And(file, folder)), Box::new(Restriction::And(a, b)))
+        );
+        assert_eq!(
+            parse("not file and folder"),
+            Restriction::And(Box::new(Restriction::Not(Box::new(Restriction::File))), Box::new(Restriction::Folder))
+        );
+    }
+
+    #[test]
+    fn parentheses_group() {
+        let (file, folder) = boxed(Restriction::File, Restriction::Folder);
+        assert_eq!(
+            parse("(file or folder) and main"),
+            Restriction::And(Box::new(Restriction::Or(file, folder)), Box::new(Restriction::Main))
+        );
+        assert_eq!(parse("((file))"), Restriction::File);
+        assert_eq!(
+            parse("not(file or folder)"),
+            Restriction::Not(Box::new(Restriction::Or(Box::new(Restriction::File), Box::new(Restriction::Folder))))
+        );
+        assert_eq!(parse("  "), Restriction::None);
+    }
+
+    #[test]
+    fn display_parses_back_to_the_same_restriction() {
+        for text in [
+            "file and (ext:rs,toml or size>1M)",
+            "not (file and folder)",
+            "not (file or main) or count>=2 and count=3",
+            "(file or folder) and (main or symlink)",
+            "glob:\"my notes*.txt\" and under:\"~/My Documents\"",
+            "mime:image/* and size<10 and executable and git",
+            "not not none",
+            "file and (folder and main)",
+            "file or (folder or main) or symlink",
+        ] {
+            let restriction = parse(text);
+            let shown = restriction.to_string();
+            assert_eq!(parse(&shown), restriction, "{} was shown as {}", text, shown);
+        }
+        assert_eq!(parse("file and (ext:RS,.toml or folder)").to_string(), "file and (ext:rs,toml or folder)");
+    }
+
+    #[test]
+    fn sizes_take_binary_suffixes() {
+        assert_eq!(parse_size("10"), Ok(10));
+        assert_eq!(parse_size("4K"), Ok(4096));
+        assert_eq!(parse_size("4kb"), Ok(4096));
+        assert_eq!(parse_size("1.5M"), Ok(3 << 19));
+        assert_eq!(parse_size("2G"), Ok(2 << 30));
+        assert_eq!(parse_size("1T"), Ok(1 << 40));
+        assert_eq!(parse("size>1K"), Restriction::SizeAbove(1024));
+        assert!(parse("size>1K").matches(&[&entry("big", 1025)], false));
+        assert!(!parse("size>1K").matches(&[&entry("small", 1024)], false));
+    }
+
+    #[test]
+    fn malformed_restrictions_say_what_is_wrong() {
+        let error = |text: &str| text.parse::<Restriction>().unwrap_err();
+        assert_eq!(error("file and"), "unexpected end");
+        assert_eq!(error("(file or folder"), "unexpected end");
+        assert_eq!(error("(file folder)"), "expected ) but found folder");
+        assert_eq!(error("file folder"), "unexpected folder");
+        assert_eq!(error("file)"), "unexpected )");
+        assert_eq!(error("directory"), "unknown restriction directory");
+        assert_eq!(error("size>lots"), "invalid size lots");
+        assert_eq!(error("count=-1"), "invalid count -1");
+        assert_eq!(error("glob:\"a b"), "unclosed quote");
+        assert!(error("glob:[a").starts_with("error parsing glob"));
+    }
+
+    #[test]
+    fn every_entry_and_the_count_must_match() {
+        let (notes, code) = (entry("notes.TXT", 0), entry("main.rs", 0));
+        assert!(parse("ext:txt,rs").matches(&[&notes, &code], false));
+        assert!(!parse("ext:txt").matches(&[&notes, &code], false));
+        assert!(parse("glob:*.rs or count=2").matches(&[&notes, &code], false));
+        assert!(!parse("glob:*.rs or count=2").matches(&[&notes], false));
+        assert!(parse("main").matches(&[&notes], true));
+        assert!(parse("under:/docs and not under:/doc").matches(&[&notes], false));
+    }
+}
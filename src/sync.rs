@@ -0,0 +1,325 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use filetime::FileTime;
+use walkdir::WalkDir;
+
+use crate::{
+    compare::{compare_dirs, CompareEntry, Difference},
+    files::{FileEntry, GlobFilter},
+    job::Progress,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Make the target an exact copy of the source, deleting extra entries.
+    Mirror,
+    /// Copy new entries and entries that are newer in the source.
+    Update,
+    /// Copy in both directions, the newer side wins.
+    TwoWay,
+}
+
+impl SyncMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncMode::Mirror => "mirror",
+            SyncMode::Update => "update",
+            SyncMode::TwoWay => "two-way",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SyncOperation {
+    CreateDir(PathBuf),
+    Copy { from: PathBuf, to: PathBuf },
+    Delete { path: PathBuf, is_dir: bool },
+}
+
+impl SyncOperation {
+    pub fn describe(&self) -> String {
+        match self {
+            SyncOperation::CreateDir(path) => format!("create {}", path.display()),
+            SyncOperation::Copy { from, to } => {
+                format!("copy {} ➡ {}", from.display(), to.display())
+            }
+            SyncOperation::Delete { path, .. } => format!("delete {}", path.display()),
+        }
+    }
+}
+
+/// Copies `relative` from below `from` to below `to`, expanding folders so
+/// that the filter applies to every entry inside them.
+fn plan_copy(
+    from: &Path,
+    to: &Path,
+    relative: &str,
+    filter: &GlobFilter,
+    ops: &mut Vec<SyncOperation>,
+) {
+    let root = from.join(relative);
+    for entry in WalkDir::new(&root).follow_root_links(false).into_iter().filter_map(|e| e.ok()) {
+        let Ok(inner) = entry.path().strip_prefix(from) else {
+            continue;
+        };
+        let is_dir = entry.file_type().is_dir();
+        if !filter.matches(inner, is_dir) {
+            continue;
+        }
+        if is_dir {
+            ops.push(SyncOperation::CreateDir(to.join(inner)));
+        } else {
+            ops.push(SyncOperation::Copy {
+                from: entry.path().to_path_buf(),
+                to: to.join(inner),
+            });
+        }
+    }
+}
+
+fn plan_delete(root: &Path, entry: &CompareEntry, ops: &mut Vec<SyncOperation>) {
+    ops.push(SyncOperation::Delete {
+        path: root.join(&entry.relative),
+        is_dir: entry.is_dir,
+    });
+}
+
+/// Deletes `relative` below `root` entry by entry, leaving out what the filter excludes
+/// and the folders that still hold such entries.
+fn plan_delete_tree(root: &Path, relative: &str, filter: &GlobFilter, ops: &mut Vec<SyncOperation>) {
+    let mut kept: HashSet<PathBuf> = HashSet::new();
+    let walk = WalkDir::new(root.join(relative)).follow_root_links(false).contents_first(true);
+    for entry in walk.into_iter().filter_map(|e| e.ok()) {
+        let Ok(inner) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        let is_dir = entry.file_type().is_dir();
+        if kept.contains(entry.path()) || !filter.matches(inner, is_dir) {
+            kept.extend(entry.path().ancestors().take_while(|a| *a != root).map(Path::to_path_buf));
+            continue;
+        }
+        ops.push(SyncOperation::Delete {
+            path: entry.path().to_path_buf(),
+            is_dir,
+        });
+    }
+}
+
+fn is_newer(a: &Option<FileEntry>, b: &Option<FileEntry>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.modified > b.modified,
+        _ => false,
+    }
+}
+
+/// Lists every copy and delete needed to synchronize `source` and `target`.
+pub fn plan(
+    source: &str,
+    target: &str,
+    mode: SyncMode,
+    filter: &GlobFilter,
+    progress: &Progress,
+) -> io::Result<Vec<SyncOperation>> {
+    let cancelled = || io::Error::new(io::ErrorKind::Interrupted, "cancelled");
+    let entries = compare_dirs(source, target, false, progress)?;
+    // an incomplete comparison would look like folders that are already in sync
+    if progress.is_cancelled() {
+        return Err(cancelled());
+    }
+    let (source, target) = (Path::new(source), Path::new(target));
+    let mut ops = vec![];
+    for entry in entries.iter() {
+        if progress.is_cancelled() {
+            return Err(cancelled());
+        }
+        if !filter.matches(Path::new(&entry.relative), entry.is_dir) {
+            continue;
+        }
+        // the entries inside get their own operations, copying the whole folder
+        // would repeat them and overwrite files that are newer on the other side
+        if entry.is_dir && entry.reasons == ["contents"] {
+            continue;
+        }
+        match (entry.difference, mode) {
+            // what's inside an unreadable folder is unknown, leave it alone
+            (Difference::Same | Difference::Error, _) => {}
+            (Difference::LeftOnly, _) => {
+                plan_copy(source, target, &entry.relative, filter, &mut ops)
+            }
+            (Difference::RightOnly, SyncMode::Mirror) => {
+                plan_delete_tree(target, &entry.relative, filter, &mut ops)
+            }
+            (Difference::RightOnly, SyncMode::Update) => {}
+            (Difference::RightOnly, SyncMode::TwoWay) => {
+                plan_copy(target, source, &entry.relative, filter, &mut ops)
+            }
+            (Difference::Different, SyncMode::Mirror) => {
+                if entry.reasons.contains(&"type") {
+                    plan_delete(target, entry, &mut ops);
+                }
+                plan_copy(source, target, &entry.relative, filter, &mut ops);
+            }
+            (Difference::Different, SyncMode::Update) => {
+                if is_newer(&entry.left, &entry.right) {
+                    plan_copy(source, target, &entry.relative, filter, &mut ops);
+                }
+            }
+            (Difference::Different, SyncMode::TwoWay) => {
+                if is_newer(&entry.left, &entry.right) {
+                    plan_copy(source, target, &entry.relative, filter, &mut ops);
+                } else if is_newer(&entry.right, &entry.left) {
+                    plan_copy(target, source, &entry.relative, filter, &mut ops);
+                }
+            }
+        }
+    }
+    Ok(ops)
+}
+
+/// Copies a file with its modification time, otherwise the copy looks newer
+/// than the original and the next run would copy it again.
+fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to)?;
+    let modified = FileTime::from_last_modification_time(&fs::metadata(from)?);
+    filetime::set_file_mtime(to, modified)
+}
+
+/// Runs the planned operations and returns a description of every failure.
+pub fn execute(ops: &[SyncOperation], progress: &Progress) -> Vec<String> {
+    progress.set_total(ops.len() as u64);
+    let mut errors = vec![];
+    for op in ops {
+        if progress.is_cancelled() {
+            errors.push("cancelled".to_string());
+            break;
+        }
+        progress.set_message(op.describe());
+        let result = match op {
+            SyncOperation::CreateDir(path) => fs::create_dir_all(path),
+            SyncOperation::Copy { from, to } => {
+                if let Some(parent) = to.parent() {
+                    let _ = fs::create_dir_all(parent);
+                }
+                copy_file(from, to)
+            }
+            SyncOperation::Delete { path, is_dir: true } => fs::remove_dir_all(path),
+            SyncOperation::Delete { path, is_dir: false } => fs::remove_file(path),
+        };
+        if let Err(e) = result {
+            errors.push(format!("{}: {}", op.describe(), e));
+        }
+        progress.add(1);
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str, modified: i64) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+        filetime::set_file_mtime(path, FileTime::from_unix_time(modified, 0)).unwrap();
+    }
+
+    fn plan_for(source: &Path, target: &Path, mode: SyncMode) -> Vec<String> {
+        let filter = GlobFilter::new("", "").unwrap();
+        let ops = plan(
+            source.to_str().unwrap(),
+            target.to_str().unwrap(),
+            mode,
+            &filter,
+            &Progress::default(),
+        )
+        .unwrap();
+        let relative = |p: &Path| {
+            let p = p.strip_prefix(source).or_else(|_| p.strip_prefix(target)).unwrap();
+            p.to_str().unwrap().to_string()
+        };
+        let mut described: Vec<_> = ops
+            .iter()
+            .map(|op| match op {
+                SyncOperation::CreateDir(path) => format!("create {}", relative(path)),
+                SyncOperation::Copy { from, to } if from.starts_with(source) => {
+                    format!("copy {} >", relative(to))
+                }
+                SyncOperation::Copy { to, .. } => format!("copy {} <", relative(to)),
+                SyncOperation::Delete { path, .. } => format!("delete {}", relative(path)),
+            })
+            .collect();
+        described.sort();
+        described
+    }
+
+    #[test]
+    fn mirror_copies_changed_files_and_deletes_extra_ones() {
+        let (source, target) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let (source, target) = (source.path(), target.path());
+        write(&source.join("dir/changed"), "new", 200);
+        write(&target.join("dir/changed"), "old", 100);
+        write(&source.join("dir/same"), "same", 100);
+        write(&target.join("dir/same"), "same", 100);
+        write(&target.join("dir/extra"), "extra", 100);
+        write(&source.join("new/file"), "new", 100);
+
+        assert_eq!(
+            plan_for(source, target, SyncMode::Mirror),
+            ["copy dir/changed >", "copy new/file >", "create new", "delete dir/extra"]
+        );
+    }
+
+    #[test]
+    fn two_way_keeps_the_newer_file_inside_changed_folders() {
+        let (source, target) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let (source, target) = (source.path(), target.path());
+        write(&source.join("dir/a"), "older", 100);
+        write(&target.join("dir/a"), "newer", 200);
+        write(&source.join("dir/b"), "newer", 200);
+        write(&target.join("dir/b"), "older", 100);
+        // the folder itself looks newer in the source
+        filetime::set_file_mtime(source.join("dir"), FileTime::from_unix_time(300, 0)).unwrap();
+
+        assert_eq!(
+            plan_for(source, target, SyncMode::TwoWay),
+            ["copy dir/a <", "copy dir/b >"]
+        );
+        assert_eq!(plan_for(source, target, SyncMode::Update), ["copy dir/b >"]);
+    }
+
+    #[test]
+    fn mirror_keeps_excluded_entries_inside_extra_folders() {
+        let (source, target) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let (source, target) = (source.path(), target.path());
+        write(&target.join("extra/notes.bak"), "keep", 100);
+        write(&target.join("extra/file"), "delete", 100);
+        write(&target.join("gone/file"), "delete", 100);
+
+        let filter = GlobFilter::new("", "*.bak").unwrap();
+        let ops = plan(
+            source.to_str().unwrap(),
+            target.to_str().unwrap(),
+            SyncMode::Mirror,
+            &filter,
+            &Progress::default(),
+        )
+        .unwrap();
+        let mut deleted: Vec<_> = ops
+            .iter()
+            .map(|op| match op {
+                SyncOperation::Delete { path, .. } => path.strip_prefix(target).unwrap().to_str().unwrap().to_string(),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        deleted.sort();
+        assert_eq!(deleted, ["extra/file", "gone", "gone/file"]);
+
+        assert!(execute(&ops, &Progress::default()).is_empty());
+        assert!(target.join("extra/notes.bak").exists());
+        assert!(!target.join("gone").exists());
+    }
+}
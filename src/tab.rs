@@ -1,6 +1,6 @@
-use std::{collections::HashSet, io};
+use std::{collections::{HashMap, HashSet}, io};
 
-use crate::{actions::ActionState, files::{get_entries, get_meta, FileEntry}};
+use crate::{actions::ActionState, archive::ArchiveInfo, compare::CompareDirs, diff::CompareFiles, git::GitState, files::{FileEntry, ListingFilter}, job::Job, vfs::{self, Vfs}};
 
 #[derive(Debug)]
 pub struct Tab {
@@ -15,6 +15,22 @@ pub struct Tab {
     pub previous_paths2: Vec<String>,
     pub state: ActionState,
     pub sorting : TabSorting,
+    pub view: TabView,
+    pub filter: ListingFilter,
+    pub git: Option<GitState>,
+    /// Sniffs the MIME types of the listed files, until then they are guessed by name.
+    pub mimes: Option<Job<HashMap<String, String>>>,
+    /// Where `path` lives, local files or the inside of an archive.
+    pub fs: Vfs,
+}
+
+/// What a tab shows; everything but `Explorer` is a result view opened by an action.
+#[derive(Debug)]
+pub enum TabView {
+    Explorer,
+    Compare(CompareDirs),
+    Diff(CompareFiles),
+    ArchiveInfo(ArchiveInfo),
 }
 #[derive(Debug)]
 pub struct TabSorting{
@@ -26,14 +42,37 @@ pub struct TabSorting{
 pub enum SortingColumn {
     Filename,
     Date,
-    Size
+    Size,
+    Kind,
 }
 
 impl Tab {
     pub fn new(path: impl Into<String>, id: egui::Id) -> Self {
+        Self::with_filter(path, id, ListingFilter::default())
+    }
+
+    pub fn with_filter(path: impl Into<String>, id: egui::Id, filter: ListingFilter) -> Self {
         let path = path.into();
-        let info = get_meta(&path);
-        let entries = get_entries(&path);
+        let fs = vfs::for_path(&path);
+        let info = fs.stat(&path);
+        let entries = fs.list(&path);
+        let entries = entries.map(|mut entries| {
+            filter.apply(&path, &mut entries);
+            entries
+        });
+        let state = ActionState {
+            filter,
+            fs: fs.clone(),
+            ..Default::default()
+        };
+        let git = if fs.is_local() { GitState::for_path(&path) } else { None };
+        let mimes = match &entries {
+            Ok(entries) if fs.is_local() => {
+                let paths: Vec<_> = entries.iter().filter(|e| e.file_type.is_file()).map(|e| e.path.clone()).collect();
+                Some(Job::spawn(move |p| crate::mime::sniff(paths, p)))
+            }
+            _ => None,
+        };
         return Self {
             id,
             path,
@@ -44,11 +83,22 @@ impl Tab {
             previous_paths2: vec![],
             selected_entries: Default::default(),
             last_clicked_entry: None,
-            state: ActionState::default(),
+            state,
             sorting: TabSorting { reverse: false, column: SortingColumn::Filename },
+            view: TabView::Explorer,
+            filter,
+            git,
+            mimes,
+            fs,
         };
     }
 
+    pub fn with_view(path: impl Into<String>, view: TabView, id: egui::Id) -> Self {
+        let mut tab = Self::new(path, id);
+        tab.view = view;
+        return tab;
+    }
+
     pub fn refresh(&mut self, path: impl Into<String>) {
         let path = path.into();
         if let Ok(i) = &self.info {
@@ -60,8 +110,30 @@ impl Tab {
         self.refresh_hard(path);
     }
 
+    /// Hands finished MIME types to the entries. Returns whether sniffing is still running.
+    pub fn update_mimes(&mut self) -> bool {
+        let Some(job) = &mut self.mimes else {
+            return false;
+        };
+        let Some(mimes) = job.poll() else {
+            return true;
+        };
+        if let Ok(entries) = &self.entries {
+            for entry in entries.iter() {
+                if let Some(mime) = mimes.remove(&entry.path) {
+                    let _ = entry.sniffed_mime.set(mime);
+                }
+            }
+        }
+        self.mimes = None;
+        false
+    }
+
     pub fn refresh_hard(&mut self, path: impl Into<String>) {
-        let mut new = Self::new(path, self.id);
+        if let Some(job) = &self.mimes {
+            job.cancel();
+        }
+        let mut new = Self::with_filter(path, self.id, self.filter);
         new.previous_paths.append(&mut self.previous_paths);
         new.previous_paths.push(self.path.clone());
         *self = new;
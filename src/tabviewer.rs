@@ -1,12 +1,29 @@
 use crate::{
-    actions::Action,
-    files::{self, bytes_to_human_readable, FileEntry},
-    tab::{SortingColumn, Tab},
+    actions::{Action, ActionState, Checksums},
+    archive::{ArchiveFormat, ArchiveInfo},
+    checksum::{Algorithm, VerifyStatus},
+    files::{self, bytes_to_human_readable, FileEntry, IgnoreMode},
+    git::FileStatus as GitFileStatus,
+    icons::{Icon, IconCache},
+    properties::{format_size, format_time},
+    job::{Job, Progress},
+    launcher,
+    palette::{self, Command, CommandKind, Palette},
+    permissions::ModeEdit,
+    sync::SyncMode,
+    compare::{self, CompareDirs, Difference},
+    diff::{CompareFiles, FileDiff, Row, Side, MAX_TEXT_DIFF_SIZE},
+    tab::{SortingColumn, Tab, TabView},
+    timestamps::{self, TimeSource},
+    vfs,
 };
-use std::{borrow::Borrow, path::Path};
+use std::path::Path;
 
-use egui::{Id, Key, Label, Modifiers, PointerButton, Rect, Sense, TextEdit, Widget};
-use egui_dock::{DockArea, DockState, NodeIndex, Style, SurfaceIndex, TabIndex};
+use egui::{
+    text::LayoutJob, Color32, FontId, Id, Key, Label, Modifiers, PointerButton, Rect, Sense,
+    TextEdit, TextFormat, Widget,
+};
+use egui_dock::{NodeIndex, SurfaceIndex};
 use egui_extras::{Column, TableBuilder};
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -21,8 +38,34 @@ pub struct AppData {
     #[serde(skip)]
     pub drag_paths: Option<(String, Vec<(String, String)>)>,
     pub drop_path: Option<String>,
+    /// The tab that received the drop, `drop_path` may be an archive shown in it.
+    #[serde(skip)]
+    pub drop_tab: Option<Id>,
+    pub follow_symlinks: bool,
+    #[serde(skip)]
+    pub focused_tab: Option<Id>,
+    #[serde(skip)]
+    pub open_paths: Vec<String>,
+    #[serde(skip)]
+    pub new_views: Vec<(String, TabView)>,
+    #[serde(skip)]
+    pub palette: Option<Palette>,
+    /// Labels of palette commands, most recently used first.
+    pub recent_commands: Vec<String>,
+    /// Why the custom actions, or some of them, could not be loaded.
+    #[serde(skip)]
+    pub actions_error: Option<String>,
+    /// Use the icons built into the app instead of the system icon theme.
+    pub embedded_icons: bool,
+    #[serde(skip)]
+    pub icons: IconCache,
 }
 
+const TOGGLE_HIDDEN: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Period);
+const OPEN_PALETTE: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::P);
+
 fn show_sorting_header(
     header: &mut egui_extras::TableRow<'_, '_>,
     sorting: &mut crate::tab::TabSorting,
@@ -58,13 +101,610 @@ fn show_sorting_header(
     return clicked;
 }
 
+fn show_icon(ui: &mut egui::Ui, icons: &mut IconCache, icon: Icon, system_theme: bool) {
+    let texture = icons.get(ui.ctx(), icon, system_theme);
+    egui::Image::new((texture.id(), egui::vec2(16., 16.))).ui(ui);
+}
+
+fn show_progress(ui: &mut egui::Ui, progress: &Progress) {
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::ProgressBar::new(progress.fraction())
+                .text(progress.message())
+                .desired_width(ui.available_width() - 60.),
+        );
+        if ui.button("cancel").clicked() {
+            progress.cancel();
+        }
+    });
+    ui.ctx()
+        .request_repaint_after(std::time::Duration::from_millis(100));
+}
+
+fn show_compare(ui: &mut egui::Ui, compare: &mut CompareDirs) {
+    ui.horizontal(|ui| {
+        if ui.button("⟳").clicked() {
+            compare.rerun();
+        }
+        if ui.checkbox(&mut compare.content, "content").changed() {
+            compare.rerun();
+        }
+        ui.separator();
+        ui.checkbox(&mut compare.show_left_only, "left only");
+        ui.checkbox(&mut compare.show_right_only, "right only");
+        ui.checkbox(&mut compare.show_different, "different");
+        ui.checkbox(&mut compare.show_same, "same");
+        ui.separator();
+        let copy_right = ui.button("copy ➡").clicked();
+        let copy_left = ui.button("⬅ copy").clicked();
+        if copy_right || copy_left {
+            if let Some(Ok(entries)) = compare.job.poll() {
+                let mut failed = 0;
+                for i in compare.selected.iter() {
+                    let entry = &entries[*i];
+                    let result = match (entry.difference, copy_right) {
+                        (Difference::LeftOnly | Difference::Different, true) => {
+                            compare::copy_entry(&compare.left, &compare.right, &entry.relative)
+                        }
+                        (Difference::RightOnly | Difference::Different, false) => {
+                            compare::copy_entry(&compare.right, &compare.left, &entry.relative)
+                        }
+                        _ => Ok(()),
+                    };
+                    failed += result.is_err() as usize;
+                }
+                compare.message = (failed > 0).then(|| format!("{} entries could not be copied", failed));
+            }
+            compare.rerun();
+        }
+        if let Some(message) = &compare.message {
+            ui.label(message);
+        }
+    });
+
+    if compare.job.is_running() {
+        show_progress(ui, &compare.job.progress);
+        return;
+    }
+    let entries = match compare.job.poll() {
+        Some(Ok(entries)) => entries,
+        Some(Err(e)) => {
+            ui.label(format!("could not compare: {}", e));
+            return;
+        }
+        None => return,
+    };
+
+    let side = |entry: &Option<FileEntry>| match entry {
+        Some(e) if e.file_type.is_dir() => e.modified.format("%d/%m/%Y %H:%M").to_string(),
+        Some(e) => format!(
+            "{}  {}",
+            e.modified.format("%d/%m/%Y %H:%M"),
+            bytes_to_human_readable(e.len)
+        ),
+        None => "".into(),
+    };
+
+    TableBuilder::new(ui)
+        .column(Column::remainder())
+        .column(Column::auto().at_least(180.))
+        .column(Column::auto().at_least(120.))
+        .column(Column::auto().at_least(180.))
+        .sense(egui::Sense::click())
+        .header(20.0, |mut header| {
+            header.col(|ui| {
+                ui.strong("Path");
+            });
+            header.col(|ui| {
+                ui.strong("Left");
+            });
+            header.col(|ui| {
+                ui.strong("Status");
+            });
+            header.col(|ui| {
+                ui.strong("Right");
+            });
+        })
+        .body(|mut body| {
+            for (i, entry) in entries.iter().enumerate() {
+                let visible = match entry.difference {
+                    Difference::LeftOnly => compare.show_left_only,
+                    Difference::RightOnly => compare.show_right_only,
+                    Difference::Different => compare.show_different,
+                    Difference::Same => compare.show_same,
+                    Difference::Error => true,
+                };
+                if !visible {
+                    continue;
+                }
+                body.row(18.0, |mut row| {
+                    row.set_selected(compare.selected.contains(&i));
+                    row.col(|ui| {
+                        let mut text: egui::RichText = entry.relative.to_string().into();
+                        if entry.is_dir {
+                            text = text.strong();
+                        }
+                        Label::new(text).selectable(false).ui(ui);
+                    });
+                    row.col(|ui| {
+                        ui.label(side(&entry.left));
+                    });
+                    row.col(|ui| {
+                        ui.label(match entry.difference {
+                            Difference::LeftOnly => "left only".to_string(),
+                            Difference::RightOnly => "right only".to_string(),
+                            Difference::Different => entry.reasons.join(", "),
+                            Difference::Same => "same".to_string(),
+                            Difference::Error => entry.error.clone().unwrap_or_default(),
+                        });
+                    });
+                    row.col(|ui| {
+                        ui.label(side(&entry.right));
+                    });
+                    if row.response().clicked() {
+                        if !compare.selected.remove(&i) {
+                            compare.selected.insert(i);
+                        }
+                    }
+                });
+            }
+        });
+}
+
+fn diff_side_job(ui: &egui::Ui, side: Option<&Side>, prefix: &str, color: (u8, u8, u8)) -> LayoutJob {
+    let (r, g, b) = color;
+    let mut job = LayoutJob::default();
+    let format = TextFormat {
+        font_id: FontId::monospace(12.),
+        color: ui.visuals().text_color(),
+        ..Default::default()
+    };
+    let Some(side) = side else {
+        return job;
+    };
+    job.append(&format!("{:>5} {}", side.number, prefix), 0., format.clone());
+    for (emphasized, text) in side.segments.iter() {
+        let background = match (side.changed, emphasized) {
+            (false, _) => Color32::TRANSPARENT,
+            (true, false) => Color32::from_rgba_unmultiplied(r, g, b, 40),
+            (true, true) => Color32::from_rgba_unmultiplied(r, g, b, 120),
+        };
+        job.append(text, 0., TextFormat { background, ..format.clone() });
+    }
+    job
+}
+
+fn show_archive_info(ui: &mut egui::Ui, info: &mut ArchiveInfo) {
+    if info.entries.is_running() {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("reading archive…");
+        });
+        ui.ctx()
+            .request_repaint_after(std::time::Duration::from_millis(100));
+        return;
+    }
+    ui.horizontal(|ui| {
+        if ui.button("copy as text").clicked() {
+            ui.ctx().copy_text(info.to_text());
+            info.message = Some("copied".into());
+        }
+        let path = info.text_path();
+        let mut save = false;
+        if info.confirm_overwrite {
+            ui.label(format!("replace {}?", path));
+            save = ui.button("replace").clicked();
+            if ui.button("cancel").clicked() {
+                info.confirm_overwrite = false;
+            }
+        } else if ui.button("save as text").clicked() {
+            info.confirm_overwrite = Path::new(&path).exists();
+            save = !info.confirm_overwrite;
+        }
+        if save {
+            info.confirm_overwrite = false;
+            info.message = Some(match std::fs::write(&path, info.to_text()) {
+                Ok(_) => format!("saved to {}", path),
+                Err(e) => format!("could not save {}: {}", path, e),
+            });
+        }
+        if let Some(message) = &info.message {
+            ui.label(message);
+        }
+    });
+    let entries = match info.entries.poll().unwrap() {
+        Ok(entries) => entries,
+        Err(e) => {
+            ui.label(format!("could not read archive: {}", e));
+            return;
+        }
+    };
+    let size: u64 = entries.iter().map(|e| e.size).sum();
+    let compressed: u64 = entries.iter().filter_map(|e| e.compressed_size).sum();
+    ui.label(format!(
+        "{} entries, {} uncompressed, {} on disk",
+        entries.len(),
+        bytes_to_human_readable(size),
+        bytes_to_human_readable(std::fs::metadata(&info.archive).map(|m| m.len()).unwrap_or(compressed)),
+    ));
+
+    TableBuilder::new(ui)
+        .column(Column::remainder())
+        .column(Column::auto().at_least(80.))
+        .column(Column::auto().at_least(80.))
+        .column(Column::auto().at_least(50.))
+        .column(Column::auto().at_least(80.))
+        .column(Column::auto().at_least(120.))
+        .header(20.0, |mut header| {
+            for name in ["Path", "Size", "Compressed", "Ratio", "Method", "Modified"] {
+                header.col(|ui| {
+                    ui.strong(name);
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(18.0, entries.len(), |mut row| {
+                let entry = &entries[row.index()];
+                row.col(|ui| {
+                    let lock = if entry.encrypted { "🔒 " } else { "" };
+                    let slash = if entry.is_dir { "/" } else { "" };
+                    ui.label(format!("{}{}{}", lock, entry.path, slash));
+                });
+                row.col(|ui| {
+                    if !entry.is_dir {
+                        ui.label(bytes_to_human_readable(entry.size));
+                    }
+                });
+                row.col(|ui| {
+                    if let (Some(c), false) = (entry.compressed_size, entry.is_dir) {
+                        ui.label(bytes_to_human_readable(c));
+                    }
+                });
+                row.col(|ui| {
+                    if let Some(ratio) = entry.ratio() {
+                        ui.label(format!("{:.0}%", ratio * 100.));
+                    }
+                });
+                row.col(|ui| {
+                    ui.label(&entry.method);
+                });
+                row.col(|ui| {
+                    ui.label(entry.modified.format("%d/%m/%Y %H:%M").to_string());
+                });
+            });
+        });
+}
+
+fn describe_binary(first_difference: Option<u64>, left_len: u64, right_len: u64) -> String {
+    match first_difference {
+        None => "are identical".to_string(),
+        Some(offset) => format!(
+            "differ at offset {} (0x{:x}), sizes {} and {} bytes",
+            offset, offset, left_len, right_len
+        ),
+    }
+}
+
+fn show_diff(ui: &mut egui::Ui, diff: &mut CompareFiles) {
+    let hunk_count = match diff.diff.poll() {
+        Some(Ok(FileDiff::Text(hunks))) => hunks.len(),
+        _ => 0,
+    };
+    ui.horizontal(|ui| {
+        if ui.button("⟳").clicked() {
+            diff.rerun();
+        }
+        if ui.checkbox(&mut diff.ignore_whitespace, "ignore whitespace").changed() {
+            diff.rerun();
+        }
+        ui.radio_value(&mut diff.unified, false, "side by side");
+        ui.radio_value(&mut diff.unified, true, "unified");
+        ui.separator();
+        if ui.button("⬆").clicked() && diff.current_hunk > 0 {
+            diff.current_hunk -= 1;
+            diff.scroll_to_hunk = true;
+        }
+        if ui.button("⬇").clicked() && diff.current_hunk + 1 < hunk_count {
+            diff.current_hunk += 1;
+            diff.scroll_to_hunk = true;
+        }
+        ui.label(format!("hunk {}/{}", (diff.current_hunk + 1).min(hunk_count), hunk_count));
+    });
+
+    if diff.diff.is_running() {
+        show_progress(ui, &diff.diff.progress);
+        return;
+    }
+    let hunks = match diff.diff.poll().unwrap() {
+        Err(e) => {
+            ui.label(format!("could not compare: {}", e));
+            return;
+        }
+        Ok(FileDiff::Binary { first_difference, left_len, right_len }) => {
+            let described = describe_binary(*first_difference, *left_len, *right_len);
+            if (*left_len).max(*right_len) > MAX_TEXT_DIFF_SIZE {
+                ui.label(format!("files too large for a text diff {}", described));
+            } else {
+                ui.label(format!("binary files {}", described));
+            }
+            return;
+        }
+        Ok(FileDiff::Text(hunks)) => hunks,
+    };
+    if hunks.is_empty() {
+        ui.label("files are identical");
+        return;
+    }
+
+    let red = (220, 50, 50);
+    let green = (50, 180, 50);
+    egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+        for (i, hunk) in hunks.iter().enumerate() {
+            let resp = ui.label(egui::RichText::new(&hunk.header).monospace().weak());
+            if i == diff.current_hunk && diff.scroll_to_hunk {
+                resp.scroll_to_me(Some(egui::Align::TOP));
+                diff.scroll_to_hunk = false;
+            }
+            if diff.unified {
+                let flush = |ui: &mut egui::Ui, pending: &mut Vec<&Row>| {
+                    for row in pending.iter().filter(|r| r.left.is_some()) {
+                        ui.label(diff_side_job(ui, row.left.as_ref(), "-", red));
+                    }
+                    for row in pending.iter().filter(|r| r.right.is_some()) {
+                        ui.label(diff_side_job(ui, row.right.as_ref(), "+", green));
+                    }
+                    pending.clear();
+                };
+                let mut pending = vec![];
+                for row in hunk.rows.iter() {
+                    let changed = row.left.as_ref().is_none_or(|s| s.changed)
+                        || row.right.as_ref().is_none_or(|s| s.changed);
+                    if changed {
+                        pending.push(row);
+                        continue;
+                    }
+                    flush(ui, &mut pending);
+                    ui.label(diff_side_job(ui, row.left.as_ref(), " ", red));
+                }
+                flush(ui, &mut pending);
+            } else {
+                let width = ui.available_width() / 2.;
+                for row in hunk.rows.iter() {
+                    ui.horizontal(|ui| {
+                        for (side, color) in [(&row.left, red), (&row.right, green)] {
+                            let job = diff_side_job(ui, side.as_ref(), " ", color);
+                            ui.allocate_ui(egui::vec2(width, 0.), |ui| {
+                                ui.set_min_width(width);
+                                Label::new(job).truncate().ui(ui);
+                            });
+                        }
+                    });
+                }
+            }
+        }
+    });
+}
+
+fn mode_editor(ui: &mut egui::Ui, id: &str, mode: &mut ModeEdit) {
+    egui::Grid::new(id).num_columns(4).show(ui, |ui| {
+        ui.label("");
+        ui.label("read");
+        ui.label("write");
+        ui.label("execute");
+        ui.end_row();
+        for (name, shift) in [("owner", 6), ("group", 3), ("others", 0)] {
+            ui.label(name);
+            for bit in [0o4, 0o2, 0o1] {
+                mode_checkbox(ui, mode, bit << shift, "");
+            }
+            ui.end_row();
+        }
+    });
+    ui.horizontal(|ui| {
+        for (name, bit) in [
+            ("setuid", crate::permissions::SETUID),
+            ("setgid", crate::permissions::SETGID),
+            ("sticky", crate::permissions::STICKY),
+        ] {
+            mode_checkbox(ui, mode, bit, name);
+        }
+    });
+    ui.horizontal(|ui| {
+        let mut octal = format!("{:04o}", mode.mode);
+        let resp = TextEdit::singleline(&mut octal).desired_width(40.).show(ui).response;
+        if resp.changed() {
+            if let Ok(parsed) = u32::from_str_radix(&octal, 8) {
+                mode.set_all(parsed);
+            }
+        }
+        ui.monospace(crate::permissions::mode_string(mode.mode));
+        if mode.mixed & !mode.edited != 0 {
+            ui.weak("(mixed bits are kept)");
+        }
+    });
+}
+
+/// A checkbox that shows bits differing between the selected entries as indeterminate.
+fn mode_checkbox(ui: &mut egui::Ui, mode: &mut ModeEdit, bit: u32, text: &str) {
+    let mut set = mode.is_set(bit);
+    let resp = egui::Checkbox::new(&mut set, text).indeterminate(mode.is_mixed(bit)).ui(ui);
+    if resp.changed() {
+        mode.set(bit, set);
+    }
+}
+
+/// Lists the applications that claim the MIME type of the selected files.
+fn open_with_menu(ui: &mut egui::Ui, entries: &[&FileEntry], state: &mut ActionState) {
+    // without desktop files, e.g. on macOS, the configured open with actions are all there is
+    if launcher::apps().is_empty() || entries.iter().any(|e| !e.file_type.is_file() || e.archive.is_some()) {
+        return;
+    }
+    let Some(first) = entries.first() else {
+        return;
+    };
+    ui.menu_button("open with", |ui| {
+        let apps = launcher::apps_for_mime(first.mime());
+        if apps.is_empty() {
+            ui.weak("no applications found");
+        }
+        for app in apps.iter() {
+            if ui.button(&app.name).clicked() {
+                let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+                if let Err(e) = app.launch(&paths) {
+                    state.message = Some(format!("could not open with {}: {}", app.name, e));
+                }
+                ui.close_menu();
+            }
+        }
+    });
+}
+
+impl AppData {
+    /// Rebuilds the actions, picking up changes of the custom actions file.
+    pub fn reload_actions(&mut self) {
+        (self.actions, self.actions_error) = crate::custom_actions::all_actions();
+    }
+
+    fn show_palette(&mut self, ui: &mut egui::Ui, tab: &mut Tab) {
+        let Some(palette) = &mut self.palette else {
+            return;
+        };
+        let is_main = tab.selected_entries.is_empty();
+        let action_entries: Vec<&FileEntry> = match (&tab.info, &tab.entries) {
+            (Ok(info), _) if is_main => vec![info],
+            (_, Ok(entries)) if !is_main => entries
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| tab.selected_entries.contains(i))
+                .map(|(_, x)| x)
+                .collect(),
+            _ => vec![],
+        };
+        let git_root = tab.git.as_ref().map(|g| g.root.as_path());
+        let mut commands: Vec<Command> = self
+            .actions
+            .iter()
+            .enumerate()
+            .filter(|(_, action)| !action_entries.is_empty() && action.is_available(&action_entries, is_main, git_root))
+            .map(|(i, action)| Command::new((action.name)(&action_entries), CommandKind::Action(i)))
+            .collect();
+        commands.push(Command::new("new tab", CommandKind::NewTab));
+        commands.push(Command::new("go up", CommandKind::GoUp));
+        commands.push(Command::new("refresh", CommandKind::Refresh));
+        commands.push(Command::new("reload custom actions", CommandKind::ReloadActions));
+        commands.push(Command::new("toggle hidden files", CommandKind::ToggleHidden).with_shortcut(TOGGLE_HIDDEN));
+        for favorite in self.favorites.iter() {
+            commands.push(Command::new(
+                format!("go to favorite {}", favorite),
+                CommandKind::GoToFavorite(favorite.clone()),
+            ));
+        }
+        let matches = palette::filter(&commands, &palette.query, &self.recent_commands);
+
+        let mut run = None;
+        let mut close = false;
+        egui::Window::new("command palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0., 40.])
+            .default_width(400.)
+            .show(ui.ctx(), |ui| {
+                let resp = TextEdit::singleline(&mut palette.query)
+                    .hint_text("type a command")
+                    .desired_width(f32::INFINITY)
+                    .show(ui)
+                    .response;
+                resp.request_focus();
+                if resp.changed() {
+                    palette.selected = 0;
+                }
+                let (up, down) = ui.input(|i| (i.key_pressed(Key::ArrowUp), i.key_pressed(Key::ArrowDown)));
+                if down {
+                    palette.selected = (palette.selected + 1).min(matches.len().saturating_sub(1));
+                }
+                if up {
+                    palette.selected = palette.selected.saturating_sub(1);
+                }
+                if ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Enter)) {
+                    run = matches.get(palette.selected).copied();
+                }
+                // consumed so windows below don't close along with the palette
+                close = ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape));
+                egui::ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+                    for (row, i) in matches.iter().enumerate() {
+                        let command = &commands[*i];
+                        ui.horizontal(|ui| {
+                            let resp = ui.selectable_label(row == palette.selected, &command.label);
+                            if row == palette.selected && (up || down) {
+                                resp.scroll_to_me(None);
+                            }
+                            if resp.clicked() {
+                                run = Some(*i);
+                            }
+                            if let Some(shortcut) = &command.shortcut {
+                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                    ui.weak(ui.ctx().format_shortcut(shortcut));
+                                });
+                            }
+                        });
+                    }
+                    if matches.is_empty() {
+                        ui.weak("no matching commands");
+                    }
+                });
+            });
+
+        let Some(i) = run else {
+            if close {
+                self.palette = None;
+            }
+            return;
+        };
+        let command = &commands[i];
+        palette::remember(&mut self.recent_commands, &command.label);
+        self.palette = None;
+        match &command.kind {
+            CommandKind::Action(action) => (self.actions[*action].execute)(&action_entries, &mut tab.state),
+            CommandKind::NewTab => self.new_views.push((tab.path.clone(), TabView::Explorer)),
+            CommandKind::GoToFavorite(path) => tab.refresh(path.clone()),
+            CommandKind::GoUp => {
+                if let Some(parent) = Path::new(&tab.path).parent() {
+                    let parent = parent.to_str().unwrap_or_default().to_string();
+                    tab.refresh(parent);
+                }
+            }
+            CommandKind::Refresh => tab.refresh_hard(tab.path.clone()),
+            CommandKind::ReloadActions => self.reload_actions(),
+            CommandKind::ToggleHidden => {
+                tab.filter.show_hidden = !tab.filter.show_hidden;
+                tab.refresh_hard(tab.path.clone());
+            }
+        }
+    }
+}
+
 impl egui_dock::TabViewer for AppData {
     type Tab = Tab;
 
     fn title(&mut self, tab: &mut Self::Tab) -> egui::WidgetText {
-        match &tab.info {
-            Ok(info) => info.path.clone().into(),
-            Err(_) => "invalid path".into(),
+        match &tab.view {
+            TabView::Compare(compare) => {
+                return format!("{} ⇔ {}", compare.left, compare.right).into();
+            }
+            TabView::Diff(diff) => {
+                return format!("{} ⇔ {}", diff.left, diff.right).into();
+            }
+            TabView::ArchiveInfo(info) => {
+                return format!("ⓘ {}", info.archive).into();
+            }
+            TabView::Explorer => {}
+        }
+        let branch = tab.git.as_ref().and_then(|g| g.branch());
+        match (&tab.info, branch) {
+            (Ok(info), Some(branch)) => format!("{} [{}]", info.path, branch).into(),
+            (Ok(info), None) => info.path.clone().into(),
+            (Err(_), _) => "invalid path".into(),
         }
     }
 
@@ -77,13 +717,29 @@ impl egui_dock::TabViewer for AppData {
     }
 
     fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Self::Tab) {
+        match &mut tab.view {
+            TabView::Compare(compare) => return show_compare(ui, compare),
+            TabView::Diff(diff) => return show_diff(ui, diff),
+            TabView::ArchiveInfo(info) => return show_archive_info(ui, info),
+            TabView::Explorer => {}
+        }
         tab.state.relead = false;
+        if let Some(git) = &mut tab.git {
+            if git.update() {
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_millis(200));
+            }
+        }
+        if tab.update_mimes() {
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_millis(200));
+        }
 
         if ui.input(|i| i.pointer.button_clicked(PointerButton::Extra1)) {
             // previous
             if !tab.previous_paths.is_empty() {
                 let last = tab.previous_paths.remove(tab.previous_paths.len() - 1);
-                let mut new = Tab::new(last.clone(), tab.id);
+                let mut new = Tab::with_filter(last.clone(), tab.id, tab.filter);
                 new.previous_paths.append(&mut tab.previous_paths);
                 new.previous_paths2.append(&mut tab.previous_paths2);
                 new.previous_paths2.push(last);
@@ -91,6 +747,16 @@ impl egui_dock::TabViewer for AppData {
             }
         }
         if ui.input(|i| i.pointer.button_clicked(PointerButton::Extra2)) {}
+        if self.focused_tab == Some(tab.id) && ui.input_mut(|i| i.consume_shortcut(&TOGGLE_HIDDEN)) {
+            tab.filter.show_hidden = !tab.filter.show_hidden;
+            tab.refresh_hard(tab.path.clone());
+        }
+        if self.focused_tab == Some(tab.id) {
+            if ui.input_mut(|i| i.consume_shortcut(&OPEN_PALETTE)) {
+                self.palette = Some(Palette::default());
+            }
+            self.show_palette(ui, tab);
+        }
         ui.horizontal(|ui| {
             if ui.button("★").clicked() {
                 if !self.favorites.contains(&tab.path) {
@@ -109,6 +775,28 @@ impl egui_dock::TabViewer for AppData {
             if ui.button("⟳").clicked() {
                 tab.refresh_hard(tab.path.clone());
             }
+            let toggle_hidden = ui
+                .toggle_value(&mut tab.filter.show_hidden, ".*")
+                .on_hover_text(format!("show hidden files ({})", ui.ctx().format_shortcut(&TOGGLE_HIDDEN)))
+                .changed();
+            let ignore_mode = tab.filter.ignored;
+            egui::ComboBox::from_id_salt(("ignore_mode", tab.id))
+                .width(60.)
+                .selected_text(match tab.filter.ignored {
+                    IgnoreMode::Show => "ignored",
+                    IgnoreMode::Dim => "dim",
+                    IgnoreMode::Hide => "hide",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut tab.filter.ignored, IgnoreMode::Show, "show ignored");
+                    ui.selectable_value(&mut tab.filter.ignored, IgnoreMode::Dim, "dim ignored");
+                    ui.selectable_value(&mut tab.filter.ignored, IgnoreMode::Hide, "hide ignored");
+                })
+                .response
+                .on_hover_text("entries matched by .gitignore and .ignore files");
+            if toggle_hidden || ignore_mode != tab.filter.ignored {
+                tab.refresh_hard(tab.path.clone());
+            }
             let search_width = 150.0;
             let resp = TextEdit::singleline(&mut tab.path)
                 .desired_width(ui.available_width() - search_width)
@@ -140,19 +828,33 @@ impl egui_dock::TabViewer for AppData {
             }
         });
 
+        if let Some(message) = &tab.state.message {
+            let mut dismiss = false;
+            ui.horizontal(|ui| {
+                ui.colored_label(ui.visuals().warn_fg_color, message);
+                dismiss = ui.button("x").clicked();
+            });
+            if dismiss {
+                tab.state.message = None;
+            }
+        }
+
         if let Ok(entries) = &mut tab.entries {
             let mut new_path = None;
 
             let ctx = ui.ctx().clone();
-            let mut builder = TableBuilder::new(ui)
+            let builder = TableBuilder::new(ui)
+                .column(Column::exact(18.))
                 .column(Column::remainder())
                 .column(Column::auto().at_least(160.))
                 .column(Column::auto().at_least(60.))
+                .column(Column::auto().at_least(120.))
                 .sense(egui::Sense::click());
 
             let mut resort = false;
 
             let table = builder.header(20.0, |mut header| {
+                header.col(|_| {});
                 resort = resort || show_sorting_header(
                     &mut header,
                     &mut tab.sorting,
@@ -166,6 +868,7 @@ impl egui_dock::TabViewer for AppData {
                     SortingColumn::Date,
                 );
                 resort = resort || show_sorting_header(&mut header, &mut tab.sorting, "Size", SortingColumn::Size);
+                resort = resort || show_sorting_header(&mut header, &mut tab.sorting, "Kind", SortingColumn::Kind);
             });
             if resort {
                 files::sort(entries, &tab.sorting);
@@ -176,6 +879,10 @@ impl egui_dock::TabViewer for AppData {
                     let mut close = false;
                     body.row(18.0, |mut row| {
                         row.set_selected(true);
+                        row.col(|ui| {
+                            let icon = if *is_dir { Icon::Folder } else { Icon::File };
+                            show_icon(ui, &mut self.icons, icon, !self.embedded_icons);
+                        });
                         row.col(|ui| {
                             let resp = TextEdit::singleline(name)
                                 .return_key(Some(egui::KeyboardShortcut::new(
@@ -194,16 +901,17 @@ impl egui_dock::TabViewer for AppData {
                                 resp.response.request_focus();
                             }
                         });
-                        row.col(|ui| {});
-                        row.col(|ui| {});
+                        row.col(|_| {});
+                        row.col(|_| {});
+                        row.col(|_| {});
                     });
                     if close {
-                        let path = Path::new(&tab.path).join(name);
-                        if *is_dir {
-                            let _ = std::fs::create_dir_all(path);
+                        let path = vfs::join(&tab.path, name);
+                        let _ = if *is_dir {
+                            tab.fs.create_dir(&path)
                         } else {
-                            let _ = std::fs::File::create(path);
-                        }
+                            tab.fs.create_file(&path)
+                        };
                         tab.state.relead = true;
                         tab.state.add_entry = None;
                     }
@@ -215,6 +923,9 @@ impl egui_dock::TabViewer for AppData {
                             let mut close = false;
                             body.row(18.0, |mut row| {
                                 row.set_selected(true);
+                                row.col(|ui| {
+                                    show_icon(ui, &mut self.icons, Icon::of(entry), !self.embedded_icons);
+                                });
                                 row.col(|ui| {
                                     let resp = TextEdit::singleline(&mut rename.new_name)
                                         .return_key(Some(egui::KeyboardShortcut::new(
@@ -241,20 +952,21 @@ impl egui_dock::TabViewer for AppData {
                                         ui.label(bytes_to_human_readable(entry.len));
                                     }
                                 });
+                                row.col(|ui| {
+                                    ui.label(entry.kind());
+                                });
                             });
                             if close {
                                 if rename.new_name != entry.file_name {
-                                    let source = Path::new(&rename.source_path);
-                                    let target = Path::new(&tab.info.as_ref().unwrap().path)
-                                        .join(&rename.new_name);
-                                    if rename.duplicate {
-                                        if entry.file_type.is_file() {
-                                            let _ = std::fs::copy(&source, &target);
-                                        } else if entry.file_type.is_dir() {
-                                            let _ = files::copy_dir(&source, &target);
-                                        }
+                                    let source = &rename.source_path;
+                                    let target = vfs::join(&tab.info.as_ref().unwrap().path, &rename.new_name);
+                                    let result = if rename.duplicate {
+                                        tab.fs.copy(source, &target, self.follow_symlinks)
                                     } else {
-                                        let _ = std::fs::rename(&source, &target);
+                                        tab.fs.rename(source, &target)
+                                    };
+                                    if let Err(e) = result {
+                                        tab.state.message = Some(format!("could not rename {}: {}", entry.file_name, e));
                                     }
                                 }
                                 tab.state.relead = true;
@@ -272,12 +984,52 @@ impl egui_dock::TabViewer for AppData {
                     {
                         body.row(18.0, |mut row| {
                             row.set_selected(tab.selected_entries.contains(&i));
+                            row.col(|ui| {
+                                show_icon(ui, &mut self.icons, Icon::of(entry), !self.embedded_icons);
+                            });
                             row.col(|ui| {
                                 let mut text: egui::RichText = entry.file_name.to_string().into();
                                 if entry.file_type.is_dir() {
                                     text = text.strong();
                                 }
-                                Label::new(text).selectable(false).ui(ui);
+                                if entry.ignored || entry.is_hidden() {
+                                    text = text.weak();
+                                }
+                                let git_status = tab
+                                    .git
+                                    .as_ref()
+                                    .and_then(|g| g.entry_status(&entry.path, entry.file_type.is_dir()));
+                                if let Some(status) = git_status {
+                                    ui.horizontal(|ui| {
+                                        let color = match status {
+                                            GitFileStatus::Conflicted => ui.visuals().error_fg_color,
+                                            GitFileStatus::Modified => ui.visuals().warn_fg_color,
+                                            GitFileStatus::Staged => Color32::from_rgb(80, 180, 80),
+                                            GitFileStatus::ContainsChanges => ui.visuals().warn_fg_color,
+                                            _ => ui.visuals().weak_text_color(),
+                                        };
+                                        if status == GitFileStatus::Ignored {
+                                            text = text.clone().weak();
+                                        }
+                                        Label::new(egui::RichText::new(status.letter()).monospace().color(color))
+                                            .selectable(false)
+                                            .ui(ui)
+                                            .on_hover_text(status.name());
+                                    });
+                                }
+                                if let Some(target) = &entry.symlink_target {
+                                    text = text.italics();
+                                    let mut target = egui::RichText::new(format!("→ {}", target)).weak();
+                                    if entry.is_broken_symlink() {
+                                        target = target.color(ui.visuals().error_fg_color);
+                                    }
+                                    ui.horizontal(|ui| {
+                                        Label::new(text).selectable(false).ui(ui);
+                                        Label::new(target).selectable(false).truncate().ui(ui);
+                                    });
+                                } else {
+                                    Label::new(text).selectable(false).ui(ui);
+                                }
                             });
                             row.col(|ui| {
                                 ui.label(entry.modified.format("%d/%m/%Y %H:%M").to_string());
@@ -287,10 +1039,23 @@ impl egui_dock::TabViewer for AppData {
                                     ui.label(bytes_to_human_readable(entry.len));
                                 }
                             });
+                            row.col(|ui| {
+                                Label::new(egui::RichText::new(entry.kind()).weak())
+                                    .selectable(false)
+                                    .truncate()
+                                    .ui(ui)
+                                    .on_hover_text(entry.mime());
+                            });
 
                             let resp = row.response();
-                            if resp.double_clicked() && entry.file_type.is_dir() {
+                            let is_archive = entry.file_type.is_file()
+                                && ArchiveFormat::of(&entry.file_name).is_some();
+                            if resp.double_clicked() && (entry.file_type.is_dir() || is_archive) {
                                 new_path = Some(entry.path.clone());
+                            } else if resp.double_clicked() && entry.file_type.is_file() && entry.archive.is_none() {
+                                if let Err(e) = launcher::open_as(&entry.path, entry.mime()) {
+                                    tab.state.message = Some(format!("could not open {}: {}", entry.file_name, e));
+                                }
                             }
 
                             let command = ctx.input(|i| i.modifiers.command);
@@ -332,21 +1097,20 @@ impl egui_dock::TabViewer for AppData {
                                 entries
                                     .iter()
                                     .enumerate()
-                                    .filter(|(i, x)| tab.selected_entries.contains(i))
-                                    .map(|(i, x)| x)
+                                    .filter(|(i, _)| tab.selected_entries.contains(i))
+                                    .map(|(_, x)| x)
                                     .collect()
                             };
                             resp.context_menu(|ui| {
                                 for action in self.actions.iter() {
-                                    if (action.can_execute)(&action_entries, is_main) {
+                                    if action.is_available(&action_entries, is_main, tab.git.as_ref().map(|g| g.root.as_path())) {
                                         if ui.button((action.name)(&action_entries)).clicked() {
-                                            for entry in action_entries.iter() {
-                                                (action.execute)(&entry, &mut tab.state);
-                                            }
+                                            (action.execute)(&action_entries, &mut tab.state);
                                             ui.close_menu();
                                         }
                                     }
                                 }
+                                open_with_menu(ui, &action_entries, &mut tab.state);
                             });
                             if resp.contains_pointer()
                                 && ctx.input(|i| i.pointer.primary_pressed())
@@ -357,20 +1121,25 @@ impl egui_dock::TabViewer for AppData {
                                     entries
                                         .iter()
                                         .enumerate()
-                                        .filter(|(i, x)| tab.selected_entries.contains(i))
-                                        .map(|(i, x)| (x.path.to_string(), x.file_name.to_string()))
+                                        .filter(|(i, _)| tab.selected_entries.contains(i))
+                                        .map(|(_, x)| (x.path.to_string(), x.file_name.to_string()))
                                         .collect(),
                                 ));
                                 //println!("drag");
                             }
-                            // if resp.contains_pointer()
-                            //     && self.drag_paths.is_some()
-                            //     && ctx.input(|i| i.pointer.primary_released())
-                            // {
-                            //     self.drop_path = Some(tab.path.to_string());
-                            //     tab.state.relead = true;
-                            //     println!("drop");
-                            // }
+                            // dropping onto a zip archive adds the dragged entries to it
+                            let is_zip = ArchiveFormat::of(&entry.file_name) == Some(ArchiveFormat::Zip);
+                            if is_zip
+                                && entry.file_type.is_file()
+                                && !tab.selected_entries.contains(&i)
+                                && resp.contains_pointer()
+                                && self.drag_paths.is_some()
+                                && ctx.input(|i| i.pointer.primary_released())
+                            {
+                                self.drop_path = Some(entry.path.to_string());
+                                self.drop_tab = Some(tab.id);
+                                tab.state.relead = true;
+                            }
                         });
                     }
                 }
@@ -397,21 +1166,20 @@ impl egui_dock::TabViewer for AppData {
                 entries
                     .iter()
                     .enumerate()
-                    .filter(|(i, x)| tab.selected_entries.contains(i))
-                    .map(|(i, x)| x)
+                    .filter(|(i, _)| tab.selected_entries.contains(i))
+                    .map(|(_, x)| x)
                     .collect()
             };
             resp.context_menu(|ui| {
                 for action in self.actions.iter() {
-                    if (action.can_execute)(&action_entries, is_main) {
+                    if action.is_available(&action_entries, is_main, tab.git.as_ref().map(|g| g.root.as_path())) {
                         if ui.button((action.name)(&action_entries)).clicked() {
-                            for entry in action_entries.iter() {
-                                (action.execute)(&entry, &mut tab.state);
-                            }
+                            (action.execute)(&action_entries, &mut tab.state);
                             ui.close_menu();
                         }
                     }
                 }
+                open_with_menu(ui, &action_entries, &mut tab.state);
             });
 
             if resp.contains_pointer()
@@ -419,100 +1187,1110 @@ impl egui_dock::TabViewer for AppData {
                 && ui.input(|i| i.pointer.primary_released())
             {
                 self.drop_path = Some(tab.path.to_string());
+                self.drop_tab = Some(tab.id);
                 tab.state.relead = true;
                 //println!("drop");
             }
         }
 
-        if let Some(zip) = &mut tab.state.extract_zip_archive {
-            egui::Window::new("extract zip archive")
+        if let Some(extract) = &mut tab.state.extract_archive {
+            let mut close = false;
+            egui::Window::new("extract archive")
                 .default_width(ui.available_width())
                 .show(ui.ctx(), |ui| {
-                    ui.vertical(|ui| {
-                        ui.label(format!("source: {}", zip.source));
-                        ui.horizontal(|ui| {
-                            ui.label("target:");
-                            let resp = TextEdit::singleline(&mut zip.target)
-                                .return_key(Some(egui::KeyboardShortcut::new(
-                                    Modifiers::NONE,
-                                    Key::Enter,
-                                )))
-                                .cursor_at_end(true)
-                                .desired_width(ui.available_width())
-                                .show(ui);
-                            if resp.response.lost_focus()
-                                && ui.input(|i| i.key_pressed(egui::Key::Enter))
-                            {
-                                let contents = std::fs::read(zip.source.clone()).unwrap();
-                                let _ = zip_extract::extract(
-                                    std::io::Cursor::new(contents),
-                                    Path::new(&zip.target),
-                                    zip.strip_toplevel,
+                    ui.label(format!("source: {}", extract.source));
+                    if let Some(job) = &mut extract.job {
+                        if job.is_running() {
+                            show_progress(ui, &job.progress);
+                            return;
+                        }
+                        match job.poll().unwrap() {
+                            Ok(errors) if errors.is_empty() => close = true,
+                            Ok(errors) => {
+                                ui.colored_label(
+                                    Color32::RED,
+                                    format!("{} entries could not be extracted:", errors.len()),
                                 );
-                                tab.state.relead = true;
+                                egui::ScrollArea::vertical().max_height(200.).show(ui, |ui| {
+                                    for error in errors.iter() {
+                                        ui.label(error);
+                                    }
+                                });
                             }
-                        });
-                        ui.checkbox(&mut zip.strip_toplevel, "strip toplevel");
+                            Err(e) => {
+                                ui.colored_label(Color32::RED, e.to_string());
+                            }
+                        }
+                        if ui.button("close").clicked() {
+                            close = true;
+                        }
+                        return;
+                    }
+                    let mut start = false;
+                    ui.horizontal(|ui| {
+                        ui.label("target:");
+                        let resp = TextEdit::singleline(&mut extract.target)
+                            .return_key(Some(egui::KeyboardShortcut::new(
+                                Modifiers::NONE,
+                                Key::Enter,
+                            )))
+                            .cursor_at_end(true)
+                            .desired_width(ui.available_width())
+                            .show(ui);
+                        start = resp.response.lost_focus()
+                            && ui.input(|i| i.key_pressed(egui::Key::Enter));
                     });
+                    ui.checkbox(&mut extract.strip_toplevel, "strip toplevel");
+                    if extract.encrypted {
+                        ui.horizontal(|ui| {
+                            ui.label("password:");
+                            ui.add(TextEdit::singleline(&mut extract.password).password(true));
+                        });
+                    }
+                    match &extract.listing {
+                        Ok(listing) => {
+                            ui.checkbox(&mut extract.choose, "only selected entries");
+                            if extract.choose {
+                                egui::ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+                                    for entry in listing.iter() {
+                                        let depth = entry.path.matches('/').count();
+                                        let name = entry.path.rsplit('/').next().unwrap_or_default();
+                                        let mut checked = extract.selected.contains(&entry.path);
+                                        ui.horizontal(|ui| {
+                                            ui.add_space(depth as f32 * 16.);
+                                            let label = if entry.file_type.is_dir() {
+                                                format!("{}/", name)
+                                            } else {
+                                                name.to_string()
+                                            };
+                                            if ui.checkbox(&mut checked, label).changed() {
+                                                if checked {
+                                                    extract.selected.insert(entry.path.clone());
+                                                } else {
+                                                    extract.selected.remove(&entry.path);
+                                                }
+                                            }
+                                        });
+                                    }
+                                });
+                            }
+                        }
+                        Err(e) => {
+                            ui.colored_label(Color32::RED, format!("could not read archive: {}", e));
+                        }
+                    }
+                    if ui.button("extract").clicked() || start {
+                        extract.start();
+                    }
                 });
-            if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
-                tab.state.extract_zip_archive = None;
+            if close || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                if extract.job.is_some() {
+                    tab.state.relead = true;
+                }
+                tab.state.extract_archive = None;
             }
         }
-        if let Some(zip) = &mut tab.state.zip_dir {
-            egui::Window::new("create zip archive")
+        if let Some(create) = &mut tab.state.create_archive {
+            let mut close = false;
+            egui::Window::new("create archive")
                 .default_width(ui.available_width())
                 .show(ui.ctx(), |ui| {
-                    ui.vertical(|ui| {
-                        ui.label(format!("source: {}", zip.source));
-                        ui.horizontal(|ui| {
-                            ui.label("target:");
-                            let resp = TextEdit::singleline(&mut zip.target)
-                                .return_key(Some(egui::KeyboardShortcut::new(
-                                    Modifiers::NONE,
-                                    Key::Enter,
-                                )))
-                                .cursor_at_end(true)
-                                .desired_width(ui.available_width())
-                                .show(ui);
-                            if resp.response.lost_focus()
-                                && ui.input(|i| i.key_pressed(egui::Key::Enter))
-                            {
-                                let _ = crate::zip::zip_dir(
-                                    Path::new(&zip.source),
-                                    Path::new(&zip.target),
-                                    zip.method,
-                                );
-                                tab.state.relead = true;
+                    match create.sources.as_slice() {
+                        [source] => ui.label(format!("source: {}", source)),
+                        sources => ui.label(format!("{} items from {}", sources.len(), create.base)),
+                    };
+                    if let Some(job) = &mut create.job {
+                        if job.is_running() {
+                            show_progress(ui, &job.progress);
+                            return;
+                        }
+                        match job.poll().unwrap() {
+                            Ok(_) => close = true,
+                            Err(e) => {
+                                ui.colored_label(Color32::RED, e.to_string());
+                                if ui.button("back").clicked() {
+                                    create.job = None;
+                                }
                             }
-                        });
-                        egui::ComboBox::from_label("compression method")
-                            .selected_text(&zip.method.to_string())
+                        }
+                        return;
+                    }
+                    let mut start = false;
+                    ui.horizontal(|ui| {
+                        ui.label("target:");
+                        let resp = TextEdit::singleline(&mut create.target)
+                            .return_key(Some(egui::KeyboardShortcut::new(
+                                Modifiers::NONE,
+                                Key::Enter,
+                            )))
+                            .cursor_at_end(true)
+                            .desired_width(ui.available_width())
+                            .show(ui);
+                        start = resp.response.lost_focus()
+                            && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    });
+                    let mut format = create.options.format;
+                    egui::ComboBox::from_label("format")
+                        .selected_text(format.extension())
+                        .show_ui(ui, |ui| {
+                            for f in ArchiveFormat::ALL {
+                                ui.selectable_value(&mut format, f, f.extension());
+                            }
+                        });
+                    if format != create.options.format {
+                        create.set_format(format);
+                    }
+                    let options = &mut create.options;
+                    if options.format == ArchiveFormat::Zip {
+                        let zip = &mut options.zip;
+                        egui::ComboBox::from_label("compression method")
+                            .selected_text(zip.method.to_string())
                             .show_ui(ui, |ui| {
-                                ui.selectable_value(
-                                    &mut zip.method,
-                                    zip::CompressionMethod::Stored,
-                                    zip::CompressionMethod::Stored.to_string(),
-                                );
-                                ui.selectable_value(
-                                    &mut zip.method,
-                                    zip::CompressionMethod::Deflated,
-                                    zip::CompressionMethod::Deflated.to_string(),
+                                for method in crate::zip::METHODS {
+                                    if ui
+                                        .selectable_value(&mut zip.method, method, method.to_string())
+                                        .changed()
+                                    {
+                                        zip.level = None;
+                                    }
+                                }
+                            });
+                        if let Some(range) = crate::zip::level_range(zip.method) {
+                            ui.horizontal(|ui| {
+                                let mut custom = zip.level.is_some();
+                                ui.checkbox(&mut custom, "compression level");
+                                if !custom {
+                                    zip.level = None;
+                                } else {
+                                    let level = zip.level.get_or_insert(6);
+                                    ui.add(egui::Slider::new(level, range));
+                                }
+                            });
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("password (AES-256):");
+                            ui.add(TextEdit::singleline(&mut zip.password).password(true));
+                        });
+                        ui.label("comment:");
+                        ui.add(TextEdit::multiline(&mut zip.comment).desired_rows(2));
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("exclude:");
+                        ui.add(TextEdit::singleline(&mut options.exclude).hint_text(".DS_Store, target/"));
+                    });
+                    ui.checkbox(&mut options.filter.show_hidden, "include hidden files");
+                    ui.checkbox(&mut options.follow_links, "follow symlinks");
+                    if ui.button("create").clicked() || start {
+                        create.start();
+                    }
+                });
+            if close || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                if let Some(job) = &create.job {
+                    job.cancel();
+                }
+                tab.state.relead |= close;
+                tab.state.create_archive = None;
+            }
+        }
+        if let Some(checksums) = &mut tab.state.checksums {
+            let mut open = true;
+            egui::Window::new("checksums")
+                .open(&mut open)
+                .default_width(ui.available_width())
+                .show(ui.ctx(), |ui| {
+                    let mut changed = false;
+                    ui.horizontal(|ui| {
+                        for algorithm in Algorithm::ALL {
+                            let mut checked = checksums.algorithms.contains(&algorithm);
+                            if ui.checkbox(&mut checked, algorithm.name()).changed() {
+                                checksums.algorithms.retain(|a| *a != algorithm);
+                                if checked {
+                                    checksums.algorithms.push(algorithm);
+                                }
+                                changed = true;
+                            }
+                        }
+                    });
+                    if changed {
+                        // keep the order of the checkboxes
+                        checksums.algorithms.sort_by_key(|a| Algorithm::ALL.iter().position(|x| x == a));
+                        checksums.rerun();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("compare with:");
+                        ui.add(
+                            TextEdit::singleline(&mut checksums.expected)
+                                .hint_text("paste a hash")
+                                .desired_width(ui.available_width()),
+                        );
+                    });
+                    if checksums.job.is_running() {
+                        show_progress(ui, &checksums.job.progress);
+                        return;
+                    }
+                    let expected = checksums.expected.trim().to_lowercase();
+                    let mut matched = false;
+                    egui::ScrollArea::vertical().max_height(400.).show(ui, |ui| {
+                        for file in checksums.job.poll().unwrap().iter() {
+                            ui.strong(&file.path);
+                            let hashes = match &file.hashes {
+                                Ok(hashes) => hashes,
+                                Err(e) => {
+                                    ui.colored_label(Color32::RED, e);
+                                    continue;
+                                }
+                            };
+                            egui::Grid::new(&file.path).num_columns(3).show(ui, |ui| {
+                                for (algorithm, hash) in hashes {
+                                    ui.label(algorithm.name());
+                                    let text = egui::RichText::new(hash).monospace();
+                                    if !expected.is_empty() && *hash == expected {
+                                        matched = true;
+                                        ui.label(text.color(Color32::GREEN));
+                                    } else {
+                                        ui.label(text);
+                                    }
+                                    if ui.small_button("📋").on_hover_text("copy").clicked() {
+                                        ui.ctx().copy_text(hash.clone());
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                        }
+                    });
+                    if !expected.is_empty() {
+                        if matched {
+                            ui.colored_label(Color32::GREEN, "✔ the hash matches");
+                        } else {
+                            ui.colored_label(Color32::RED, "✘ no computed hash matches");
+                        }
+                    }
+                });
+            if !open || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                checksums.job.cancel();
+                tab.state.checksums = None;
+            }
+        }
+        if let Some(manifest) = &mut tab.state.create_manifest {
+            let mut open = true;
+            let mut done = false;
+            egui::Window::new("create checksum manifest")
+                .open(&mut open)
+                .default_width(ui.available_width())
+                .show(ui.ctx(), |ui| {
+                    ui.label(format!("folder: {}", manifest.dir));
+                    if let Some(job) = &mut manifest.job {
+                        if job.is_running() {
+                            show_progress(ui, &job.progress);
+                            return;
+                        }
+                        match job.poll().unwrap() {
+                            Ok(written) => {
+                                done = true;
+                                ui.label(format!("wrote {} checksums to {}", written.count, written.path.display()));
+                                if !written.failed.is_empty() {
+                                    ui.colored_label(
+                                        Color32::RED,
+                                        format!("{} files could not be read and are left out:", written.failed.len()),
+                                    );
+                                    egui::ScrollArea::vertical().max_height(200.).show(ui, |ui| {
+                                        for failure in written.failed.iter() {
+                                            ui.label(failure);
+                                        }
+                                    });
+                                }
+                            }
+                            Err(e) => {
+                                ui.colored_label(Color32::RED, e.to_string());
+                            }
+                        }
+                        return;
+                    }
+                    egui::ComboBox::from_label("algorithm")
+                        .selected_text(manifest.algorithm.name())
+                        .show_ui(ui, |ui| {
+                            for algorithm in Algorithm::ALL {
+                                ui.selectable_value(&mut manifest.algorithm, algorithm, algorithm.name());
+                            }
+                        });
+                    ui.checkbox(&mut manifest.filter.show_hidden, "include hidden files");
+                    ui.label(format!("writes {}", manifest.algorithm.manifest_name()));
+                    if ui.button("create").clicked() {
+                        manifest.start();
+                    }
+                });
+            if !open || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                if let Some(job) = &manifest.job {
+                    job.cancel();
+                }
+                tab.state.relead |= done;
+                tab.state.create_manifest = None;
+            }
+        }
+        if let Some(verify) = &mut tab.state.verify_manifest {
+            let mut open = true;
+            egui::Window::new("verify checksums")
+                .open(&mut open)
+                .default_width(ui.available_width())
+                .show(ui.ctx(), |ui| {
+                    ui.label(format!("manifest: {}", verify.manifest));
+                    if verify.job.is_running() {
+                        show_progress(ui, &verify.job.progress);
+                        return;
+                    }
+                    let entries = match verify.job.poll().unwrap() {
+                        Ok(entries) => entries,
+                        Err(e) => {
+                            ui.colored_label(Color32::RED, format!("could not read manifest: {}", e));
+                            return;
+                        }
+                    };
+                    let count = |status: &VerifyStatus| entries.iter().filter(|e| e.status == *status).count();
+                    let ok = count(&VerifyStatus::Ok);
+                    ui.label(format!(
+                        "{} of {} files ok, {} mismatched, {} missing",
+                        ok,
+                        entries.len(),
+                        count(&VerifyStatus::Mismatch),
+                        count(&VerifyStatus::Missing),
+                    ));
+                    egui::ScrollArea::vertical().max_height(400.).show(ui, |ui| {
+                        for entry in entries.iter() {
+                            match &entry.status {
+                                VerifyStatus::Ok => {}
+                                VerifyStatus::Mismatch => {
+                                    ui.colored_label(Color32::RED, format!("mismatch: {}", entry.path));
+                                }
+                                VerifyStatus::Missing => {
+                                    ui.colored_label(Color32::YELLOW, format!("missing: {}", entry.path));
+                                }
+                                VerifyStatus::Error(e) => {
+                                    ui.colored_label(Color32::RED, format!("{}: {}", entry.path, e));
+                                }
+                            }
+                        }
+                    });
+                });
+            if !open || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                verify.job.cancel();
+                tab.state.verify_manifest = None;
+            }
+        }
+        if let Some(output) = &mut tab.state.command_output {
+            let mut open = true;
+            let mut done = false;
+            egui::Window::new(output.name.as_str())
+                .id(Id::new(("command_output", tab.id)))
+                .open(&mut open)
+                .default_width(ui.available_width())
+                .show(ui.ctx(), |ui| {
+                    if output.job.is_running() {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(format!("running… {}", output.job.progress.message()));
+                        });
+                        ui.ctx()
+                            .request_repaint_after(std::time::Duration::from_millis(100));
+                        return;
+                    }
+                    done = true;
+                    let outputs = output.job.poll().unwrap();
+                    let many = outputs.len() > 1;
+                    egui::ScrollArea::vertical().max_height(600.).show(ui, |ui| {
+                        for (i, (label, result)) in outputs.iter().enumerate() {
+                            if many {
+                                ui.separator();
+                                ui.strong(label);
+                            }
+                            let result = match result {
+                                Ok(result) => result,
+                                Err(e) => {
+                                    ui.colored_label(Color32::RED, e.as_str());
+                                    continue;
+                                }
+                            };
+                            if result.status.success() {
+                                ui.label(format!("✔ {}", result.status));
+                            } else {
+                                ui.colored_label(Color32::RED, format!("✘ {}", result.status));
+                            }
+                            for (name, bytes) in [("output", &result.stdout), ("errors", &result.stderr)] {
+                                if bytes.is_empty() {
+                                    continue;
+                                }
+                                let text = String::from_utf8_lossy(bytes).to_string();
+                                ui.horizontal(|ui| {
+                                    ui.strong(name);
+                                    if ui.small_button("📋").on_hover_text("copy").clicked() {
+                                        ui.ctx().copy_text(text.clone());
+                                    }
+                                });
+                                egui::ScrollArea::vertical().id_salt((name, i)).max_height(250.).show(ui, |ui| {
+                                    ui.add(
+                                        TextEdit::multiline(&mut text.as_str())
+                                            .code_editor()
+                                            .desired_width(f32::INFINITY),
+                                    );
+                                });
+                            }
+                        }
+                    });
+                });
+            if !open || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                // commands that didn't start yet are skipped
+                output.job.cancel();
+                // the command might have changed the folder
+                tab.state.relead |= done;
+                tab.state.command_output = None;
+            }
+        }
+        if let Some(test) = &mut tab.state.test_archive {
+            let mut open = true;
+            egui::Window::new("test archive")
+                .open(&mut open)
+                .default_width(ui.available_width())
+                .show(ui.ctx(), |ui| {
+                    ui.label(format!("testing {}", test.archive));
+                    if test.job.is_running() {
+                        show_progress(ui, &test.job.progress);
+                        return;
+                    }
+                    match test.job.poll().unwrap() {
+                        Ok(problems) if problems.is_empty() => {
+                            ui.label("✔ all entries are intact");
+                        }
+                        Ok(problems) => {
+                            ui.colored_label(Color32::RED, format!("{} problems found:", problems.len()));
+                            egui::ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+                                for problem in problems.iter() {
+                                    ui.label(problem);
+                                }
+                            });
+                        }
+                        Err(e) => {
+                            ui.colored_label(Color32::RED, format!("could not read archive: {}", e));
+                        }
+                    }
+                });
+            if !open || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                test.job.cancel();
+                tab.state.test_archive = None;
+            }
+        }
+        if let Some(add) = &mut tab.state.add_to_archive {
+            let mut close = false;
+            egui::Window::new("add to zip archive")
+                .default_width(ui.available_width())
+                .show(ui.ctx(), |ui| {
+                    ui.label(format!("{} items", add.sources.len()));
+                    if let Some(job) = &mut add.job {
+                        if job.is_running() {
+                            show_progress(ui, &job.progress);
+                            return;
+                        }
+                        match job.poll().unwrap() {
+                            Ok(_) => close = true,
+                            Err(e) => {
+                                ui.colored_label(Color32::RED, e.to_string());
+                                if ui.button("back").clicked() {
+                                    add.job = None;
+                                }
+                            }
+                        }
+                        return;
+                    }
+                    let mut start = false;
+                    ui.horizontal(|ui| {
+                        ui.label("archive:");
+                        let resp = TextEdit::singleline(&mut add.target)
+                            .return_key(Some(egui::KeyboardShortcut::new(
+                                Modifiers::NONE,
+                                Key::Enter,
+                            )))
+                            .hint_text("/path/to/archive.zip/folder")
+                            .cursor_at_end(true)
+                            .desired_width(ui.available_width())
+                            .show(ui);
+                        start = resp.response.lost_focus()
+                            && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    });
+                    ui.checkbox(&mut add.filter.show_hidden, "include hidden files");
+                    ui.checkbox(&mut add.follow_links, "follow symlinks");
+                    if ui.button("add").clicked() || start {
+                        add.start();
+                    }
+                });
+            if close || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                if let Some(job) = &add.job {
+                    job.cancel();
+                }
+                tab.state.relead |= close;
+                tab.state.add_to_archive = None;
+            }
+        }
+
+        if let Some(duplicates) = &mut tab.state.duplicates {
+            let mut open = true;
+            egui::Window::new("duplicates")
+                .open(&mut open)
+                .default_width(ui.available_width())
+                .show(ui.ctx(), |ui| {
+                    ui.label(format!("searching in: {}", duplicates.roots.join(", ")));
+                    if duplicates.job.is_running() {
+                        show_progress(ui, &duplicates.job.progress);
+                        return;
+                    }
+                    let groups = duplicates.job.poll().unwrap();
+                    let reclaimable: u64 = groups.iter().map(|g| g.reclaimable()).sum();
+                    ui.label(format!(
+                        "{} groups of duplicates, {} reclaimable",
+                        groups.len(),
+                        bytes_to_human_readable(reclaimable)
+                    ));
+                    let mut trash = false;
+                    let mut link = false;
+                    ui.horizontal(|ui| {
+                        if duplicates.confirm_trash {
+                            let count: usize = groups.iter().map(|g| g.paths.len() - 1).sum();
+                            ui.label(format!("move {} files to the trash?", count));
+                            trash = ui.button("trash").clicked();
+                            if ui.button("cancel").clicked() {
+                                duplicates.confirm_trash = false;
+                            }
+                        } else {
+                            duplicates.confirm_trash = ui.button("keep one, trash the rest").clicked();
+                            link = ui.button("replace duplicates with hard links").clicked();
+                        }
+                    });
+                    if trash || link {
+                        duplicates.confirm_trash = false;
+                        let mut failed = 0;
+                        let mut reclaimed = 0;
+                        groups.retain(|group| {
+                            let result = if trash {
+                                crate::duplicates::trash_duplicates(group)
+                            } else {
+                                crate::duplicates::link_duplicates(group).map_err(|e| e.into())
+                            };
+                            match result {
+                                Ok(()) => reclaimed += group.reclaimable(),
+                                Err(_) => failed += 1,
+                            }
+                            result.is_err()
+                        });
+                        duplicates.message = Some(if failed == 0 {
+                            format!("reclaimed {}", bytes_to_human_readable(reclaimed))
+                        } else {
+                            format!(
+                                "reclaimed {}, {} groups could not be processed",
+                                bytes_to_human_readable(reclaimed),
+                                failed
+                            )
+                        });
+                    }
+                    if let Some(message) = &duplicates.message {
+                        ui.label(message);
+                    }
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for (i, group) in groups.iter_mut().enumerate() {
+                            egui::CollapsingHeader::new(format!(
+                                "{} × {}",
+                                group.paths.len(),
+                                bytes_to_human_readable(group.len)
+                            ))
+                            .id_salt(i)
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                for (j, path) in group.paths.iter().enumerate() {
+                                    ui.radio_value(&mut group.keep, j, path);
+                                }
+                            });
+                        }
+                    });
+                });
+            if !open || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                duplicates.job.cancel();
+                tab.state.relead = duplicates.message.is_some();
+                tab.state.duplicates = None;
+            }
+        }
+
+        if let Some(request) = &mut tab.state.compare_dirs {
+            let mut close = false;
+            egui::Window::new("compare folders")
+                .default_width(ui.available_width())
+                .show(ui.ctx(), |ui| {
+                    ui.label(format!("left: {}", request.left));
+                    ui.horizontal(|ui| {
+                        ui.label("right:");
+                        egui::ComboBox::from_id_salt("compare_right")
+                            .selected_text("open tabs")
+                            .show_ui(ui, |ui| {
+                                for path in self.open_paths.iter() {
+                                    if path != &request.left {
+                                        ui.selectable_value(&mut request.right, path.clone(), path);
+                                    }
+                                }
+                            });
+                        TextEdit::singleline(&mut request.right)
+                            .desired_width(ui.available_width())
+                            .show(ui);
+                    });
+                    ui.checkbox(&mut request.content, "compare content hashes");
+                    if ui.button("compare").clicked() && !request.right.is_empty() {
+                        self.new_views.push((
+                            request.left.clone(),
+                            TabView::Compare(CompareDirs::new(
+                                request.left.clone(),
+                                request.right.clone(),
+                                request.content,
+                            )),
+                        ));
+                        close = true;
+                    }
+                });
+            if close || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                tab.state.compare_dirs = None;
+            }
+        }
+
+        if let Some(sync) = &mut tab.state.sync {
+            let mut open = true;
+            egui::Window::new("synchronize folders")
+                .open(&mut open)
+                .default_width(ui.available_width())
+                .show(ui.ctx(), |ui| {
+                    let idle = sync.run.as_mut().is_none_or(|r| !r.is_running());
+                    ui.add_enabled_ui(idle, |ui| {
+                        egui::Grid::new("sync_grid").num_columns(2).show(ui, |ui| {
+                            ui.label("source:");
+                            TextEdit::singleline(&mut sync.source)
+                                .desired_width(ui.available_width())
+                                .show(ui);
+                            ui.end_row();
+                            ui.label("target:");
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_id_salt("sync_target")
+                                    .selected_text("open tabs")
+                                    .show_ui(ui, |ui| {
+                                        for path in self.open_paths.iter() {
+                                            ui.selectable_value(&mut sync.target, path.clone(), path);
+                                        }
+                                    });
+                                TextEdit::singleline(&mut sync.target)
+                                    .desired_width(ui.available_width())
+                                    .show(ui);
+                            });
+                            ui.end_row();
+                            ui.label("mode:");
+                            ui.horizontal(|ui| {
+                                for mode in [SyncMode::Mirror, SyncMode::Update, SyncMode::TwoWay] {
+                                    ui.radio_value(&mut sync.mode, mode, mode.name());
+                                }
+                            });
+                            ui.end_row();
+                            ui.label("include:");
+                            TextEdit::singleline(&mut sync.include)
+                                .hint_text("*.png, assets/**")
+                                .desired_width(ui.available_width())
+                                .show(ui);
+                            ui.end_row();
+                            ui.label("exclude:");
+                            TextEdit::singleline(&mut sync.exclude)
+                                .desired_width(ui.available_width())
+                                .show(ui);
+                            ui.end_row();
+                        });
+                        if ui.button("preview").clicked() {
+                            let (source, target, mode) = (sync.source.clone(), sync.target.clone(), sync.mode);
+                            let filter = files::GlobFilter::new(&sync.include, &sync.exclude);
+                            sync.run = None;
+                            sync.plan = Some(Job::spawn(move |p| {
+                                let filter = filter.map_err(|e| e.to_string())?;
+                                crate::sync::plan(&source, &target, mode, &filter, p)
+                                    .map_err(|e| e.to_string())
+                            }));
+                        }
+                    });
+
+                    if let Some(run) = &mut sync.run {
+                        if run.is_running() {
+                            show_progress(ui, &run.progress);
+                        } else {
+                            let errors = run.poll().unwrap();
+                            if errors.is_empty() {
+                                ui.label("synchronized");
+                            }
+                            egui::ScrollArea::vertical().show(ui, |ui| {
+                                for error in errors.iter() {
+                                    ui.label(error);
+                                }
+                            });
+                        }
+                        return;
+                    }
+
+                    let Some(plan) = &mut sync.plan else {
+                        return;
+                    };
+                    if plan.is_running() {
+                        show_progress(ui, &plan.progress);
+                        return;
+                    }
+                    match plan.poll().unwrap() {
+                        Err(e) => {
+                            ui.label(e.as_str());
+                        }
+                        Ok(ops) => {
+                            ui.label(format!("{} planned operations", ops.len()));
+                            if !ops.is_empty() && ui.button("run").clicked() {
+                                let ops = std::mem::take(ops);
+                                sync.run = Some(Job::spawn(move |p| crate::sync::execute(&ops, p)));
+                                return;
+                            }
+                            egui::ScrollArea::vertical().show(ui, |ui| {
+                                for op in ops.iter() {
+                                    ui.label(op.describe());
+                                }
+                            });
+                        }
+                    }
+                });
+            if !open || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                if let Some(run) = &sync.run {
+                    run.cancel();
+                    tab.state.relead = true;
+                }
+                tab.state.sync = None;
+            }
+        }
+
+        if let Some(editor) = &mut tab.state.permissions {
+            let mut open = true;
+            let mut apply = false;
+            egui::Window::new("permissions")
+                .open(&mut open)
+                .show(ui.ctx(), |ui| {
+                    if editor.paths.len() == 1 {
+                        ui.label(&editor.paths[0]);
+                    } else {
+                        ui.label(format!("{} entries", editor.paths.len()));
+                    }
+                    if editor.has_files || editor.recursive {
+                        ui.checkbox(&mut editor.change_files, "files");
+                        ui.add_enabled_ui(editor.change_files, |ui| {
+                            mode_editor(ui, "file_mode", &mut editor.file_mode);
+                        });
+                    }
+                    if editor.has_dirs {
+                        ui.checkbox(&mut editor.change_dirs, "folders");
+                        ui.add_enabled_ui(editor.change_dirs, |ui| {
+                            mode_editor(ui, "dir_mode", &mut editor.dir_mode);
+                        });
+                    }
+                    egui::Grid::new("owner_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("owner:");
+                        ui.text_edit_singleline(&mut editor.owner);
+                        ui.end_row();
+                        ui.label("group:");
+                        ui.text_edit_singleline(&mut editor.group);
+                        ui.end_row();
+                    });
+                    ui.checkbox(&mut editor.recursive, "apply recursively");
+                    apply = ui.button("apply").clicked();
+                    for error in editor.errors.iter() {
+                        ui.label(error);
+                    }
+                });
+            if apply {
+                editor.apply();
+                if editor.errors.is_empty() {
+                    tab.state.relead = true;
+                }
+            }
+            if !open || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                tab.state.permissions = None;
+            }
+        }
+
+        if let Some(properties) = &mut tab.state.properties {
+            let mut open = true;
+            let mut checksums = false;
+            egui::Window::new("properties")
+                .open(&mut open)
+                .show(ui.ctx(), |ui| {
+                    let row = |ui: &mut egui::Ui, name: &str, value: String| {
+                        ui.label(name);
+                        ui.add(Label::new(value).selectable(true));
+                        ui.end_row();
+                    };
+                    egui::Grid::new("properties_grid").num_columns(2).show(ui, |ui| {
+                        if let [item] = properties.items.as_slice() {
+                            row(ui, "path:", item.path.clone());
+                            row(ui, "kind:", item.kind().to_string());
+                            if let Some(target) = &item.symlink_target {
+                                row(ui, "link target:", target.clone());
+                            }
+                            if let Some(mime) = &item.mime {
+                                row(ui, "MIME type:", mime.clone());
+                            }
+                            if item.file_type.is_file() {
+                                row(ui, "size:", format_size(item.len));
+                            }
+                            if let Some(created) = &item.created {
+                                row(ui, "created:", format_time(created));
+                            }
+                            row(ui, "modified:", format_time(&item.modified));
+                            row(ui, "accessed:", format_time(&item.accessed));
+                            row(ui, "changed:", format_time(&item.changed));
+                            row(ui, "permissions:", item.permissions());
+                            row(ui, "owner:", format!("{}:{}", item.owner, item.group));
+                            row(ui, "inode:", item.inode.to_string());
+                            row(ui, "device:", item.device.to_string());
+                            row(ui, "hard links:", item.links.to_string());
+                        } else {
+                            let folders = properties.items.iter().filter(|i| i.file_type.is_dir()).count();
+                            row(
+                                ui,
+                                "selected:",
+                                format!(
+                                    "{} items, {} files, {} folders",
+                                    properties.items.len(),
+                                    properties.items.len() - folders,
+                                    folders
+                                ),
+                            );
+                            row(ui, "kind:", properties.common(|i| i.kind().to_string()));
+                            row(ui, "MIME type:", properties.common(|i| i.mime.clone().unwrap_or_default()));
+                            row(ui, "size of files:", format_size(properties.own_size()));
+                            if let Some((first, last)) = properties.modified_range() {
+                                row(ui, "modified from:", format_time(&first));
+                                row(ui, "modified to:", format_time(&last));
+                            }
+                            row(ui, "permissions:", properties.common(|i| i.permissions()));
+                            row(ui, "owner:", properties.common(|i| format!("{}:{}", i.owner, i.group)));
+                        }
+                        let own_size = properties.own_size();
+                        if let Some(job) = &mut properties.contents {
+                            if job.is_running() {
+                                let progress = &job.progress;
+                                ui.label("contents:");
+                                ui.horizontal(|ui| {
+                                    ui.spinner();
+                                    if ui.small_button("cancel").clicked() {
+                                        progress.cancel();
+                                    }
+                                });
+                                ui.end_row();
+                            } else {
+                                let contents = job.poll().unwrap();
+                                row(
+                                    ui,
+                                    "contents:",
+                                    format!("{} files, {} folders", contents.files, contents.folders),
                                 );
-                                // ui.selectable_value(&mut zip.method, zip::CompressionMethod::Bzip2, zip::CompressionMethod::Bzip2.to_string());
-                                // ui.selectable_value(&mut zip.method, zip::CompressionMethod::Zstd, zip::CompressionMethod::Zstd.to_string());
-                                // ui.selectable_value(&mut zip.method, zip::CompressionMethod::Lzma, zip::CompressionMethod::Lzma.to_string());
-                                // ui.selectable_value(&mut zip.method, zip::CompressionMethod::Xz, zip::CompressionMethod::Xz.to_string());
+                                row(ui, "total size:", format_size(contents.bytes + own_size));
+                                if contents.unreadable > 0 {
+                                    row(ui, "unreadable:", format!("{} entries", contents.unreadable));
+                                }
+                            }
+                        }
+                    });
+                    for error in properties.errors.iter() {
+                        ui.colored_label(Color32::RED, error);
+                    }
+                    let files = properties.files();
+                    if !files.is_empty() {
+                        checksums = ui.button("checksums…").clicked();
+                    }
+                });
+            if checksums {
+                tab.state.checksums = Some(Checksums::new(properties.files()));
+            }
+            if !open || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                if let Some(job) = &properties.contents {
+                    job.cancel();
+                }
+                tab.state.properties = None;
+            }
+        }
+
+        if let Some(editor) = &mut tab.state.timestamps {
+            let mut open = true;
+            let mut apply = false;
+            egui::Window::new("change timestamps")
+                .open(&mut open)
+                .show(ui.ctx(), |ui| {
+                    if editor.paths.len() == 1 {
+                        ui.label(&editor.paths[0]);
+                    } else {
+                        ui.label(format!("{} entries", editor.paths.len()));
+                    }
+                    ui.horizontal(|ui| {
+                        for source in TimeSource::ALL {
+                            ui.radio_value(&mut editor.source, source, source.name());
+                        }
+                    });
+                    egui::Grid::new("timestamps_grid").num_columns(2).show(ui, |ui| {
+                        ui.checkbox(&mut editor.set_modified, "modified");
+                        if editor.source == TimeSource::Fixed {
+                            ui.add_enabled(editor.set_modified, TextEdit::singleline(&mut editor.modified));
+                        }
+                        ui.end_row();
+                        ui.checkbox(&mut editor.set_accessed, "accessed");
+                        if editor.source == TimeSource::Fixed {
+                            ui.add_enabled(editor.set_accessed, TextEdit::singleline(&mut editor.accessed));
+                        }
+                        ui.end_row();
+                        match editor.source {
+                            TimeSource::CopyFrom => {
+                                ui.label("file:");
+                                ui.add(TextEdit::singleline(&mut editor.reference).hint_text("/path/to/reference"));
+                                ui.end_row();
+                            }
+                            TimeSource::Shift => {
+                                ui.label("offset:");
+                                ui.add(TextEdit::singleline(&mut editor.offset).hint_text("-2d 3h"));
+                                ui.end_row();
+                            }
+                            _ => {}
+                        }
+                    });
+                    match editor.source {
+                        TimeSource::Fixed => {
+                            ui.small(format!("format: {}", timestamps::TIME_FORMAT));
+                        }
+                        TimeSource::Shift => {
+                            ui.small("units: w, d, h, m, s");
+                        }
+                        _ => {}
+                    }
+                    ui.checkbox(&mut editor.recursive, "apply recursively");
+                    apply = ui
+                        .add_enabled(editor.set_modified || editor.set_accessed, egui::Button::new("apply"))
+                        .clicked();
+                    for error in editor.errors.iter() {
+                        ui.colored_label(Color32::RED, error);
+                    }
+                });
+            if apply {
+                editor.apply();
+                if editor.errors.is_empty() {
+                    tab.state.relead = true;
+                }
+            }
+            if !open || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                tab.state.timestamps = None;
+            }
+        }
+
+        if let Some(link) = &mut tab.state.create_link {
+            let mut close = false;
+            let title = if link.symbolic { "create symbolic link" } else { "create hard link" };
+            egui::Window::new(title)
+                .default_width(ui.available_width())
+                .show(ui.ctx(), |ui| {
+                    ui.label(format!("target: {}", link.source));
+                    ui.horizontal(|ui| {
+                        ui.label("link:");
+                        let resp = TextEdit::singleline(&mut link.link)
+                            .return_key(Some(egui::KeyboardShortcut::new(
+                                Modifiers::NONE,
+                                Key::Enter,
+                            )))
+                            .cursor_at_end(true)
+                            .desired_width(ui.available_width())
+                            .show(ui);
+                        if resp.response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            match link.create() {
+                                Ok(_) => close = true,
+                                Err(e) => link.error = Some(e.to_string()),
+                            }
+                        }
+                    });
+                    if let Some(error) = &link.error {
+                        ui.label(error);
+                    }
+                });
+            if close {
+                tab.state.relead = true;
+            }
+            if close || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                tab.state.create_link = None;
+            }
+        }
+
+        if let Some(paths) = &tab.state.discard_changes {
+            let mut close = false;
+            egui::Window::new("git discard changes")
+                .id(Id::new(("discard_changes", tab.id)))
+                .show(ui.ctx(), |ui| {
+                    ui.label(format!(
+                        "throw away the uncommitted changes of {} entries? this can't be undone",
+                        paths.len()
+                    ));
+                    ui.horizontal(|ui| {
+                        if ui.button("discard").clicked() {
+                            match crate::git::discard(paths) {
+                                Ok(_) => tab.state.relead = true,
+                                Err(e) => tab.state.message = Some(e),
+                            }
+                            close = true;
+                        }
+                        close |= ui.button("cancel").clicked();
+                    });
+                });
+            if close || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                tab.state.discard_changes = None;
+            }
+        }
+
+        if let Some(extract) = &mut tab.state.extract_entries {
+            let mut close = false;
+            egui::Window::new("extract entries")
+                .default_width(ui.available_width())
+                .show(ui.ctx(), |ui| {
+                    ui.label(format!("{} entries from {}", extract.entries.len(), extract.archive));
+                    ui.horizontal(|ui| {
+                        ui.label("target:");
+                        let resp = TextEdit::singleline(&mut extract.target)
+                            .return_key(Some(egui::KeyboardShortcut::new(
+                                Modifiers::NONE,
+                                Key::Enter,
+                            )))
+                            .cursor_at_end(true)
+                            .desired_width(ui.available_width())
+                            .show(ui);
+                        if resp.response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            let result = extract.entries.iter().try_for_each(|inner| {
+                                crate::archive::extract_entry(&extract.archive, inner, Path::new(&extract.target))
                             });
+                            match result {
+                                Ok(_) => close = true,
+                                Err(e) => extract.error = Some(e.to_string()),
+                            }
+                        }
                     });
+                    if let Some(error) = &extract.error {
+                        ui.label(error);
+                    }
                 });
-            if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
-                tab.state.zip_dir = None;
+            if close || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                tab.state.extract_entries = None;
             }
         }
 
-        if tab.state.relead {
+        if let Some(view) = tab.state.open_view.take() {
+            self.new_views.push((tab.path.clone(), view));
+        }
+
+        if let Some(path) = tab.state.navigate.take() {
+            tab.refresh(path);
+        } else if tab.state.relead {
+            // the reloaded tab still shows what went wrong
+            let message = tab.state.message.take();
             tab.refresh_hard(tab.path.clone());
+            tab.state.message = message;
         }
     }
 }
@@ -0,0 +1,291 @@
+use std::{fs, io, path::Path};
+
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone};
+use filetime::FileTime;
+use walkdir::WalkDir;
+
+pub const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeSource {
+    Now,
+    /// The dates typed into the editor.
+    Fixed,
+    /// The times of a reference file.
+    CopyFrom,
+    /// The current times of every entry moved by an offset.
+    Shift,
+}
+
+impl TimeSource {
+    pub const ALL: [TimeSource; 4] = [TimeSource::Now, TimeSource::Fixed, TimeSource::CopyFrom, TimeSource::Shift];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeSource::Now => "now",
+            TimeSource::Fixed => "fixed date",
+            TimeSource::CopyFrom => "copy from file",
+            TimeSource::Shift => "shift by offset",
+        }
+    }
+}
+
+pub fn parse_time(text: &str) -> Option<FileTime> {
+    let naive = NaiveDateTime::parse_from_str(text.trim(), TIME_FORMAT).ok()?;
+    let time = Local.from_local_datetime(&naive).earliest()?;
+    Some(FileTime::from_unix_time(time.timestamp(), 0))
+}
+
+pub fn format_time(time: FileTime) -> String {
+    DateTime::from_timestamp(time.unix_seconds(), time.nanoseconds())
+        .map(|t| t.with_timezone(&Local).format(TIME_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+/// Parses offsets like `+1h`, `-2d 30m` or `90s`, units are `w`, `d`, `h`, `m` and `s`.
+pub fn parse_offset(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut any = false;
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        if c.is_whitespace() && number.is_empty() {
+            continue;
+        }
+        let n: i64 = number.parse().ok()?;
+        number.clear();
+        total += match c {
+            'w' => Duration::try_weeks(n)?,
+            'd' => Duration::try_days(n)?,
+            'h' => Duration::try_hours(n)?,
+            'm' => Duration::try_minutes(n)?,
+            's' => Duration::try_seconds(n)?,
+            _ => return None,
+        };
+        any = true;
+    }
+    if !number.is_empty() || !any {
+        return None;
+    }
+    Some(if negative { -total } else { total })
+}
+
+fn shift(time: FileTime, offset: Duration) -> FileTime {
+    FileTime::from_unix_time(time.unix_seconds() + offset.num_seconds(), time.nanoseconds())
+}
+
+/// Maps the current modified and accessed time of an entry to the new ones.
+type Times = dyn Fn(FileTime, FileTime) -> (FileTime, FileTime);
+
+#[derive(Debug)]
+pub struct TimestampEditor {
+    pub paths: Vec<String>,
+    pub source: TimeSource,
+    pub set_modified: bool,
+    pub set_accessed: bool,
+    pub modified: String,
+    pub accessed: String,
+    pub reference: String,
+    pub offset: String,
+    pub recursive: bool,
+    pub errors: Vec<String>,
+}
+
+impl TimestampEditor {
+    pub fn new(paths: Vec<String>) -> Self {
+        let meta = paths.first().and_then(|p| fs::symlink_metadata(p).ok());
+        let modified = meta.as_ref().map(FileTime::from_last_modification_time);
+        let accessed = meta.as_ref().map(FileTime::from_last_access_time);
+        Self {
+            source: TimeSource::Now,
+            set_modified: true,
+            set_accessed: true,
+            modified: modified.map(format_time).unwrap_or_default(),
+            accessed: accessed.map(format_time).unwrap_or_default(),
+            reference: String::new(),
+            offset: "+1h".to_string(),
+            recursive: false,
+            errors: vec![],
+            paths,
+        }
+    }
+
+    /// Checks the inputs of the chosen source, returning a function computing the new times
+    /// from the current ones.
+    fn times(&self) -> Result<Box<Times>, String> {
+        match self.source {
+            TimeSource::Now => {
+                let now = FileTime::now();
+                Ok(Box::new(move |_, _| (now, now)))
+            }
+            TimeSource::Fixed => {
+                let parse = |text: &str| {
+                    parse_time(text).ok_or_else(|| format!("invalid date {}, use {}", text, TIME_FORMAT))
+                };
+                // unchecked fields are kept as they are, so they don't need to parse
+                let modified = if self.set_modified { Some(parse(&self.modified)?) } else { None };
+                let accessed = if self.set_accessed { Some(parse(&self.accessed)?) } else { None };
+                Ok(Box::new(move |m, a| (modified.unwrap_or(m), accessed.unwrap_or(a))))
+            }
+            TimeSource::CopyFrom => {
+                let meta = fs::metadata(self.reference.trim()).map_err(|e| format!("{}: {}", self.reference, e))?;
+                let modified = FileTime::from_last_modification_time(&meta);
+                let accessed = FileTime::from_last_access_time(&meta);
+                Ok(Box::new(move |_, _| (modified, accessed)))
+            }
+            TimeSource::Shift => {
+                let offset = parse_offset(&self.offset).ok_or_else(|| format!("invalid offset {}", self.offset))?;
+                Ok(Box::new(move |m, a| (shift(m, offset), shift(a, offset))))
+            }
+        }
+    }
+
+    fn apply_to(&self, path: &Path, meta: &fs::Metadata, times: &Times) -> io::Result<()> {
+        let old_modified = FileTime::from_last_modification_time(meta);
+        let old_accessed = FileTime::from_last_access_time(meta);
+        let (modified, accessed) = times(old_modified, old_accessed);
+        filetime::set_symlink_file_times(
+            path,
+            if self.set_accessed { accessed } else { old_accessed },
+            if self.set_modified { modified } else { old_modified },
+        )
+    }
+
+    /// Sets the times, links themselves are changed and not their targets.
+    pub fn apply(&mut self) {
+        self.errors.clear();
+        let times = match self.times() {
+            Ok(times) => times,
+            Err(e) => {
+                self.errors.push(e);
+                return;
+            }
+        };
+        let mut errors = vec![];
+        for path in self.paths.iter() {
+            // listing a folder updates its access time, so the times of every entry are read
+            // before its folder is listed and only set once the walk is done
+            let mut entries = vec![];
+            for entry in WalkDir::new(path).max_depth(if self.recursive { usize::MAX } else { 0 }) {
+                match entry.and_then(|e| Ok((e.path().to_path_buf(), e.metadata()?))) {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => errors.push(format!("{}: {}", path, io::Error::from(e))),
+                }
+            }
+            for (entry, meta) in entries {
+                if let Err(e) = self.apply_to(&entry, &meta, &*times) {
+                    errors.push(format!("{}: {}", path, e));
+                }
+            }
+        }
+        self.errors = errors;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn times(path: &Path) -> (i64, i64) {
+        let meta = fs::symlink_metadata(path).unwrap();
+        (
+            FileTime::from_last_modification_time(&meta).unix_seconds(),
+            FileTime::from_last_access_time(&meta).unix_seconds(),
+        )
+    }
+
+    #[test]
+    fn offsets_add_up_their_units() {
+        assert_eq!(parse_offset("+1h"), Some(Duration::hours(1)));
+        assert_eq!(parse_offset("90s"), Some(Duration::seconds(90)));
+        assert_eq!(parse_offset("-2d 30m"), Some(-(Duration::days(2) + Duration::minutes(30))));
+        assert_eq!(parse_offset(" 1w1d "), Some(Duration::days(8)));
+        for invalid in ["", "+", "5", "1x", "h", "1h 5"] {
+            assert_eq!(parse_offset(invalid), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn times_are_formatted_as_they_are_parsed() {
+        let time = parse_time(" 2024-02-29 13:45:10 ").unwrap();
+        assert_eq!(format_time(time), "2024-02-29 13:45:10");
+        assert_eq!(parse_time("2024-02-30 00:00:00"), None);
+        assert_eq!(parse_time("yesterday"), None);
+    }
+
+    #[test]
+    fn fixed_dates_only_touch_the_checked_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        filetime::set_file_times(&file, FileTime::from_unix_time(1000, 0), FileTime::from_unix_time(2000, 0)).unwrap();
+
+        let mut editor = TimestampEditor::new(vec![file.to_str().unwrap().to_string()]);
+        assert_eq!(editor.modified, format_time(FileTime::from_unix_time(2000, 0)));
+        editor.source = TimeSource::Fixed;
+        editor.modified = "2020-01-01 00:00:00".to_string();
+        editor.set_accessed = false;
+        editor.accessed = "not a date".to_string();
+        editor.apply();
+
+        assert!(editor.errors.is_empty(), "{:?}", editor.errors);
+        let expected = parse_time("2020-01-01 00:00:00").unwrap().unix_seconds();
+        assert_eq!(times(&file), (expected, 1000));
+
+        editor.set_accessed = true;
+        editor.apply();
+        assert_eq!(editor.errors, vec!["invalid date not a date, use %Y-%m-%d %H:%M:%S"]);
+    }
+
+    #[test]
+    fn shifting_moves_every_entry_by_the_offset() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("folder");
+        fs::create_dir(&folder).unwrap();
+        fs::write(folder.join("file"), "").unwrap();
+        for path in [&folder, &folder.join("file")] {
+            filetime::set_file_times(path, FileTime::from_unix_time(10_000, 0), FileTime::from_unix_time(20_000, 0))
+                .unwrap();
+        }
+
+        let mut editor = TimestampEditor::new(vec![folder.to_str().unwrap().to_string()]);
+        editor.source = TimeSource::Shift;
+        editor.offset = "-1h 1s".to_string();
+        editor.recursive = true;
+        editor.apply();
+
+        assert!(editor.errors.is_empty(), "{:?}", editor.errors);
+        assert_eq!(times(&folder), (20_000 - 3601, 10_000 - 3601));
+        assert_eq!(times(&folder.join("file")), (20_000 - 3601, 10_000 - 3601));
+
+        editor.offset = "soon".to_string();
+        editor.apply();
+        assert_eq!(editor.errors, vec!["invalid offset soon"]);
+    }
+
+    #[test]
+    fn copying_takes_both_times_of_the_reference() {
+        let dir = tempfile::tempdir().unwrap();
+        let (file, reference) = (dir.path().join("file"), dir.path().join("reference"));
+        fs::write(&file, "").unwrap();
+        fs::write(&reference, "").unwrap();
+        filetime::set_file_times(&reference, FileTime::from_unix_time(3000, 0), FileTime::from_unix_time(4000, 0))
+            .unwrap();
+
+        let mut editor = TimestampEditor::new(vec![file.to_str().unwrap().to_string()]);
+        editor.source = TimeSource::CopyFrom;
+        editor.reference = reference.to_str().unwrap().to_string();
+        editor.apply();
+
+        assert!(editor.errors.is_empty(), "{:?}", editor.errors);
+        assert_eq!(times(&file), (4000, 3000));
+    }
+}
@@ -0,0 +1,540 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    ops::Deref,
+    path::Path,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    archive::{self, ArchiveEntry, ArchiveFormat},
+    files::{self, FileEntry, FileType},
+    zip,
+};
+
+/// A place tabs can list and actions can modify, paths are plain strings
+/// in the provider's own namespace.
+pub trait FileSystem: Send + Sync + fmt::Debug {
+    fn list(&self, path: &str) -> io::Result<Vec<FileEntry>>;
+    fn stat(&self, path: &str) -> io::Result<FileEntry>;
+    fn read(&self, path: &str) -> io::Result<Vec<u8>>;
+    fn write(&self, path: &str, content: &[u8]) -> io::Result<()>;
+    fn rename(&self, from: &str, to: &str) -> io::Result<()>;
+    /// Removes a file, or a folder with everything inside it.
+    fn remove(&self, path: &str) -> io::Result<()>;
+    fn create_dir(&self, path: &str) -> io::Result<()>;
+    fn create_file(&self, path: &str) -> io::Result<()> {
+        self.write(path, &[])
+    }
+    fn is_read_only(&self) -> bool {
+        false
+    }
+    /// Whether paths are real paths on disk.
+    fn is_local(&self) -> bool {
+        false
+    }
+
+    /// Copies a file or folder within this provider, only local ones have links to follow.
+    fn copy(&self, from: &str, to: &str, _follow_links: bool) -> io::Result<()> {
+        let entry = self.stat(from)?;
+        if !entry.file_type.is_dir() {
+            return self.write(to, &self.read(from)?);
+        }
+        self.create_dir(to)?;
+        for child in self.list(from)? {
+            self.copy(&child.path, &join(to, &child.file_name), false)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn join(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+fn read_only_error() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, "this location is read-only")
+}
+
+/// A shared handle to a provider, local files by default.
+#[derive(Debug, Clone)]
+pub struct Vfs(pub Arc<dyn FileSystem>);
+
+impl Default for Vfs {
+    fn default() -> Self {
+        Vfs(Arc::new(LocalFs))
+    }
+}
+
+impl Deref for Vfs {
+    type Target = dyn FileSystem;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+/// Picks the provider responsible for `path`.
+pub fn for_path(path: &str) -> Vfs {
+    match archive::split_path(path) {
+        Some((archive, _)) => Vfs(Arc::new(ArchiveFs::new(archive))),
+        None => Vfs::default(),
+    }
+}
+
+/// Like `for_path`, but an archive itself is a local file rather than the root of its contents.
+pub fn for_entry(path: &str) -> Vfs {
+    match archive::split_path(path) {
+        Some((_, inner)) if inner.is_empty() => Vfs::default(),
+        _ => for_path(path),
+    }
+}
+
+/// Copies or moves between two providers, which may be the same.
+pub fn transfer(
+    from_fs: &Vfs,
+    from: &str,
+    to_fs: &Vfs,
+    to: &str,
+    keep_source: bool,
+    follow_links: bool,
+) -> io::Result<()> {
+    if from_fs.is_local() && to_fs.is_local() {
+        let (from, to) = (Path::new(from), Path::new(to));
+        return if keep_source {
+            files::copy_entry(from, to, follow_links)
+        } else {
+            files::move_entry(from, to, follow_links)
+        };
+    }
+    if Arc::ptr_eq(&from_fs.0, &to_fs.0) {
+        return if keep_source {
+            to_fs.copy(from, to, follow_links)
+        } else {
+            to_fs.rename(from, to)
+        };
+    }
+    let entry = from_fs.stat(from)?;
+    if entry.file_type.is_dir() {
+        to_fs.create_dir(to)?;
+        for child in from_fs.list(from)? {
+            transfer(from_fs, &child.path, to_fs, &join(to, &child.file_name), true, follow_links)?;
+        }
+    } else {
+        to_fs.write(to, &from_fs.read(from)?)?;
+    }
+    if !keep_source {
+        from_fs.remove(from)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct LocalFs;
+
+impl FileSystem for LocalFs {
+    fn list(&self, path: &str) -> io::Result<Vec<FileEntry>> {
+        files::get_entries(path)
+    }
+
+    fn stat(&self, path: &str) -> io::Result<FileEntry> {
+        files::get_meta(path)
+    }
+
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &str, content: &[u8]) -> io::Result<()> {
+        fs::write(path, content)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        files::ensure_free(Path::new(from), Path::new(to))?;
+        fs::rename(from, to)
+    }
+
+    fn remove(&self, path: &str) -> io::Result<()> {
+        if fs::symlink_metadata(path)?.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+    }
+
+    fn create_dir(&self, path: &str) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn create_file(&self, path: &str) -> io::Result<()> {
+        fs::File::create(path).map(|_| ())
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    fn copy(&self, from: &str, to: &str, follow_links: bool) -> io::Result<()> {
+        files::copy_entry(Path::new(from), Path::new(to), follow_links)
+    }
+}
+
+/// The contents of a zip or tar archive. Zip archives can be modified, every change
+/// rewrites the archive.
+#[derive(Debug)]
+pub struct ArchiveFs {
+    archive: String,
+    listing: Mutex<Option<Arc<Vec<ArchiveEntry>>>>,
+}
+
+impl ArchiveFs {
+    pub fn new(archive: String) -> Self {
+        Self {
+            archive,
+            listing: Mutex::new(None),
+        }
+    }
+
+    fn listing(&self) -> io::Result<Arc<Vec<ArchiveEntry>>> {
+        let mut listing = self.listing.lock().unwrap();
+        if listing.is_none() {
+            *listing = Some(Arc::new(archive::list(&self.archive)?));
+        }
+        Ok(listing.as_ref().unwrap().clone())
+    }
+
+    fn inner<'a>(&self, path: &'a str) -> io::Result<&'a str> {
+        let inner = path
+            .strip_prefix(self.archive.as_str())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "path is not inside the archive"))?;
+        Ok(inner.trim_matches('/'))
+    }
+
+    /// Runs a modification of a zip archive and forgets the now stale listing.
+    fn modify(&self, f: impl FnOnce(&Path) -> anyhow::Result<()>) -> io::Result<()> {
+        if self.is_read_only() {
+            return Err(read_only_error());
+        }
+        let result = f(Path::new(&self.archive)).map_err(|e| io::Error::other(e.to_string()));
+        *self.listing.lock().unwrap() = None;
+        result
+    }
+}
+
+impl FileSystem for ArchiveFs {
+    fn list(&self, path: &str) -> io::Result<Vec<FileEntry>> {
+        Ok(archive::children(&self.archive, &self.listing()?, self.inner(path)?))
+    }
+
+    fn stat(&self, path: &str) -> io::Result<FileEntry> {
+        archive::stat(&self.archive, &self.listing()?, self.inner(path)?)
+    }
+
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        archive::read_entry(&self.archive, self.inner(path)?)
+    }
+
+    fn write(&self, path: &str, content: &[u8]) -> io::Result<()> {
+        let inner = self.inner(path)?;
+        self.modify(|archive| zip::write_entry(archive, inner, content))
+    }
+
+    fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let (from, to) = (self.inner(from)?, self.inner(to)?);
+        self.modify(|archive| zip::rename_entry(archive, from, to))
+    }
+
+    fn remove(&self, path: &str) -> io::Result<()> {
+        let inner = self.inner(path)?.to_string();
+        self.modify(|archive| zip::remove_entries(archive, &[inner]))
+    }
+
+    fn create_dir(&self, path: &str) -> io::Result<()> {
+        let inner = self.inner(path)?;
+        self.modify(|archive| zip::add_directory(archive, inner))
+    }
+
+    fn is_read_only(&self) -> bool {
+        ArchiveFormat::of(&self.archive) != Some(ArchiveFormat::Zip)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MemoryNode {
+    is_dir: bool,
+    content: Vec<u8>,
+    modified: DateTime<Utc>,
+}
+
+/// Keeps everything in memory, rooted at `/`. Meant for tests and scratch locations.
+#[derive(Debug)]
+pub struct MemoryFs {
+    nodes: Mutex<BTreeMap<String, MemoryNode>>,
+}
+
+impl Default for MemoryFs {
+    fn default() -> Self {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(
+            "/".to_string(),
+            MemoryNode {
+                is_dir: true,
+                content: vec![],
+                modified: Utc::now(),
+            },
+        );
+        Self {
+            nodes: Mutex::new(nodes),
+        }
+    }
+}
+
+fn normalize(path: &str) -> String {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        "/".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn parent(path: &str) -> String {
+    normalize(&path[..path.rfind('/').unwrap_or(0)])
+}
+
+fn not_found(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path))
+}
+
+impl MemoryFs {
+    fn to_entry(path: &str, node: &MemoryNode) -> FileEntry {
+        FileEntry {
+            len: node.content.len() as u64,
+            file_type: if node.is_dir { FileType::Dir } else { FileType::File },
+            created: node.modified,
+            modified: node.modified,
+            accessed: node.modified,
+            path: path.to_string(),
+            file_name: path.rsplit('/').next().unwrap_or_default().to_string(),
+            symlink_target: None,
+            ignored: false,
+            archive: None,
+            mime: OnceLock::new(),
+            sniffed_mime: OnceLock::new(),
+        }
+    }
+
+    fn insert(&self, path: &str, node: MemoryNode, overwrite: bool) -> io::Result<()> {
+        let path = normalize(path);
+        let mut nodes = self.nodes.lock().unwrap();
+        match nodes.get(&parent(&path)) {
+            Some(p) if p.is_dir => {}
+            _ => return Err(not_found(&parent(&path))),
+        }
+        match nodes.get(&path) {
+            Some(existing) if existing.is_dir || !overwrite => {
+                return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} exists", path)));
+            }
+            _ => {}
+        }
+        nodes.insert(path, node);
+        Ok(())
+    }
+
+    /// The path itself and everything below it.
+    fn subtree(nodes: &BTreeMap<String, MemoryNode>, path: &str) -> Vec<String> {
+        let prefix = format!("{}/", path);
+        nodes
+            .keys()
+            .filter(|k| *k == path || k.starts_with(&prefix))
+            .cloned()
+            .collect()
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn list(&self, path: &str) -> io::Result<Vec<FileEntry>> {
+        let path = normalize(path);
+        let nodes = self.nodes.lock().unwrap();
+        match nodes.get(&path) {
+            Some(node) if node.is_dir => {}
+            _ => return Err(not_found(&path)),
+        }
+        Ok(nodes
+            .iter()
+            .filter(|(p, _)| **p != path && parent(p) == path)
+            .map(|(p, node)| Self::to_entry(p, node))
+            .collect())
+    }
+
+    fn stat(&self, path: &str) -> io::Result<FileEntry> {
+        let path = normalize(path);
+        let nodes = self.nodes.lock().unwrap();
+        let node = nodes.get(&path).ok_or_else(|| not_found(&path))?;
+        Ok(Self::to_entry(&path, node))
+    }
+
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let nodes = self.nodes.lock().unwrap();
+        match nodes.get(&normalize(path)) {
+            Some(node) if !node.is_dir => Ok(node.content.clone()),
+            _ => Err(not_found(path)),
+        }
+    }
+
+    fn write(&self, path: &str, content: &[u8]) -> io::Result<()> {
+        let node = MemoryNode {
+            is_dir: false,
+            content: content.to_vec(),
+            modified: Utc::now(),
+        };
+        self.insert(path, node, true)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let (from, to) = (normalize(from), normalize(to));
+        let mut nodes = self.nodes.lock().unwrap();
+        if !nodes.contains_key(&from) {
+            return Err(not_found(&from));
+        }
+        if !nodes.get(&parent(&to)).is_some_and(|p| p.is_dir) {
+            return Err(not_found(&parent(&to)));
+        }
+        if nodes.contains_key(&to) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} exists", to)));
+        }
+        if to.starts_with(&format!("{}/", from)) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is inside {}", to, from)));
+        }
+        for old in Self::subtree(&nodes, &from) {
+            let node = nodes.remove(&old).unwrap();
+            nodes.insert(format!("{}{}", to, &old[from.len()..]), node);
+        }
+        Ok(())
+    }
+
+    fn remove(&self, path: &str) -> io::Result<()> {
+        let path = normalize(path);
+        let mut nodes = self.nodes.lock().unwrap();
+        if path == "/" || !nodes.contains_key(&path) {
+            return Err(not_found(&path));
+        }
+        for p in Self::subtree(&nodes, &path) {
+            nodes.remove(&p);
+        }
+        Ok(())
+    }
+
+    fn create_dir(&self, path: &str) -> io::Result<()> {
+        let node = MemoryNode {
+            is_dir: true,
+            content: vec![],
+            modified: Utc::now(),
+        };
+        self.insert(path, node, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(fs: &dyn FileSystem, path: &str) -> Vec<String> {
+        let mut names: Vec<_> = fs.list(path).unwrap().into_iter().map(|e| e.file_name).collect();
+        names.sort();
+        names
+    }
+
+    fn memory() -> Vfs {
+        let fs = MemoryFs::default();
+        fs.create_dir("/docs").unwrap();
+        fs.write("/docs/a.txt", b"a").unwrap();
+        fs.create_dir("/docs/sub").unwrap();
+        fs.write("/docs/sub/b.txt", b"b").unwrap();
+        fs.write("/c.txt", b"c").unwrap();
+        Vfs(Arc::new(fs))
+    }
+
+    #[test]
+    fn memory_lists_direct_children() {
+        let fs = memory();
+        assert_eq!(names(&*fs, "/"), ["c.txt", "docs"]);
+        assert_eq!(names(&*fs, "/docs/"), ["a.txt", "sub"]);
+        assert!(fs.list("/c.txt").is_err());
+        assert!(fs.list("/missing").is_err());
+    }
+
+    #[test]
+    fn memory_rename_moves_folders_but_never_overwrites() {
+        let fs = memory();
+        fs.rename("/docs", "/papers").unwrap();
+        assert_eq!(names(&*fs, "/"), ["c.txt", "papers"]);
+        assert_eq!(fs.read("/papers/sub/b.txt").unwrap(), b"b");
+
+        let err = fs.rename("/c.txt", "/papers/a.txt").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs.read("/papers/a.txt").unwrap(), b"a");
+        assert!(fs.rename("/papers", "/papers/sub/papers").is_err());
+        assert!(fs.rename("/missing", "/other").is_err());
+    }
+
+    #[test]
+    fn memory_remove_takes_everything_below() {
+        let fs = memory();
+        fs.remove("/docs").unwrap();
+        assert_eq!(names(&*fs, "/"), ["c.txt"]);
+        assert!(fs.stat("/docs/sub/b.txt").is_err());
+        assert!(fs.remove("/docs").is_err());
+        assert!(fs.remove("/").is_err());
+    }
+
+    #[test]
+    fn transfer_copies_and_moves_between_providers() {
+        let (from, to) = (memory(), Vfs(Arc::new(MemoryFs::default())));
+        transfer(&from, "/docs", &to, "/copy", true, false).unwrap();
+        assert_eq!(to.read("/copy/sub/b.txt").unwrap(), b"b");
+        assert!(from.stat("/docs").is_ok());
+
+        transfer(&from, "/c.txt", &to, "/c.txt", false, false).unwrap();
+        assert_eq!(to.read("/c.txt").unwrap(), b"c");
+        assert!(from.stat("/c.txt").is_err());
+    }
+
+    #[test]
+    fn transfer_within_one_provider_renames() {
+        let fs = memory();
+        transfer(&fs, "/docs/a.txt", &fs, "/a.txt", false, false).unwrap();
+        assert_eq!(names(&*fs, "/"), ["a.txt", "c.txt", "docs"]);
+        transfer(&fs, "/a.txt", &fs, "/docs/a.txt", true, false).unwrap();
+        assert_eq!(fs.read("/docs/a.txt").unwrap(), b"a");
+        assert!(fs.stat("/a.txt").is_ok());
+    }
+
+    #[test]
+    fn transfer_between_local_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        fs::write(&from, "content").unwrap();
+        let local = Vfs::default();
+        transfer(&local, from.to_str().unwrap(), &local, to.to_str().unwrap(), false, false).unwrap();
+        assert!(!from.exists());
+        assert_eq!(fs::read_to_string(&to).unwrap(), "content");
+    }
+
+    #[test]
+    fn local_rename_never_overwrites() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        fs::write(&from, "from").unwrap();
+        fs::write(&to, "to").unwrap();
+        let local = Vfs::default();
+        let err = local.rename(from.to_str().unwrap(), to.to_str().unwrap()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        let err = transfer(&local, from.to_str().unwrap(), &local, to.to_str().unwrap(), false, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&to).unwrap(), "to");
+        assert!(from.exists());
+    }
+}
@@ -1,62 +1,336 @@
 use anyhow::Context;
+use chrono::{Datelike, Local, Timelike};
 use std::io::prelude::*;
-use zip::{result::ZipError, write::SimpleFileOptions};
+use std::ops::RangeInclusive;
+use std::os::unix::fs::PermissionsExt;
+use zip::{
+    write::{FileOptions, SimpleFileOptions},
+    AesMode, CompressionMethod, ZipArchive, ZipWriter,
+};
 
-use std::fs::File;
+use std::fs::{File, Metadata};
+use std::io::{BufReader, BufWriter};
 use std::path::{Path, PathBuf};
-use walkdir::{DirEntry, WalkDir};
+use ignore::DirEntry;
 
-fn zip_dir_core<T>(
-    it: &mut dyn Iterator<Item = DirEntry>,
-    prefix: &Path,
-    writer: T,
-    method: zip::CompressionMethod,
+use crate::files::{walk_sources, GlobFilter, ListingFilter};
+use crate::job::Progress;
+
+/// Methods the zip crate can write, Lzma and Xz are read-only there.
+pub const METHODS: [CompressionMethod; 4] = [
+    CompressionMethod::Stored,
+    CompressionMethod::Deflated,
+    CompressionMethod::Bzip2,
+    CompressionMethod::Zstd,
+];
+
+/// The levels a method accepts, `None` for methods without levels.
+pub fn level_range(method: CompressionMethod) -> Option<RangeInclusive<i64>> {
+    match method {
+        CompressionMethod::Deflated | CompressionMethod::Bzip2 => Some(1..=9),
+        CompressionMethod::Zstd => Some(1..=22),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ZipOptions {
+    pub method: CompressionMethod,
+    /// `None` uses the default level of the method.
+    pub level: Option<i64>,
+    /// Encrypts every file with AES-256 when not empty.
+    pub password: String,
+    pub comment: String,
+}
+
+impl Default for ZipOptions {
+    fn default() -> Self {
+        Self {
+            method: CompressionMethod::Deflated,
+            level: None,
+            password: String::new(),
+            comment: String::new(),
+        }
+    }
+}
+
+fn zip_time(meta: &Metadata) -> Option<zip::DateTime> {
+    let t: chrono::DateTime<Local> = meta.modified().ok()?.into();
+    zip::DateTime::from_date_and_time(
+        t.year().try_into().ok()?,
+        t.month() as u8,
+        t.day() as u8,
+        t.hour() as u8,
+        t.minute() as u8,
+        // zip stores seconds with a resolution of two
+        (t.second() / 2 * 2) as u8,
+    )
+    .ok()
+}
+
+/// Adds walked entries to `zip`, their names prefixed with `prefix` (a folder inside the archive or "").
+fn add_entries<T>(
+    zip: &mut ZipWriter<T>,
+    entries: &[(DirEntry, PathBuf)],
+    base: FileOptions<()>,
+    prefix: &str,
+    follow_links: bool,
+    progress: &Progress,
 ) -> anyhow::Result<()>
 where
     T: Write + Seek,
 {
-    let mut zip = zip::ZipWriter::new(writer);
-    let options = SimpleFileOptions::default()
-        .compression_method(method)
-        .unix_permissions(0o755);
-
-    let prefix = Path::new(prefix);
-    let mut buffer = Vec::new();
-    for entry in it {
+    progress.set_total(entries.len() as u64);
+    for (entry, name) in entries {
+        if progress.is_cancelled() {
+            anyhow::bail!("cancelled");
+        }
         let path = entry.path();
-        let name = path.strip_prefix(prefix).unwrap();
+        let name = Path::new(prefix).join(name);
         let path_as_string = name
             .to_str()
             .map(str::to_owned)
             .with_context(|| format!("{name:?} Is a Non UTF-8 Path"))?;
+        progress.set_message(&path_as_string);
 
-        if path.is_file() {
-            zip.start_file(path_as_string, options)?;
-            let mut f = File::open(path)?;
+        let is_link = entry.path_is_symlink() && !follow_links;
+        let meta = if is_link {
+            std::fs::symlink_metadata(path)?
+        } else {
+            std::fs::metadata(path)?
+        };
+        let mut options = base.unix_permissions(meta.permissions().mode() & 0o7777);
+        if let Some(time) = zip_time(&meta) {
+            options = options.last_modified_time(time);
+        }
 
-            f.read_to_end(&mut buffer)?;
-            zip.write_all(&buffer)?;
-            buffer.clear();
-        } else if !name.as_os_str().is_empty() {
+        if is_link {
+            let target = std::fs::read_link(path)?;
+            zip.add_symlink(path_as_string, target.to_string_lossy(), options)?;
+        } else if meta.is_file() {
+            zip.start_file(path_as_string, options)?;
+            std::io::copy(&mut File::open(path)?, zip)?;
+        } else {
             zip.add_directory(path_as_string, options)?;
         }
+        progress.add(1);
+    }
+    Ok(())
+}
+
+fn zip_dir_core<T>(
+    entries: &[(DirEntry, PathBuf)],
+    writer: T,
+    options: &ZipOptions,
+    follow_links: bool,
+    progress: &Progress,
+) -> anyhow::Result<()>
+where
+    T: Write + Seek,
+{
+    let mut zip = ZipWriter::new(writer);
+    let mut base = FileOptions::<()>::default()
+        .compression_method(options.method)
+        .compression_level(options.level)
+        .large_file(true);
+    if !options.password.is_empty() {
+        base = base.with_aes_encryption(AesMode::Aes256, &options.password);
+    }
+    if !options.comment.is_empty() {
+        zip.set_comment(options.comment.as_str());
     }
-    zip.finish()?;
+    add_entries(&mut zip, entries, base, "", follow_links, progress)?;
+    zip.finish()?.flush()?;
     Ok(())
 }
 
-pub fn zip_dir(src_dir: &Path, dst_file: &Path, method: zip::CompressionMethod) -> anyhow::Result<()> {
-    if !Path::new(src_dir).is_dir() {
-        return Err(ZipError::FileNotFound.into());
+/// Writes the walked `entries` into a new zip file.
+pub fn zip_entries(
+    entries: &[(DirEntry, PathBuf)],
+    dst_file: &Path,
+    options: &ZipOptions,
+    follow_links: bool,
+    progress: &Progress,
+) -> anyhow::Result<()> {
+    let file = BufWriter::new(File::create(dst_file)?);
+    zip_dir_core(entries, file, options, follow_links, progress)
+}
+
+/// Rewrites `archive` into a temp file next to it and then atomically replaces it.
+/// `rename` gets every existing entry name and returns its new name, or `None` to drop the entry.
+/// `add` can append new entries at the end.
+fn rewrite(
+    archive: &Path,
+    rename: impl Fn(&str) -> Option<String>,
+    add: impl FnOnce(&mut ZipWriter<BufWriter<File>>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut source = ZipArchive::new(BufReader::new(File::open(archive)?))?;
+    let file_name = archive.file_name().unwrap_or_default().to_string_lossy();
+    let temp = archive.with_file_name(format!(".{}.tmp", file_name));
+    let result = (|| {
+        let mut zip = ZipWriter::new(BufWriter::new(File::create(&temp)?));
+        zip.set_raw_comment(source.comment().into());
+        for i in 0..source.len() {
+            let file = source.by_index_raw(i)?;
+            let name = file.name().to_string();
+            match rename(&name) {
+                Some(new_name) if new_name == name => zip.raw_copy_file(file)?,
+                Some(new_name) => zip.raw_copy_file_rename(file, new_name)?,
+                None => {}
+            }
+        }
+        add(&mut zip)?;
+        let file = zip.finish()?.into_inner()?;
+        file.set_permissions(std::fs::metadata(archive)?.permissions())?;
+        file.sync_all()?;
+        Ok(())
+    })();
+    match result {
+        Ok(()) => Ok(std::fs::rename(&temp, archive)?),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp);
+            Err(e)
+        }
     }
+}
 
-    let path = Path::new(dst_file);
-    let file = File::create(path).unwrap();
+/// Whether the zip entry `name` is `inner` or inside of it.
+fn is_within(name: &str, inner: &str) -> bool {
+    let name = name.trim_end_matches('/');
+    name == inner || name.starts_with(&format!("{}/", inner))
+}
 
-    let walkdir = WalkDir::new(src_dir);
-    let it = walkdir.into_iter();
+/// Adds files and folders from disk into the folder `inner_dir` of an existing archive,
+/// replacing entries with the same name.
+pub fn add_paths(
+    archive: &Path,
+    inner_dir: &str,
+    sources: &[PathBuf],
+    follow_links: bool,
+    filter: ListingFilter,
+    progress: &Progress,
+) -> anyhow::Result<()> {
+    let exclude = GlobFilter::new("", "")?;
+    let mut entries = vec![];
+    for source in sources {
+        let base = source.parent().unwrap_or(source);
+        entries.extend(walk_sources(base, std::slice::from_ref(source), follow_links, filter, &exclude));
+    }
+    let added: Vec<String> = entries
+        .iter()
+        .filter_map(|(_, name)| Some(Path::new(inner_dir).join(name).to_str()?.to_string()))
+        .collect();
+    let options = FileOptions::<()>::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    rewrite(
+        archive,
+        |name| (!added.iter().any(|a| a == name.trim_end_matches('/'))).then(|| name.to_string()),
+        |zip| add_entries(zip, &entries, options, inner_dir, follow_links, progress),
+    )
+}
 
-    zip_dir_core(&mut it.filter_map(|e| e.ok()), src_dir, file, method)?;
+/// Removes entries, folders with everything inside them.
+pub fn remove_entries(archive: &Path, inner: &[String]) -> anyhow::Result<()> {
+    rewrite(
+        archive,
+        |name| (!inner.iter().any(|i| is_within(name, i))).then(|| name.to_string()),
+        |_| Ok(()),
+    )
+}
 
-    Ok(())
-}
\ No newline at end of file
+/// Renames or moves an entry, for folders everything inside them moves along.
+pub fn rename_entry(archive: &Path, from: &str, to: &str) -> anyhow::Result<()> {
+    rewrite(
+        archive,
+        |name| match is_within(name, from) {
+            true => Some(format!("{}{}", to, &name[from.len()..])),
+            false => Some(name.to_string()),
+        },
+        |_| Ok(()),
+    )
+}
+
+/// Adds or replaces a single file with `content`.
+pub fn write_entry(archive: &Path, inner: &str, content: &[u8]) -> anyhow::Result<()> {
+    rewrite(
+        archive,
+        |name| (name != inner).then(|| name.to_string()),
+        |zip| {
+            zip.start_file(inner, SimpleFileOptions::default().unix_permissions(0o644))?;
+            zip.write_all(content)?;
+            Ok(())
+        },
+    )
+}
+
+pub fn add_directory(archive: &Path, inner: &str) -> anyhow::Result<()> {
+    rewrite(
+        archive,
+        |name| Some(name.to_string()),
+        |zip| {
+            zip.add_directory(inner, SimpleFileOptions::default().unix_permissions(0o755))?;
+            Ok(())
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create(path: &Path, names: &[&str]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for name in names {
+            if name.ends_with('/') {
+                zip.add_directory(*name, SimpleFileOptions::default()).unwrap();
+            } else {
+                zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+                zip.write_all(name.as_bytes()).unwrap();
+            }
+        }
+        zip.finish().unwrap();
+    }
+
+    fn names(path: &Path) -> Vec<String> {
+        let zip = ZipArchive::new(File::open(path).unwrap()).unwrap();
+        let mut names: Vec<_> = zip.file_names().map(|n| n.to_string()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn is_within_matches_whole_path_components() {
+        assert!(is_within("docs", "docs"));
+        assert!(is_within("docs/", "docs"));
+        assert!(is_within("docs/a/b.txt", "docs"));
+        assert!(!is_within("docs2/a.txt", "docs"));
+        assert!(!is_within("doc", "docs"));
+    }
+
+    #[test]
+    fn rename_entry_moves_folders_with_their_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("test.zip");
+        create(&archive, &["docs/", "docs/a.txt", "docs2/b.txt", "c.txt"]);
+
+        rename_entry(&archive, "docs", "papers").unwrap();
+        assert_eq!(names(&archive), ["c.txt", "docs2/b.txt", "papers/", "papers/a.txt"]);
+
+        rename_entry(&archive, "c.txt", "papers/c.txt").unwrap();
+        assert_eq!(names(&archive), ["docs2/b.txt", "papers/", "papers/a.txt", "papers/c.txt"]);
+        let mut zip = ZipArchive::new(File::open(&archive).unwrap()).unwrap();
+        let mut content = String::new();
+        zip.by_name("papers/c.txt").unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "c.txt");
+    }
+
+    #[test]
+    fn remove_entries_removes_folders_with_their_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("test.zip");
+        create(&archive, &["docs/", "docs/a.txt", "docs2/b.txt"]);
+        remove_entries(&archive, &["docs".to_string()]).unwrap();
+        assert_eq!(names(&archive), ["docs2/b.txt"]);
+    }
+}